@@ -0,0 +1,571 @@
+use std::collections::HashMap;
+
+use crate::graphics::{render_state::RenderState, shaders::UniformValue, texture::{TextureFormat, TextureOptions}};
+use super::{
+	Attachment,
+	BufferHandle,
+	BufferTarget,
+	BufferUsage,
+	Capabilities,
+	CubeFace,
+	FramebufferHandle,
+	IndexType,
+	Primitive,
+	ProgramHandle,
+	RenderBackend,
+	RenderbufferHandle,
+	ShaderHandle,
+	ShaderStage,
+	TextureHandle,
+	TextureTarget,
+	UniformHandle,
+	VertexComponent
+};
+
+/// A single call made against the headless backend
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+	AllocateTexture { width: u32, height: u32, format: TextureFormat },
+	BindFramebuffer { framebuffer: Option<FramebufferHandle> },
+	BindBuffer { target: BufferTarget, buffer: Option<BufferHandle> },
+	BindTexture { unit: u32, target: TextureTarget, texture: Option<TextureHandle> },
+	BufferDataF32 { target: BufferTarget, data: Vec<f32>, usage: BufferUsage },
+	BufferDataU8 { target: BufferTarget, data: Vec<u8>, usage: BufferUsage },
+	BufferDataU16 { target: BufferTarget, data: Vec<u16>, usage: BufferUsage },
+	BufferDataU32 { target: BufferTarget, data: Vec<u32>, usage: BufferUsage },
+	Clear { color: bool, depth: bool },
+	ClearColor { red: f32, green: f32, blue: f32, alpha: f32 },
+	CompileShader { shader: ShaderHandle, stage: ShaderStage },
+	CreateBuffer { buffer: BufferHandle },
+	CreateFramebuffer { framebuffer: FramebufferHandle },
+	CreateRenderbuffer { renderbuffer: RenderbufferHandle },
+	CreateTexture { texture: TextureHandle },
+	DeleteBuffer { buffer: BufferHandle },
+	DeleteFramebuffer { framebuffer: FramebufferHandle },
+	DeleteProgram { program: ProgramHandle },
+	DeleteRenderbuffer { renderbuffer: RenderbufferHandle },
+	DeleteShader { shader: ShaderHandle },
+	DeleteTexture { texture: TextureHandle },
+	DepthRenderbufferStorage { renderbuffer: RenderbufferHandle, width: u32, height: u32 },
+	DisableVertexAttribArray { index: u32 },
+	DrawArrays { primitive: Primitive, first: i32, count: i32 },
+	DrawElements { primitive: Primitive, count: i32, index_type: IndexType, offset: i32 },
+	DrawElementsInstanced {
+		primitive: Primitive,
+		count: i32,
+		index_type: IndexType,
+		offset: i32,
+		instance_count: i32
+	},
+	EnableVertexAttribArray { index: u32 },
+	FramebufferRenderbuffer { renderbuffer: Option<RenderbufferHandle> },
+	FramebufferTexture { attachment: Attachment, texture: Option<TextureHandle> },
+	GenerateMipmap { target: TextureTarget },
+	LinkProgram {
+		program: ProgramHandle,
+		vertex_shader: ShaderHandle,
+		fragment_shader: ShaderHandle,
+		attributes: Vec<(u32, String)>
+	},
+	ResizeDrawingBuffer { width: u32, height: u32 },
+	SetRenderState { state: RenderState },
+	SetTextureOptions { target: TextureTarget, options: TextureOptions },
+	SetUniform { location: UniformHandle, value: UniformValue },
+	TexImage2D { width: u32, height: u32, pixels: Vec<u8> },
+	TexImageCubeFace { face: CubeFace, width: u32, height: u32, pixels: Vec<u8> },
+	UseProgram { program: Option<ProgramHandle> },
+	VertexAttribPointer {
+		index: u32,
+		size: i32,
+		component: VertexComponent,
+		normalized: bool,
+		stride: i32,
+		offset: i32
+	},
+	VertexAttribDivisor { index: u32, divisor: u32 },
+	Viewport { x: u32, y: u32, width: u32, height: u32 }
+}
+
+/// A backend that draws nothing and records every command it is given
+///
+/// Shaders compile unless they hit an #error directive, programs always link,
+/// framebuffers are always complete, and a uniform is found whenever the
+/// program's source declares it, which is close enough to what a driver does
+/// for checking the renderer natively.
+pub struct HeadlessBackend {
+	/// What to pretend the graphics API can do
+	capabilities: Capabilities,
+	/// Every command issued so far, in order
+	commands: Vec<Command>,
+	/// Size of the pretend drawing buffer
+	height: u32,
+	/// The next handle to give out
+	next_handle: u32,
+	/// Source code of each linked program, used to resolve uniforms
+	program_sources: HashMap<ProgramHandle, String>,
+	/// Source code of each compiled shader
+	shader_sources: HashMap<ShaderHandle, String>,
+	/// Names of uniforms that have been located, by handle
+	uniform_names: HashMap<UniformHandle, String>,
+	/// Size of the pretend drawing buffer
+	width: u32
+}
+
+impl HeadlessBackend {
+	/// Clears the recorded command list
+	pub fn clear_commands(&mut self) {
+		self.commands.clear();
+	}
+
+	/// Gets every command recorded so far
+	pub fn commands(&self) -> &[Command] {
+		&self.commands
+	}
+
+	/// Gets just the draw commands recorded so far
+	pub fn draw_calls(&self) -> Vec<&Command> {
+		self.commands.iter()
+			.filter(|command| matches!(
+				command,
+				Command::DrawArrays {..} | Command::DrawElements {..} | Command::DrawElementsInstanced {..}
+			))
+			.collect()
+	}
+
+	/// Creates a new headless backend
+	///
+	/// * `width` - the width of the pretend drawing buffer
+	/// * `height` - the height of the pretend drawing buffer
+	pub fn new(width: u32, height: u32) -> HeadlessBackend {
+		// Plain WebGL1 with the widely supported 32 bit index extension, and
+		// the limits nearly every device has
+		let capabilities = Capabilities {
+			max_texture_size: 4096,
+			max_vertex_attribs: 16,
+			uint_indices: true,
+			..Capabilities::default()
+		};
+
+		HeadlessBackend {
+			capabilities,
+			commands: Vec::new(),
+			height,
+			next_handle: 1,
+			program_sources: HashMap::new(),
+			shader_sources: HashMap::new(),
+			uniform_names: HashMap::new(),
+			width
+		}
+	}
+
+	/// Sets what to pretend the graphics API can do
+	///
+	/// Starts out as WebGL1 with 32 bit indices, 4096 pixel textures, and 16
+	/// vertex attributes.
+	pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+		self.capabilities = capabilities;
+	}
+
+	/// Gets the name a uniform handle was located with
+	pub fn uniform_name(&self, location: UniformHandle) -> Option<&str> {
+		self.uniform_names.get(&location).map(|name| name.as_str())
+	}
+
+	/// Hands out a fresh handle id
+	fn allocate(&mut self) -> u32 {
+		let handle = self.next_handle;
+		self.next_handle += 1;
+
+		handle
+	}
+}
+
+impl RenderBackend for HeadlessBackend {
+	fn allocate_texture(&mut self, width: u32, height: u32, format: TextureFormat) {
+		self.commands.push(Command::AllocateTexture { width, height, format });
+	}
+
+	fn bind_buffer(&mut self, target: BufferTarget, buffer: Option<BufferHandle>) {
+		self.commands.push(Command::BindBuffer { target, buffer });
+	}
+
+	fn bind_framebuffer(&mut self, framebuffer: Option<FramebufferHandle>) {
+		self.commands.push(Command::BindFramebuffer { framebuffer });
+	}
+
+	fn bind_texture(&mut self, unit: u32, target: TextureTarget, texture: Option<TextureHandle>) {
+		self.commands.push(Command::BindTexture { unit, target, texture });
+	}
+
+	fn buffer_data_f32(&mut self, target: BufferTarget, data: &[f32], usage: BufferUsage) {
+		self.commands.push(Command::BufferDataF32 { target, data: data.to_vec(), usage });
+	}
+
+	fn buffer_data_u8(&mut self, target: BufferTarget, data: &[u8], usage: BufferUsage) {
+		self.commands.push(Command::BufferDataU8 { target, data: data.to_vec(), usage });
+	}
+
+	fn buffer_data_u16(&mut self, target: BufferTarget, data: &[u16], usage: BufferUsage) {
+		self.commands.push(Command::BufferDataU16 { target, data: data.to_vec(), usage });
+	}
+
+	fn buffer_data_u32(&mut self, target: BufferTarget, data: &[u32], usage: BufferUsage) {
+		self.commands.push(Command::BufferDataU32 { target, data: data.to_vec(), usage });
+	}
+
+	fn capabilities(&self) -> &Capabilities {
+		&self.capabilities
+	}
+
+	fn check_framebuffer_status(&mut self) -> Result<(), String> {
+		Ok(())
+	}
+
+	fn clear(&mut self, color: bool, depth: bool) {
+		self.commands.push(Command::Clear { color, depth });
+	}
+
+	fn clear_color(&mut self, red: f32, green: f32, blue: f32, alpha: f32) {
+		self.commands.push(Command::ClearColor { red, green, blue, alpha });
+	}
+
+	fn compile_shader(&mut self, stage: ShaderStage, source: &str) -> Result<ShaderHandle, String> {
+		// Fail like a driver would on an #error directive, counting lines the
+		// way #line directives say to
+		let (mut string_number, mut line_number) = (0, 1);
+		for line in source.lines() {
+			let directive = line.trim_start();
+			if let Some(numbers) = directive.strip_prefix("#line") {
+				let mut numbers = numbers.split("//").next().unwrap_or("").split_whitespace();
+				if let Some(Ok(number)) = numbers.next().map(str::parse) {
+					line_number = number;
+				}
+				if let Some(Ok(number)) = numbers.next().map(str::parse) {
+					string_number = number;
+				}
+				continue;
+			}
+			if let Some(message) = directive.strip_prefix("#error") {
+				return Err(format!("ERROR: {}:{}: '#error' : {}", string_number, line_number, message.trim()));
+			}
+			line_number += 1;
+		}
+
+		let shader = ShaderHandle(self.allocate());
+		self.shader_sources.insert(shader, String::from(source));
+		self.commands.push(Command::CompileShader { shader, stage });
+
+		Ok(shader)
+	}
+
+	fn create_buffer(&mut self) -> Option<BufferHandle> {
+		let buffer = BufferHandle(self.allocate());
+		self.commands.push(Command::CreateBuffer { buffer });
+
+		Some(buffer)
+	}
+
+	fn create_framebuffer(&mut self) -> Option<FramebufferHandle> {
+		let framebuffer = FramebufferHandle(self.allocate());
+		self.commands.push(Command::CreateFramebuffer { framebuffer });
+
+		Some(framebuffer)
+	}
+
+	fn create_renderbuffer(&mut self) -> Option<RenderbufferHandle> {
+		let renderbuffer = RenderbufferHandle(self.allocate());
+		self.commands.push(Command::CreateRenderbuffer { renderbuffer });
+
+		Some(renderbuffer)
+	}
+
+	fn create_texture(&mut self) -> Option<TextureHandle> {
+		let texture = TextureHandle(self.allocate());
+		self.commands.push(Command::CreateTexture { texture });
+
+		Some(texture)
+	}
+
+	fn delete_buffer(&mut self, buffer: BufferHandle) {
+		self.commands.push(Command::DeleteBuffer { buffer });
+	}
+
+	fn delete_framebuffer(&mut self, framebuffer: FramebufferHandle) {
+		self.commands.push(Command::DeleteFramebuffer { framebuffer });
+	}
+
+	fn delete_program(&mut self, program: ProgramHandle) {
+		self.program_sources.remove(&program);
+		self.commands.push(Command::DeleteProgram { program });
+	}
+
+	fn delete_renderbuffer(&mut self, renderbuffer: RenderbufferHandle) {
+		self.commands.push(Command::DeleteRenderbuffer { renderbuffer });
+	}
+
+	fn delete_shader(&mut self, shader: ShaderHandle) {
+		self.shader_sources.remove(&shader);
+		self.commands.push(Command::DeleteShader { shader });
+	}
+
+	fn delete_texture(&mut self, texture: TextureHandle) {
+		self.commands.push(Command::DeleteTexture { texture });
+	}
+
+	fn depth_renderbuffer_storage(&mut self, renderbuffer: RenderbufferHandle, width: u32, height: u32) {
+		self.commands.push(Command::DepthRenderbufferStorage { renderbuffer, width, height });
+	}
+
+	fn disable_vertex_attrib_array(&mut self, index: u32) {
+		self.commands.push(Command::DisableVertexAttribArray { index });
+	}
+
+	fn draw_arrays(&mut self, primitive: Primitive, first: i32, count: i32) {
+		self.commands.push(Command::DrawArrays { primitive, first, count });
+	}
+
+	fn draw_elements(&mut self, primitive: Primitive, count: i32, index_type: IndexType, offset: i32) {
+		self.commands.push(Command::DrawElements { primitive, count, index_type, offset });
+	}
+
+	fn draw_elements_instanced(
+		&mut self,
+		primitive: Primitive,
+		count: i32,
+		index_type: IndexType,
+		offset: i32,
+		instance_count: i32
+	) {
+		self.commands.push(Command::DrawElementsInstanced { primitive, count, index_type, offset, instance_count });
+	}
+
+	fn drawing_buffer_size(&self) -> (u32, u32) {
+		(self.width, self.height)
+	}
+
+	fn enable_vertex_attrib_array(&mut self, index: u32) {
+		self.commands.push(Command::EnableVertexAttribArray { index });
+	}
+
+	fn framebuffer_renderbuffer(&mut self, renderbuffer: Option<RenderbufferHandle>) {
+		self.commands.push(Command::FramebufferRenderbuffer { renderbuffer });
+	}
+
+	fn framebuffer_texture(&mut self, attachment: Attachment, texture: Option<TextureHandle>) {
+		self.commands.push(Command::FramebufferTexture { attachment, texture });
+	}
+
+	fn generate_mipmap(&mut self, target: TextureTarget) {
+		self.commands.push(Command::GenerateMipmap { target });
+	}
+
+	fn get_uniform_location(&mut self, program: ProgramHandle, name: &str) -> Option<UniformHandle> {
+		// Only find uniforms the program could actually be using
+		let source = self.program_sources.get(&program)?;
+		if !declares_uniform(source, name) {
+			return None;
+		}
+
+		let location = UniformHandle(self.allocate());
+		self.uniform_names.insert(location, String::from(name));
+
+		Some(location)
+	}
+
+	fn link_program(
+		&mut self,
+		vertex_shader: ShaderHandle,
+		fragment_shader: ShaderHandle,
+		attributes: &[(u32, &str)]
+	) -> Result<ProgramHandle, String> {
+		// Both stages have to exist to link
+		let vertex_source = self.shader_sources.get(&vertex_shader)
+			.ok_or_else(|| String::from("Unknown vertex shader"))?;
+		let fragment_source = self.shader_sources.get(&fragment_shader)
+			.ok_or_else(|| String::from("Unknown fragment shader"))?;
+		let source = format!("{}\n{}", vertex_source, fragment_source);
+
+		let program = ProgramHandle(self.allocate());
+		self.program_sources.insert(program, source);
+		let attributes = attributes.iter()
+			.map(|&(location, name)| (location, String::from(name)))
+			.collect();
+		self.commands.push(Command::LinkProgram { program, vertex_shader, fragment_shader, attributes });
+
+		Ok(program)
+	}
+
+	fn resize_drawing_buffer(&mut self, width: u32, height: u32) {
+		self.width = width;
+		self.height = height;
+		self.commands.push(Command::ResizeDrawingBuffer { width, height });
+	}
+
+	fn set_render_state(&mut self, state: &RenderState) {
+		self.commands.push(Command::SetRenderState { state: *state });
+	}
+
+	fn set_uniform(&mut self, location: UniformHandle, value: &UniformValue) {
+		self.commands.push(Command::SetUniform { location, value: value.clone() });
+	}
+
+	fn set_texture_options(&mut self, target: TextureTarget, options: &TextureOptions) {
+		self.commands.push(Command::SetTextureOptions { target, options: *options });
+	}
+
+	fn tex_image_2d(&mut self, width: u32, height: u32, pixels: &[u8]) {
+		self.commands.push(Command::TexImage2D { width, height, pixels: pixels.to_vec() });
+	}
+
+	fn tex_image_cube_face(&mut self, face: CubeFace, width: u32, height: u32, pixels: &[u8]) {
+		self.commands.push(Command::TexImageCubeFace { face, width, height, pixels: pixels.to_vec() });
+	}
+
+	fn use_program(&mut self, program: Option<ProgramHandle>) {
+		self.commands.push(Command::UseProgram { program });
+	}
+
+	fn vertex_attrib_pointer(
+		&mut self,
+		index: u32,
+		size: i32,
+		component: VertexComponent,
+		normalized: bool,
+		stride: i32,
+		offset: i32
+	) {
+		self.commands.push(Command::VertexAttribPointer { index, size, component, normalized, stride, offset });
+	}
+
+	fn vertex_attrib_divisor(&mut self, index: u32, divisor: u32) {
+		self.commands.push(Command::VertexAttribDivisor { index, divisor });
+	}
+
+	fn viewport(&mut self, x: u32, y: u32, width: u32, height: u32) {
+		self.commands.push(Command::Viewport { x, y, width, height });
+	}
+}
+
+/// Finds whether shader source declares a uniform
+///
+/// Only whole names in uniform declarations count, so color isn't found in
+/// a program that only has light_colors.
+///
+/// * `source` - the shader source to look through
+/// * `name` - the name of the uniform, or of an element or field of one
+fn declares_uniform(source: &str, name: &str) -> bool {
+	// Elements and fields are found through the uniform they're part of
+	let name = name.split(['[', '.']).next().unwrap_or(name);
+
+	source.lines()
+		.filter_map(|line| line.trim().strip_prefix("uniform "))
+		.flat_map(|declaration| declaration.split(';').next().unwrap_or("").split(','))
+		.any(|declared| {
+			// The name is the last word before any array size
+			let declared = declared.split('[').next().unwrap_or("");
+			declared.split_whitespace().last() == Some(name)
+		})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::rc::Rc;
+
+	use nalgebra::Vector3;
+
+	use crate::{
+		graphics::{
+			material::Material,
+			mesh::{Indices, Mesh},
+			shaders::UniformValue,
+			shadows::ShadowSettings,
+			vertex_layout::{VertexAttribute, VertexLayout},
+			Graphics
+		},
+		logic::{light::Light, object::Object, world::World}
+	};
+	use super::*;
+
+	#[test]
+	fn render_draws_every_object() {
+		let mut graphics = Graphics::new(HeadlessBackend::new(800, 600));
+		graphics.set_shadow_settings(ShadowSettings { enabled: false, ..ShadowSettings::default() });
+		assert!(graphics.compile_shaders().is_empty());
+
+		// Two triangles with different materials, so they can't be instanced
+		let mesh = Rc::new(Mesh::new(Indices::U8(vec![0, 1, 2]), vec![-0.1, 0.0, 0.0, 0.1, 0.0, 0.0, 0.0, 0.1, 0.0]));
+		let objects = vec![
+			Object::from_mesh(Vector3::new(-0.2, 0.0, 0.0), 0.0, 0.0, 0.0, 1.0, Rc::new(Material::new("3d orange")), mesh.clone()),
+			Object::from_mesh(Vector3::new(0.2, 0.0, 0.0), 0.0, 0.0, 0.0, 1.0, Rc::new(Material::new("3d orange")), mesh)
+		];
+		let world = World::new(objects);
+
+		graphics.backend_mut().clear_commands();
+		graphics.render(&world);
+
+		let expected = Command::DrawElements { primitive: Primitive::Triangles, count: 3, index_type: IndexType::U8, offset: 0 };
+		assert_eq!(graphics.backend().draw_calls(), vec![&expected, &expected]);
+	}
+
+	#[test]
+	fn render_lights_objects_and_follows_mesh_changes() {
+		let mut graphics = Graphics::new(HeadlessBackend::new(800, 600));
+		graphics.set_shadow_settings(ShadowSettings { enabled: false, ..ShadowSettings::default() });
+		assert!(graphics.compile_shaders().is_empty());
+
+		// One lit triangle facing the camera, with positions and normals
+		let layout = VertexLayout::interleaved(vec![VertexAttribute::Position, VertexAttribute::Normal]);
+		let vertices = vec![
+			-0.1, 0.0, 0.0, 0.0, 0.0, 1.0,
+			0.1, 0.0, 0.0, 0.0, 0.0, 1.0,
+			0.0, 0.1, 0.0, 0.0, 0.0, 1.0
+		];
+		let mesh = Rc::new(Mesh::with_layout(Indices::U8(vec![0, 1, 2]), layout, vec![vertices]));
+		let object = Object::from_mesh(Vector3::zeros(), 0.0, 0.0, 0.0, 1.0, Rc::new(Material::new("3d lit")), mesh);
+		let mut world = World::new(vec![object]);
+		world.add_light(Light::directional(Vector3::new(0.0, 0.0, -1.0), Vector3::new(1.0, 1.0, 1.0), 1.0));
+
+		graphics.backend_mut().clear_commands();
+		graphics.render(&world);
+
+		// Drawn once, with the light uploaded and every uniform found
+		assert!(graphics.get_uniform_errors().is_empty(), "{:?}", graphics.get_uniform_errors());
+		assert_eq!(graphics.backend().draw_calls().len(), 1);
+		let light_count = graphics.backend().commands().iter().find_map(|command| match command {
+			Command::SetUniform { location, value } if graphics.backend().uniform_name(*location) == Some("light_count") => Some(value.clone()),
+			_ => None
+		});
+		assert_eq!(light_count, Some(UniformValue::Int(1)));
+
+		// Moved vertices get uploaded before the next draw
+		let moved = vec![
+			-0.2, 0.0, 0.0, 0.0, 0.0, 1.0,
+			0.2, 0.0, 0.0, 0.0, 0.0, 1.0,
+			0.0, 0.2, 0.0, 0.0, 0.0, 1.0
+		];
+		world.get_objects_mut()[0].set_vertices(moved.clone());
+		graphics.backend_mut().clear_commands();
+		graphics.render(&world);
+
+		let commands = graphics.backend().commands();
+		let upload = commands.iter().position(|command| matches!(command, Command::BufferDataF32 { data, .. } if *data == moved));
+		let draw = commands.iter().position(|command| matches!(command, Command::DrawElements {..}));
+		assert!(upload.is_some() && upload < draw, "{:?}", commands);
+		assert_eq!(graphics.backend().draw_calls().len(), 1);
+	}
+
+	#[test]
+	fn uniforms_match_whole_names() {
+		let mut backend = HeadlessBackend::new(1, 1);
+		let vertex_shader = backend.compile_shader(ShaderStage::Vertex, "uniform mat4 model;\nuniform vec3 light_colors[4];").unwrap();
+		let fragment_shader = backend.compile_shader(ShaderStage::Fragment, "uniform highp float a, b;").unwrap();
+		let program = backend.link_program(vertex_shader, fragment_shader, &[]).unwrap();
+
+		assert!(backend.get_uniform_location(program, "model").is_some());
+		assert!(backend.get_uniform_location(program, "light_colors").is_some());
+		assert!(backend.get_uniform_location(program, "light_colors[2]").is_some());
+		assert!(backend.get_uniform_location(program, "b").is_some());
+		assert!(backend.get_uniform_location(program, "color").is_none());
+		assert!(backend.get_uniform_location(program, "light").is_none());
+	}
+}
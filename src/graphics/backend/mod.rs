@@ -0,0 +1,458 @@
+pub mod headless;
+pub mod state_cache;
+pub mod webgl;
+
+use super::{render_state::RenderState, shaders::UniformValue, texture::{TextureFormat, TextureOptions}};
+
+/// Identifies a GPU buffer owned by a backend
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct BufferHandle(pub u32);
+
+/// Identifies a framebuffer owned by a backend
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FramebufferHandle(pub u32);
+
+/// Identifies a renderbuffer owned by a backend
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RenderbufferHandle(pub u32);
+
+/// Identifies a compiled shader stage owned by a backend
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ShaderHandle(pub u32);
+
+/// Identifies a linked shader program owned by a backend
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ProgramHandle(pub u32);
+
+/// Identifies a texture owned by a backend
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct TextureHandle(pub u32);
+
+/// Identifies the location of a uniform inside a linked program
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct UniformHandle(pub u32);
+
+/// What the graphics API can do, found when the backend is created
+///
+/// Anything that goes past WebGL1 without extensions should check here first.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Capabilities {
+	/// Depth can be rendered to a texture and sampled
+	pub depth_textures: bool,
+	/// Float textures can be drawn to
+	pub float_render_targets: bool,
+	/// Float textures can be sampled
+	pub float_textures: bool,
+	/// The same mesh can be drawn many times in one call
+	pub instancing: bool,
+	/// Width and height in pixels of the biggest texture that can be made
+	pub max_texture_size: u32,
+	/// How many vertex attribute locations a shader can read from
+	pub max_vertex_attribs: u32,
+	/// A fragment shader can write to more than one color attachment
+	pub multiple_render_targets: bool,
+	/// Textures that aren't a power of two in size can be mipmapped and repeated
+	pub npot_textures: bool,
+	/// Textures can be stored in sRGB
+	pub srgb: bool,
+	/// 32 bit index buffers can be drawn
+	pub uint_indices: bool,
+	/// Vertex array objects can be used
+	pub vertex_array_objects: bool,
+	/// Running on WebGL2 rather than WebGL1
+	pub webgl2: bool
+}
+
+impl Capabilities {
+	/// Everything WebGL1 has without extensions, at the smallest limits it
+	/// allows
+	pub fn webgl1() -> Capabilities {
+		Capabilities {
+			depth_textures: false,
+			float_render_targets: false,
+			float_textures: false,
+			instancing: false,
+			max_texture_size: 64,
+			max_vertex_attribs: 8,
+			multiple_render_targets: false,
+			npot_textures: false,
+			srgb: false,
+			uint_indices: false,
+			vertex_array_objects: false,
+			webgl2: false
+		}
+	}
+
+	/// Everything WebGL2 has without extensions, at the smallest limits it
+	/// allows
+	pub fn webgl2() -> Capabilities {
+		Capabilities {
+			depth_textures: true,
+			float_render_targets: false,
+			float_textures: true,
+			instancing: true,
+			max_texture_size: 2048,
+			max_vertex_attribs: 16,
+			multiple_render_targets: true,
+			npot_textures: true,
+			srgb: true,
+			uint_indices: true,
+			vertex_array_objects: true,
+			webgl2: true
+		}
+	}
+}
+
+impl Default for Capabilities {
+	fn default() -> Capabilities {
+		Self::webgl1()
+	}
+}
+
+/// Where a framebuffer keeps something it renders
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Attachment {
+	Color,
+	Depth
+}
+
+/// What a buffer is bound as
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BufferTarget {
+	/// Vertex data
+	Array,
+	/// Index data
+	ElementArray
+}
+
+/// How often the contents of a buffer are expected to change
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BufferUsage {
+	/// Uploaded once, drawn many times
+	Static,
+	/// Uploaded repeatedly, drawn many times
+	Dynamic,
+	/// Uploaded once, drawn a few times
+	Stream
+}
+
+/// The type of each index in an index buffer
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IndexType {
+	U8,
+	U16,
+	/// Only available with OES_element_index_uint on WebGL1
+	U32
+}
+
+impl IndexType {
+	/// Gets the size in bytes of one index
+	pub fn size(&self) -> i32 {
+		match self {
+			IndexType::U8 => 1,
+			IndexType::U16 => 2,
+			IndexType::U32 => 4
+		}
+	}
+}
+
+/// The type of primitive a draw call assembles
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Primitive {
+	Triangles,
+	/// Every two vertices make a separate line
+	Lines
+}
+
+/// Which face of a cube map an upload goes to
+///
+/// Faces are in the order WebGL numbers them, so ALL lines up with six images
+/// named px, nx, py, ny, pz, and nz.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CubeFace {
+	PositiveX,
+	NegativeX,
+	PositiveY,
+	NegativeY,
+	PositiveZ,
+	NegativeZ
+}
+
+impl CubeFace {
+	/// Every face, in the order WebGL numbers them
+	pub const ALL: [CubeFace; 6] = [
+		CubeFace::PositiveX,
+		CubeFace::NegativeX,
+		CubeFace::PositiveY,
+		CubeFace::NegativeY,
+		CubeFace::PositiveZ,
+		CubeFace::NegativeZ
+	];
+}
+
+/// Which stage of the pipeline a shader runs in
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ShaderStage {
+	Vertex,
+	Fragment
+}
+
+/// What kind of texture a binding or texture call is for
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TextureTarget {
+	/// A flat image
+	Texture2D,
+	/// Six square images, sampled by direction
+	CubeMap
+}
+
+/// The type of each component of a vertex attribute
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VertexComponent {
+	Float
+}
+
+/// Everything the renderer needs from a graphics API
+///
+/// Graphics only ever talks to the GPU through this, so the whole render path
+/// can run against WebGL in the browser or against the headless recorder in a
+/// native test.
+pub trait RenderBackend {
+	/// Allocates the texture bound to the active unit without filling it
+	///
+	/// * `width` - width in pixels
+	/// * `height` - height in pixels
+	/// * `format` - what each pixel stores
+	fn allocate_texture(&mut self, width: u32, height: u32, format: TextureFormat);
+
+	/// Binds a buffer to a target, or unbinds the target with None
+	fn bind_buffer(&mut self, target: BufferTarget, buffer: Option<BufferHandle>);
+
+	/// Binds a framebuffer to draw to, or goes back to the canvas with None
+	fn bind_framebuffer(&mut self, framebuffer: Option<FramebufferHandle>);
+
+	/// Binds a texture to a texture unit, or unbinds the unit with None
+	///
+	/// The unit is left active, so texture calls that follow apply to it. A
+	/// unit holds a 2D texture and a cube map at the same time, but a shader
+	/// can only sample one of them.
+	///
+	/// * `unit` - the texture unit to bind to
+	/// * `target` - what kind of texture it is
+	/// * `texture` - the texture to bind
+	fn bind_texture(&mut self, unit: u32, target: TextureTarget, texture: Option<TextureHandle>);
+
+	/// Uploads f32 data to the buffer bound to a target
+	fn buffer_data_f32(&mut self, target: BufferTarget, data: &[f32], usage: BufferUsage);
+
+	/// Uploads u8 data to the buffer bound to a target
+	fn buffer_data_u8(&mut self, target: BufferTarget, data: &[u8], usage: BufferUsage);
+
+	/// Uploads u16 data to the buffer bound to a target
+	fn buffer_data_u16(&mut self, target: BufferTarget, data: &[u16], usage: BufferUsage);
+
+	/// Uploads u32 data to the buffer bound to a target
+	fn buffer_data_u32(&mut self, target: BufferTarget, data: &[u32], usage: BufferUsage);
+
+	/// Gets what the graphics API can do
+	fn capabilities(&self) -> &Capabilities;
+
+	/// Checks that the bound framebuffer can be drawn to
+	///
+	/// Returns - why it can't, if it can't
+	fn check_framebuffer_status(&mut self) -> Result<(), String>;
+
+	/// Clears the current drawing buffer
+	///
+	/// * `color` - whether to clear the color buffer
+	/// * `depth` - whether to clear the depth buffer
+	fn clear(&mut self, color: bool, depth: bool);
+
+	/// Sets the color the color buffer gets cleared to
+	fn clear_color(&mut self, red: f32, green: f32, blue: f32, alpha: f32);
+
+	/// Compiles a single shader stage
+	///
+	/// Returns - the compiled shader, or the info log if it failed
+	fn compile_shader(&mut self, stage: ShaderStage, source: &str) -> Result<ShaderHandle, String>;
+
+	/// Creates an empty buffer
+	fn create_buffer(&mut self) -> Option<BufferHandle>;
+
+	/// Creates an empty framebuffer
+	fn create_framebuffer(&mut self) -> Option<FramebufferHandle>;
+
+	/// Creates an empty renderbuffer
+	fn create_renderbuffer(&mut self) -> Option<RenderbufferHandle>;
+
+	/// Creates an empty texture
+	fn create_texture(&mut self) -> Option<TextureHandle>;
+
+	/// Frees a buffer
+	fn delete_buffer(&mut self, buffer: BufferHandle);
+
+	/// Frees a framebuffer
+	fn delete_framebuffer(&mut self, framebuffer: FramebufferHandle);
+
+	/// Frees a linked program
+	fn delete_program(&mut self, program: ProgramHandle);
+
+	/// Frees a compiled shader stage
+	fn delete_shader(&mut self, shader: ShaderHandle);
+
+	/// Frees a renderbuffer
+	fn delete_renderbuffer(&mut self, renderbuffer: RenderbufferHandle);
+
+	/// Frees a texture
+	fn delete_texture(&mut self, texture: TextureHandle);
+
+	/// Allocates a renderbuffer to hold depth
+	///
+	/// * `renderbuffer` - the renderbuffer to allocate
+	/// * `width` - width in pixels
+	/// * `height` - height in pixels
+	fn depth_renderbuffer_storage(&mut self, renderbuffer: RenderbufferHandle, width: u32, height: u32);
+
+	/// Disables a vertex attribute array, so it reads a constant instead
+	fn disable_vertex_attrib_array(&mut self, index: u32);
+
+	/// Draws primitives straight from the bound vertex buffers, without
+	/// indices
+	///
+	/// * `primitive` - what to assemble the vertices into
+	/// * `first` - the first vertex to draw
+	/// * `count` - the number of vertices to draw
+	fn draw_arrays(&mut self, primitive: Primitive, first: i32, count: i32);
+
+	/// Draws indexed primitives from the bound buffers
+	///
+	/// * `primitive` - what to assemble the indices into
+	/// * `count` - the number of indices to draw
+	/// * `index_type` - the type of the bound index buffer
+	/// * `offset` - the byte offset into the index buffer
+	fn draw_elements(&mut self, primitive: Primitive, count: i32, index_type: IndexType, offset: i32);
+
+	/// Draws indexed primitives from the bound buffers once for each instance
+	///
+	/// Only available with capabilities().instancing.
+	///
+	/// * `primitive` - what to assemble the indices into
+	/// * `count` - the number of indices to draw
+	/// * `index_type` - the type of the bound index buffer
+	/// * `offset` - the byte offset into the index buffer
+	/// * `instance_count` - how many times to draw them
+	fn draw_elements_instanced(
+		&mut self,
+		primitive: Primitive,
+		count: i32,
+		index_type: IndexType,
+		offset: i32,
+		instance_count: i32
+	);
+
+	/// Gets the size in pixels of the buffer being drawn to
+	fn drawing_buffer_size(&self) -> (u32, u32);
+
+	/// Enables a vertex attribute array
+	fn enable_vertex_attrib_array(&mut self, index: u32);
+
+	/// Attaches a renderbuffer to the bound framebuffer as its depth
+	fn framebuffer_renderbuffer(&mut self, renderbuffer: Option<RenderbufferHandle>);
+
+	/// Attaches a texture to the bound framebuffer
+	fn framebuffer_texture(&mut self, attachment: Attachment, texture: Option<TextureHandle>);
+
+	/// Generates mipmaps for the texture of a kind bound to the active unit
+	fn generate_mipmap(&mut self, target: TextureTarget);
+
+	/// Finds the location of a uniform in a linked program
+	///
+	/// Returns - None if the program does not use this uniform
+	fn get_uniform_location(&mut self, program: ProgramHandle, name: &str) -> Option<UniformHandle>;
+
+	/// Links a vertex and fragment shader into a program
+	///
+	/// * `vertex_shader` - the compiled vertex stage
+	/// * `fragment_shader` - the compiled fragment stage
+	/// * `attributes` - attribute names to bind to fixed locations before linking
+	///
+	/// Returns - the linked program, or the info log if it failed
+	fn link_program(
+		&mut self,
+		vertex_shader: ShaderHandle,
+		fragment_shader: ShaderHandle,
+		attributes: &[(u32, &str)]
+	) -> Result<ProgramHandle, String>;
+
+	/// Changes the size of the buffer being drawn to
+	///
+	/// The viewport is left alone, so set that afterwards.
+	///
+	/// * `width` - width in pixels
+	/// * `height` - height in pixels
+	fn resize_drawing_buffer(&mut self, width: u32, height: u32);
+
+	/// Sets a uniform in the program currently in use
+	///
+	/// The value is assumed to already match the uniform's type.
+	fn set_uniform(&mut self, location: UniformHandle, value: &UniformValue);
+
+	/// Applies depth and culling state for the draw calls that follow
+	fn set_render_state(&mut self, state: &RenderState);
+
+	/// Sets how the texture of a kind bound to the active unit gets sampled
+	fn set_texture_options(&mut self, target: TextureTarget, options: &TextureOptions);
+
+	/// Uploads RGBA8 pixels to the texture bound to the active unit
+	///
+	/// * `width` - width in pixels
+	/// * `height` - height in pixels
+	/// * `pixels` - 4 bytes per pixel, rows from top to bottom
+	fn tex_image_2d(&mut self, width: u32, height: u32, pixels: &[u8]);
+
+	/// Uploads RGBA8 pixels to one face of the cube map bound to the active
+	/// unit
+	///
+	/// * `face` - the face to upload to
+	/// * `width` - width in pixels, the same as the height
+	/// * `height` - height in pixels
+	/// * `pixels` - 4 bytes per pixel, rows from top to bottom
+	fn tex_image_cube_face(&mut self, face: CubeFace, width: u32, height: u32, pixels: &[u8]);
+
+	/// Sets the program used for drawing, or unsets it with None
+	fn use_program(&mut self, program: Option<ProgramHandle>);
+
+	/// Describes how a vertex attribute is laid out in the bound array buffer
+	///
+	/// * `index` - the attribute index
+	/// * `size` - the number of components per vertex
+	/// * `component` - the type of each component
+	/// * `normalized` - whether integer components get normalized to 0-1
+	/// * `stride` - bytes between the start of each vertex
+	/// * `offset` - bytes before the first component
+	fn vertex_attrib_pointer(
+		&mut self,
+		index: u32,
+		size: i32,
+		component: VertexComponent,
+		normalized: bool,
+		stride: i32,
+		offset: i32
+	);
+
+	/// Sets how often a vertex attribute moves on to its next value
+	///
+	/// Only available with capabilities().instancing.
+	///
+	/// * `index` - the attribute index
+	/// * `divisor` - 0 to move on every vertex, or n to move on every n instances
+	fn vertex_attrib_divisor(&mut self, index: u32, divisor: u32);
+
+	/// Sets the area of the drawing buffer that draw calls render to
+	///
+	/// * `x` - pixels from the left edge
+	/// * `y` - pixels from the bottom edge
+	/// * `width` - width in pixels
+	/// * `height` - height in pixels
+	fn viewport(&mut self, x: u32, y: u32, width: u32, height: u32);
+}
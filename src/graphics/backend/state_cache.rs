@@ -0,0 +1,599 @@
+use std::collections::HashMap;
+
+use crate::graphics::{render_state::RenderState, shaders::UniformValue, texture::{TextureFormat, TextureOptions}};
+use super::{
+	Attachment,
+	BufferHandle,
+	BufferTarget,
+	BufferUsage,
+	Capabilities,
+	CubeFace,
+	FramebufferHandle,
+	IndexType,
+	Primitive,
+	ProgramHandle,
+	RenderBackend,
+	RenderbufferHandle,
+	ShaderHandle,
+	ShaderStage,
+	TextureHandle,
+	TextureTarget,
+	UniformHandle,
+	VertexComponent
+};
+
+/// Counts of what a frame asked the GPU for, and what didn't need asking
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RenderStats {
+	/// Draw calls made
+	pub draw_calls: u32,
+	/// Times the program in use actually changed
+	pub program_switches: u32,
+	/// Buffer binds skipped because the buffer was already bound
+	pub saved_buffer_binds: u32,
+	/// Framebuffer binds skipped because the framebuffer was already bound
+	pub saved_framebuffer_binds: u32,
+	/// Program changes skipped because the program was already in use
+	pub saved_program_binds: u32,
+	/// Render state, viewport, clear color, and vertex attribute changes
+	/// skipped because nothing would have changed
+	pub saved_state_changes: u32,
+	/// Texture binds skipped because the texture was already bound
+	pub saved_texture_binds: u32,
+	/// Uniform sets skipped because the uniform already had the value
+	pub saved_uniform_sets: u32,
+	/// Triangles drawn, counting every instance
+	pub triangles: u32,
+	/// Bytes uploaded to buffers and textures
+	pub upload_bytes: u32,
+	/// Vertices drawn, counting every instance and every time an index
+	/// repeats one
+	pub vertices: u32
+}
+
+impl RenderStats {
+	/// Gets how many buffer, framebuffer, program, and texture binds were
+	/// skipped
+	pub fn saved_binds(&self) -> u32 {
+		self.saved_buffer_binds
+			+ self.saved_framebuffer_binds
+			+ self.saved_program_binds
+			+ self.saved_texture_binds
+	}
+}
+
+/// Where a vertex attribute reads from
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct AttributePointer {
+	buffer: Option<BufferHandle>,
+	size: i32,
+	component: VertexComponent,
+	normalized: bool,
+	stride: i32,
+	offset: i32
+}
+
+/// Wraps a backend and skips calls that wouldn't change anything
+///
+/// Remembers the state it has set, so binding what's already bound, using
+/// the program already in use, or setting a uniform to the value it already
+/// has never reaches the GPU. Anything not known yet always goes through.
+/// Calls made straight to the wrapped backend aren't seen, so invalidate has
+/// to be called after them.
+pub struct StateCache<B: RenderBackend> {
+	/// The texture unit the wrapped backend has active
+	active_unit: Option<u32>,
+	/// The buffer bound as vertex data
+	array_buffer: Option<Option<BufferHandle>>,
+	/// Where each vertex attribute reads from
+	attribute_pointers: HashMap<u32, AttributePointer>,
+	/// The backend calls go through to
+	backend: B,
+	/// The color the color buffer gets cleared to
+	clear_color: Option<[f32; 4]>,
+	/// How often each vertex attribute moves on to its next value
+	divisors: HashMap<u32, u32>,
+	/// The buffer bound as index data
+	element_buffer: Option<Option<BufferHandle>>,
+	/// Whether each vertex attribute array is enabled
+	enabled_attributes: HashMap<u32, bool>,
+	/// The framebuffer being drawn to
+	framebuffer: Option<Option<FramebufferHandle>>,
+	/// The program in use
+	program: Option<Option<ProgramHandle>>,
+	/// Depth and culling state
+	render_state: Option<RenderState>,
+	/// The texture unit callers last bound, which texture calls apply to, and
+	/// what kind of texture they bound to it
+	selected_unit: Option<(u32, TextureTarget)>,
+	/// What's been asked for since the stats were last reset
+	stats: RenderStats,
+	/// The texture of each kind bound to each unit
+	textures: HashMap<(u32, TextureTarget), Option<TextureHandle>>,
+	/// The value each uniform was last set to
+	uniforms: HashMap<UniformHandle, UniformValue>,
+	/// The area of the drawing buffer being drawn to
+	viewport: Option<[u32; 4]>
+}
+
+impl<B: RenderBackend> StateCache<B> {
+	/// Gets the wrapped backend
+	pub fn get_inner(&self) -> &B {
+		&self.backend
+	}
+
+	/// Gets the wrapped backend mutably
+	///
+	/// Call invalidate before using it, since the cache can't see what's done
+	/// through it.
+	pub fn get_inner_mut(&mut self) -> &mut B {
+		&mut self.backend
+	}
+
+	/// Gets what's been asked for since the stats were last reset
+	pub fn get_stats(&self) -> &RenderStats {
+		&self.stats
+	}
+
+	/// Forgets all remembered state, so every call goes through until it's
+	/// known again
+	pub fn invalidate(&mut self) {
+		self.active_unit = None;
+		self.array_buffer = None;
+		self.attribute_pointers.clear();
+		self.clear_color = None;
+		self.divisors.clear();
+		self.element_buffer = None;
+		self.enabled_attributes.clear();
+		self.framebuffer = None;
+		self.program = None;
+		self.render_state = None;
+		self.selected_unit = None;
+		self.textures.clear();
+		self.uniforms.clear();
+		self.viewport = None;
+	}
+
+	/// Wraps a backend, with nothing known about its state yet
+	///
+	/// * `backend` - the backend to pass calls through to
+	pub fn new(backend: B) -> StateCache<B> {
+		StateCache {
+			active_unit: None,
+			array_buffer: None,
+			attribute_pointers: HashMap::new(),
+			backend,
+			clear_color: None,
+			divisors: HashMap::new(),
+			element_buffer: None,
+			enabled_attributes: HashMap::new(),
+			framebuffer: None,
+			program: None,
+			render_state: None,
+			selected_unit: None,
+			stats: RenderStats::default(),
+			textures: HashMap::new(),
+			uniforms: HashMap::new(),
+			viewport: None
+		}
+	}
+
+	/// Counts a draw call and what it draws
+	///
+	/// * `primitive` - what the vertices get assembled into
+	/// * `count` - how many vertices or indices each instance draws
+	/// * `instances` - how many times they get drawn
+	fn count_draw(&mut self, primitive: Primitive, count: i32, instances: i32) {
+		let vertices = count.max(0) as u32 * instances.max(0) as u32;
+		self.stats.draw_calls += 1;
+		self.stats.vertices += vertices;
+		if primitive == Primitive::Triangles {
+			self.stats.triangles += vertices / 3;
+		}
+	}
+
+	/// Counts bytes going up to the GPU
+	///
+	/// * `bytes` - how many bytes are being uploaded
+	fn count_upload(&mut self, bytes: usize) {
+		self.stats.upload_bytes = self.stats.upload_bytes.saturating_add(bytes as u32);
+	}
+
+	/// Starts counting from zero again
+	pub fn reset_stats(&mut self) {
+		self.stats = RenderStats::default();
+	}
+
+	/// Makes the unit callers last bound active, if a skipped bind left
+	/// another one active
+	///
+	/// Texture calls apply to the active unit, so this goes before them.
+	fn sync_active_unit(&mut self) {
+		if let Some((unit, target)) = self.selected_unit {
+			if self.active_unit != Some(unit) {
+				let texture = self.textures.get(&(unit, target)).copied().flatten();
+				self.backend.bind_texture(unit, target, texture);
+				self.active_unit = Some(unit);
+			}
+		}
+	}
+}
+
+impl<B: RenderBackend> RenderBackend for StateCache<B> {
+	fn allocate_texture(&mut self, width: u32, height: u32, format: TextureFormat) {
+		self.sync_active_unit();
+		self.backend.allocate_texture(width, height, format);
+	}
+
+	fn bind_buffer(&mut self, target: BufferTarget, buffer: Option<BufferHandle>) {
+		let bound = match target {
+			BufferTarget::Array => &mut self.array_buffer,
+			BufferTarget::ElementArray => &mut self.element_buffer
+		};
+		if *bound == Some(buffer) {
+			self.stats.saved_buffer_binds += 1;
+			return;
+		}
+
+		*bound = Some(buffer);
+		self.backend.bind_buffer(target, buffer);
+	}
+
+	fn bind_framebuffer(&mut self, framebuffer: Option<FramebufferHandle>) {
+		if self.framebuffer == Some(framebuffer) {
+			self.stats.saved_framebuffer_binds += 1;
+			return;
+		}
+
+		self.framebuffer = Some(framebuffer);
+		self.backend.bind_framebuffer(framebuffer);
+	}
+
+	fn bind_texture(&mut self, unit: u32, target: TextureTarget, texture: Option<TextureHandle>) {
+		// Callers expect the unit to be active afterwards, which gets sorted
+		// out before the next call that cares
+		self.selected_unit = Some((unit, target));
+		if self.textures.get(&(unit, target)) == Some(&texture) {
+			self.stats.saved_texture_binds += 1;
+			return;
+		}
+
+		self.textures.insert((unit, target), texture);
+		self.active_unit = Some(unit);
+		self.backend.bind_texture(unit, target, texture);
+	}
+
+	fn buffer_data_f32(&mut self, target: BufferTarget, data: &[f32], usage: BufferUsage) {
+		self.count_upload(std::mem::size_of_val(data));
+		self.backend.buffer_data_f32(target, data, usage);
+	}
+
+	fn buffer_data_u8(&mut self, target: BufferTarget, data: &[u8], usage: BufferUsage) {
+		self.count_upload(data.len());
+		self.backend.buffer_data_u8(target, data, usage);
+	}
+
+	fn buffer_data_u16(&mut self, target: BufferTarget, data: &[u16], usage: BufferUsage) {
+		self.count_upload(std::mem::size_of_val(data));
+		self.backend.buffer_data_u16(target, data, usage);
+	}
+
+	fn buffer_data_u32(&mut self, target: BufferTarget, data: &[u32], usage: BufferUsage) {
+		self.count_upload(std::mem::size_of_val(data));
+		self.backend.buffer_data_u32(target, data, usage);
+	}
+
+	fn capabilities(&self) -> &Capabilities {
+		self.backend.capabilities()
+	}
+
+	fn check_framebuffer_status(&mut self) -> Result<(), String> {
+		self.backend.check_framebuffer_status()
+	}
+
+	fn clear(&mut self, color: bool, depth: bool) {
+		self.backend.clear(color, depth);
+	}
+
+	fn clear_color(&mut self, red: f32, green: f32, blue: f32, alpha: f32) {
+		let color = [red, green, blue, alpha];
+		if self.clear_color == Some(color) {
+			self.stats.saved_state_changes += 1;
+			return;
+		}
+
+		self.clear_color = Some(color);
+		self.backend.clear_color(red, green, blue, alpha);
+	}
+
+	fn compile_shader(&mut self, stage: ShaderStage, source: &str) -> Result<ShaderHandle, String> {
+		self.backend.compile_shader(stage, source)
+	}
+
+	fn create_buffer(&mut self) -> Option<BufferHandle> {
+		self.backend.create_buffer()
+	}
+
+	fn create_framebuffer(&mut self) -> Option<FramebufferHandle> {
+		self.backend.create_framebuffer()
+	}
+
+	fn create_renderbuffer(&mut self) -> Option<RenderbufferHandle> {
+		self.backend.create_renderbuffer()
+	}
+
+	fn create_texture(&mut self) -> Option<TextureHandle> {
+		self.backend.create_texture()
+	}
+
+	fn delete_buffer(&mut self, buffer: BufferHandle) {
+		// Deleting a buffer unbinds it from everywhere
+		for bound in [&mut self.array_buffer, &mut self.element_buffer] {
+			if *bound == Some(Some(buffer)) {
+				*bound = Some(None);
+			}
+		}
+		self.attribute_pointers.retain(|_, pointer| pointer.buffer != Some(buffer));
+
+		self.backend.delete_buffer(buffer);
+	}
+
+	fn delete_framebuffer(&mut self, framebuffer: FramebufferHandle) {
+		// Deleting the bound framebuffer goes back to the canvas
+		if self.framebuffer == Some(Some(framebuffer)) {
+			self.framebuffer = Some(None);
+		}
+
+		self.backend.delete_framebuffer(framebuffer);
+	}
+
+	fn delete_program(&mut self, program: ProgramHandle) {
+		// Uniform handles don't say which program they're from, and programs
+		// only go away on reloads, so forget every uniform
+		if self.program == Some(Some(program)) {
+			self.program = None;
+		}
+		self.uniforms.clear();
+
+		self.backend.delete_program(program);
+	}
+
+	fn delete_shader(&mut self, shader: ShaderHandle) {
+		self.backend.delete_shader(shader);
+	}
+
+	fn delete_renderbuffer(&mut self, renderbuffer: RenderbufferHandle) {
+		self.backend.delete_renderbuffer(renderbuffer);
+	}
+
+	fn delete_texture(&mut self, texture: TextureHandle) {
+		// Deleting a texture unbinds it from every unit
+		for bound in self.textures.values_mut() {
+			if *bound == Some(texture) {
+				*bound = None;
+			}
+		}
+
+		self.backend.delete_texture(texture);
+	}
+
+	fn depth_renderbuffer_storage(&mut self, renderbuffer: RenderbufferHandle, width: u32, height: u32) {
+		self.backend.depth_renderbuffer_storage(renderbuffer, width, height);
+	}
+
+	fn disable_vertex_attrib_array(&mut self, index: u32) {
+		if self.enabled_attributes.get(&index) == Some(&false) {
+			self.stats.saved_state_changes += 1;
+			return;
+		}
+
+		self.enabled_attributes.insert(index, false);
+		self.backend.disable_vertex_attrib_array(index);
+	}
+
+	fn draw_arrays(&mut self, primitive: Primitive, first: i32, count: i32) {
+		self.count_draw(primitive, count, 1);
+		self.backend.draw_arrays(primitive, first, count);
+	}
+
+	fn draw_elements(&mut self, primitive: Primitive, count: i32, index_type: IndexType, offset: i32) {
+		self.count_draw(primitive, count, 1);
+		self.backend.draw_elements(primitive, count, index_type, offset);
+	}
+
+	fn draw_elements_instanced(
+		&mut self,
+		primitive: Primitive,
+		count: i32,
+		index_type: IndexType,
+		offset: i32,
+		instance_count: i32
+	) {
+		self.count_draw(primitive, count, instance_count);
+		self.backend.draw_elements_instanced(primitive, count, index_type, offset, instance_count);
+	}
+
+	fn drawing_buffer_size(&self) -> (u32, u32) {
+		self.backend.drawing_buffer_size()
+	}
+
+	fn enable_vertex_attrib_array(&mut self, index: u32) {
+		if self.enabled_attributes.get(&index) == Some(&true) {
+			self.stats.saved_state_changes += 1;
+			return;
+		}
+
+		self.enabled_attributes.insert(index, true);
+		self.backend.enable_vertex_attrib_array(index);
+	}
+
+	fn framebuffer_renderbuffer(&mut self, renderbuffer: Option<RenderbufferHandle>) {
+		self.backend.framebuffer_renderbuffer(renderbuffer);
+	}
+
+	fn framebuffer_texture(&mut self, attachment: Attachment, texture: Option<TextureHandle>) {
+		self.backend.framebuffer_texture(attachment, texture);
+	}
+
+	fn generate_mipmap(&mut self, target: TextureTarget) {
+		self.sync_active_unit();
+		self.backend.generate_mipmap(target);
+	}
+
+	fn get_uniform_location(&mut self, program: ProgramHandle, name: &str) -> Option<UniformHandle> {
+		self.backend.get_uniform_location(program, name)
+	}
+
+	fn link_program(
+		&mut self,
+		vertex_shader: ShaderHandle,
+		fragment_shader: ShaderHandle,
+		attributes: &[(u32, &str)]
+	) -> Result<ProgramHandle, String> {
+		self.backend.link_program(vertex_shader, fragment_shader, attributes)
+	}
+
+	fn resize_drawing_buffer(&mut self, width: u32, height: u32) {
+		self.backend.resize_drawing_buffer(width, height);
+	}
+
+	fn set_uniform(&mut self, location: UniformHandle, value: &UniformValue) {
+		if self.uniforms.get(&location) == Some(value) {
+			self.stats.saved_uniform_sets += 1;
+			return;
+		}
+
+		self.uniforms.insert(location, value.clone());
+		self.backend.set_uniform(location, value);
+	}
+
+	fn set_render_state(&mut self, state: &RenderState) {
+		if self.render_state.as_ref() == Some(state) {
+			self.stats.saved_state_changes += 1;
+			return;
+		}
+
+		self.render_state = Some(*state);
+		self.backend.set_render_state(state);
+	}
+
+	fn set_texture_options(&mut self, target: TextureTarget, options: &TextureOptions) {
+		self.sync_active_unit();
+		self.backend.set_texture_options(target, options);
+	}
+
+	fn tex_image_2d(&mut self, width: u32, height: u32, pixels: &[u8]) {
+		self.sync_active_unit();
+		self.count_upload(pixels.len());
+		self.backend.tex_image_2d(width, height, pixels);
+	}
+
+	fn tex_image_cube_face(&mut self, face: CubeFace, width: u32, height: u32, pixels: &[u8]) {
+		self.sync_active_unit();
+		self.count_upload(pixels.len());
+		self.backend.tex_image_cube_face(face, width, height, pixels);
+	}
+
+	fn use_program(&mut self, program: Option<ProgramHandle>) {
+		if self.program == Some(program) {
+			self.stats.saved_program_binds += 1;
+			return;
+		}
+
+		self.program = Some(program);
+		self.stats.program_switches += 1;
+		self.backend.use_program(program);
+	}
+
+	fn vertex_attrib_pointer(
+		&mut self,
+		index: u32,
+		size: i32,
+		component: VertexComponent,
+		normalized: bool,
+		stride: i32,
+		offset: i32
+	) {
+		// The pointer reads from whatever is bound, which has to be known to
+		// tell if anything changed
+		let pointer = self.array_buffer.map(|buffer| AttributePointer { buffer, size, component, normalized, stride, offset });
+		if pointer.is_some() && self.attribute_pointers.get(&index) == pointer.as_ref() {
+			self.stats.saved_state_changes += 1;
+			return;
+		}
+
+		match pointer {
+			Some(pointer) => self.attribute_pointers.insert(index, pointer),
+			None => self.attribute_pointers.remove(&index)
+		};
+		self.backend.vertex_attrib_pointer(index, size, component, normalized, stride, offset);
+	}
+
+	fn vertex_attrib_divisor(&mut self, index: u32, divisor: u32) {
+		if self.divisors.get(&index) == Some(&divisor) {
+			self.stats.saved_state_changes += 1;
+			return;
+		}
+
+		self.divisors.insert(index, divisor);
+		self.backend.vertex_attrib_divisor(index, divisor);
+	}
+
+	fn viewport(&mut self, x: u32, y: u32, width: u32, height: u32) {
+		let viewport = [x, y, width, height];
+		if self.viewport == Some(viewport) {
+			self.stats.saved_state_changes += 1;
+			return;
+		}
+
+		self.viewport = Some(viewport);
+		self.backend.viewport(x, y, width, height);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::rc::Rc;
+
+	use nalgebra::Vector3;
+
+	use crate::{
+		graphics::{
+			backend::headless::HeadlessBackend,
+			material::Material,
+			mesh::{Indices, Mesh},
+			shadows::ShadowSettings,
+			Graphics
+		},
+		logic::{object::Object, world::World}
+	};
+
+	#[test]
+	fn objects_sharing_a_shader_save_calls() {
+		let mut graphics = Graphics::new(HeadlessBackend::new(800, 600));
+		graphics.set_shadow_settings(ShadowSettings { enabled: false, ..ShadowSettings::default() });
+		assert!(graphics.compile_shaders().is_empty());
+
+		// Same shader and mesh, but different materials, so they're drawn one
+		// at a time
+		let mesh = Rc::new(Mesh::new(Indices::U8(vec![0, 1, 2]), vec![-0.1, 0.0, 0.0, 0.1, 0.0, 0.0, 0.0, 0.1, 0.0]));
+		let objects = vec![
+			Object::from_mesh(Vector3::new(-0.2, 0.0, 0.0), 0.0, 0.0, 0.0, 1.0, Rc::new(Material::new("3d orange")), mesh.clone()),
+			Object::from_mesh(Vector3::new(0.2, 0.0, 0.0), 0.0, 0.0, 0.0, 1.0, Rc::new(Material::new("3d orange")), mesh)
+		];
+		graphics.render(&World::new(objects));
+
+		// The second object reuses the program, the mesh's buffers, the
+		// camera's uniforms, and the render state
+		let render_stats = graphics.get_render_stats();
+		assert_eq!(render_stats.saved_program_binds, 1);
+		assert!(render_stats.saved_buffer_binds > 0);
+
+		let stats = graphics.get_frame_stats();
+		assert_eq!(stats.draw_calls, 2);
+		assert_eq!(stats.program_switches, 1);
+		assert_eq!(stats.saved_binds, render_stats.saved_binds());
+		assert!(stats.saved_uniform_sets >= 2);
+		assert!(stats.saved_state_changes > 0);
+	}
+}
@@ -0,0 +1,828 @@
+use std::collections::HashMap;
+
+use js_sys::{Float32Array, Uint16Array, Uint32Array};
+use wasm_bindgen::JsCast;
+use web_sys::{
+	AngleInstancedArrays,
+	HtmlCanvasElement,
+	WebGl2RenderingContext,
+	WebGlBuffer,
+	WebGlFramebuffer,
+	WebGlProgram,
+	WebGlRenderbuffer,
+	WebGlRenderingContext,
+	WebGlShader,
+	WebGlTexture,
+	WebGlUniformLocation
+};
+
+use crate::graphics::{
+	render_state::{BlendMode, CullMode, DepthFunc, FrontFace, RenderState},
+	shaders::UniformValue,
+	texture::{FilterMode, TextureFormat, TextureOptions, WrapMode}
+};
+use super::{
+	Attachment,
+	BufferHandle,
+	BufferTarget,
+	BufferUsage,
+	Capabilities,
+	CubeFace,
+	FramebufferHandle,
+	IndexType,
+	Primitive,
+	ProgramHandle,
+	RenderBackend,
+	RenderbufferHandle,
+	ShaderHandle,
+	ShaderStage,
+	TextureHandle,
+	TextureTarget,
+	UniformHandle,
+	VertexComponent
+};
+
+/// Renders through a WebGL rendering context
+///
+/// Uses WebGL2 when the browser has it, and WebGL1 otherwise.
+pub struct WebGlBackend {
+	/// Buffers that have been created, by handle
+	buffers: HashMap<BufferHandle, WebGlBuffer>,
+	/// The canvas the context draws to
+	canvas: HtmlCanvasElement,
+	/// What the context can do
+	capabilities: Capabilities,
+	/// The webgl context to render to
+	///
+	/// A WebGL2 context gets stored as this too, since WebGL2 has every
+	/// WebGL1 function and the bindings call them by name.
+	context: WebGlRenderingContext,
+	/// Framebuffers that have been created, by handle
+	framebuffers: HashMap<FramebufferHandle, WebGlFramebuffer>,
+	/// The extension instanced drawing goes through on WebGL1
+	instanced_arrays: Option<AngleInstancedArrays>,
+	/// The next handle to give out
+	next_handle: u32,
+	/// Programs that have been linked, by handle
+	programs: HashMap<ProgramHandle, WebGlProgram>,
+	/// Renderbuffers that have been created, by handle
+	renderbuffers: HashMap<RenderbufferHandle, WebGlRenderbuffer>,
+	/// Shaders that have been compiled, by handle
+	shaders: HashMap<ShaderHandle, WebGlShader>,
+	/// Textures that have been created, by handle
+	textures: HashMap<TextureHandle, WebGlTexture>,
+	/// Uniform locations that have been found, by handle
+	uniforms: HashMap<UniformHandle, WebGlUniformLocation>
+}
+
+impl WebGlBackend {
+	/// Creates a backend that renders to a canvas
+	///
+	/// Asks for WebGL2 first and falls back to WebGL1.
+	/// Shits the bed if neither is available, because there's no reason to
+	/// continue without it
+	///
+	/// * `canvas` - the canvas to render to
+	pub fn new(canvas: &HtmlCanvasElement) -> WebGlBackend {
+		let webgl2 = canvas.get_context("webgl2").ok().flatten();
+		let (context, capabilities, instanced_arrays) = match webgl2 {
+			Some(context) => {
+				let context: WebGlRenderingContext = context.unchecked_into();
+
+				// Drawing to float textures is still an extension
+				let capabilities = Capabilities {
+					float_render_targets: has_extension(&context, "EXT_color_buffer_float"),
+					max_texture_size: get_limit(&context, WebGlRenderingContext::MAX_TEXTURE_SIZE, 2048),
+					max_vertex_attribs: get_limit(&context, WebGlRenderingContext::MAX_VERTEX_ATTRIBS, 16),
+					..Capabilities::webgl2()
+				};
+
+				(context, capabilities, None)
+			},
+			None => {
+				let context = canvas.get_context("webgl").unwrap().unwrap()
+					.dyn_into::<WebGlRenderingContext>().unwrap();
+
+				// Everything past the basics needs an extension on WebGL1
+				// Instancing gets called through its extension, so that one is kept
+				let float_textures = has_extension(&context, "OES_texture_float");
+				let instanced_arrays = context.get_extension("ANGLE_instanced_arrays").ok().flatten()
+					.map(|extension| extension.unchecked_into::<AngleInstancedArrays>());
+				let capabilities = Capabilities {
+					depth_textures: has_extension(&context, "WEBGL_depth_texture"),
+					float_render_targets: float_textures && has_extension(&context, "WEBGL_color_buffer_float"),
+					float_textures,
+					instancing: instanced_arrays.is_some(),
+					max_texture_size: get_limit(&context, WebGlRenderingContext::MAX_TEXTURE_SIZE, 64),
+					max_vertex_attribs: get_limit(&context, WebGlRenderingContext::MAX_VERTEX_ATTRIBS, 8),
+					multiple_render_targets: has_extension(&context, "WEBGL_draw_buffers"),
+					npot_textures: false,
+					srgb: has_extension(&context, "EXT_sRGB"),
+					uint_indices: has_extension(&context, "OES_element_index_uint"),
+					vertex_array_objects: has_extension(&context, "OES_vertex_array_object"),
+					webgl2: false
+				};
+
+				(context, capabilities, instanced_arrays)
+			}
+		};
+
+		WebGlBackend {
+			buffers: HashMap::new(),
+			canvas: canvas.clone(),
+			capabilities,
+			context,
+			framebuffers: HashMap::new(),
+			instanced_arrays,
+			next_handle: 1,
+			programs: HashMap::new(),
+			renderbuffers: HashMap::new(),
+			shaders: HashMap::new(),
+			textures: HashMap::new(),
+			uniforms: HashMap::new()
+		}
+	}
+
+	/// Hands out a fresh handle id
+	fn allocate(&mut self) -> u32 {
+		let handle = self.next_handle;
+		self.next_handle += 1;
+
+		handle
+	}
+}
+
+impl RenderBackend for WebGlBackend {
+	fn allocate_texture(&mut self, width: u32, height: u32, format: TextureFormat) {
+		// WebGL2 wants a sized depth format, WebGL1 only knows the unsized one
+		let (internal_format, format, data_type) = match format {
+			TextureFormat::Rgba8 => (
+				WebGlRenderingContext::RGBA,
+				WebGlRenderingContext::RGBA,
+				WebGlRenderingContext::UNSIGNED_BYTE
+			),
+			TextureFormat::Depth if self.capabilities.webgl2 => (
+				WebGl2RenderingContext::DEPTH_COMPONENT24,
+				WebGlRenderingContext::DEPTH_COMPONENT,
+				WebGlRenderingContext::UNSIGNED_INT
+			),
+			TextureFormat::Depth => (
+				WebGlRenderingContext::DEPTH_COMPONENT,
+				WebGlRenderingContext::DEPTH_COMPONENT,
+				WebGlRenderingContext::UNSIGNED_SHORT
+			)
+		};
+
+		// Only fails on bad arguments, which would be a bug here
+		self.context
+			.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+				WebGlRenderingContext::TEXTURE_2D,
+				0,
+				internal_format as i32,
+				width as i32,
+				height as i32,
+				0,
+				format,
+				data_type,
+				None
+			)
+			.expect("Invalid texture allocation");
+	}
+
+	fn bind_buffer(&mut self, target: BufferTarget, buffer: Option<BufferHandle>) {
+		let buffer = buffer.and_then(|handle| self.buffers.get(&handle));
+		self.context.bind_buffer(buffer_target(target), buffer);
+	}
+
+	fn bind_framebuffer(&mut self, framebuffer: Option<FramebufferHandle>) {
+		let framebuffer = framebuffer.and_then(|handle| self.framebuffers.get(&handle));
+		self.context.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, framebuffer);
+	}
+
+	fn bind_texture(&mut self, unit: u32, target: TextureTarget, texture: Option<TextureHandle>) {
+		let texture = texture.and_then(|handle| self.textures.get(&handle));
+		self.context.active_texture(WebGlRenderingContext::TEXTURE0 + unit);
+		self.context.bind_texture(texture_target(target), texture);
+	}
+
+	fn buffer_data_f32(&mut self, target: BufferTarget, data: &[f32], usage: BufferUsage) {
+		// The view points straight into wasm memory, so it has to be used
+		// before anything else gets allocated
+		unsafe {
+			let view = Float32Array::view(data);
+			self.context.buffer_data_with_array_buffer_view(buffer_target(target), &view, buffer_usage(usage));
+		}
+	}
+
+	fn buffer_data_u8(&mut self, target: BufferTarget, data: &[u8], usage: BufferUsage) {
+		self.context.buffer_data_with_u8_array(buffer_target(target), data, buffer_usage(usage));
+	}
+
+	fn buffer_data_u16(&mut self, target: BufferTarget, data: &[u16], usage: BufferUsage) {
+		// Same deal as the f32 view
+		unsafe {
+			let view = Uint16Array::view(data);
+			self.context.buffer_data_with_array_buffer_view(buffer_target(target), &view, buffer_usage(usage));
+		}
+	}
+
+	fn buffer_data_u32(&mut self, target: BufferTarget, data: &[u32], usage: BufferUsage) {
+		// Same deal as the f32 view
+		unsafe {
+			let view = Uint32Array::view(data);
+			self.context.buffer_data_with_array_buffer_view(buffer_target(target), &view, buffer_usage(usage));
+		}
+	}
+
+	fn capabilities(&self) -> &Capabilities {
+		&self.capabilities
+	}
+
+	fn check_framebuffer_status(&mut self) -> Result<(), String> {
+		let status = self.context.check_framebuffer_status(WebGlRenderingContext::FRAMEBUFFER);
+		let problem = match status {
+			WebGlRenderingContext::FRAMEBUFFER_COMPLETE => return Ok(()),
+			WebGlRenderingContext::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => "an attachment is incomplete",
+			WebGlRenderingContext::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => "nothing is attached",
+			WebGlRenderingContext::FRAMEBUFFER_INCOMPLETE_DIMENSIONS => "attachments are different sizes",
+			WebGlRenderingContext::FRAMEBUFFER_UNSUPPORTED => "these attachments aren't supported together",
+			_ => return Err(format!("Framebuffer status 0x{:x}", status))
+		};
+
+		Err(format!("Framebuffer incomplete, {}", problem))
+	}
+
+	fn clear(&mut self, color: bool, depth: bool) {
+		let mut mask = 0;
+		if color {
+			mask |= WebGlRenderingContext::COLOR_BUFFER_BIT;
+		}
+		if depth {
+			mask |= WebGlRenderingContext::DEPTH_BUFFER_BIT;
+		}
+
+		self.context.clear(mask);
+	}
+
+	fn clear_color(&mut self, red: f32, green: f32, blue: f32, alpha: f32) {
+		self.context.clear_color(red, green, blue, alpha);
+	}
+
+	fn compile_shader(&mut self, stage: ShaderStage, source: &str) -> Result<ShaderHandle, String> {
+		let context = &self.context;
+		let shader_type = match stage {
+			ShaderStage::Vertex => WebGlRenderingContext::VERTEX_SHADER,
+			ShaderStage::Fragment => WebGlRenderingContext::FRAGMENT_SHADER
+		};
+
+		// Create the shader
+		let shader = context
+			.create_shader(shader_type)
+			.ok_or_else(|| String::from("Unable to create shader object"))?;
+
+		// Load and compile
+		context.shader_source(&shader, source);
+		context.compile_shader(&shader);
+
+		// Check if it went well
+		if context
+			.get_shader_parameter(&shader, WebGlRenderingContext::COMPILE_STATUS)
+			.as_bool()
+			.unwrap_or(false)
+		{
+			let handle = ShaderHandle(self.allocate());
+			self.shaders.insert(handle, shader);
+
+			Ok(handle)
+		} else {
+			// Return the error
+			let log = context
+				.get_shader_info_log(&shader)
+				.unwrap_or_else(|| String::from("Unknown error creating shader"));
+			context.delete_shader(Some(&shader));
+
+			Err(log)
+		}
+	}
+
+	fn create_buffer(&mut self) -> Option<BufferHandle> {
+		let buffer = self.context.create_buffer()?;
+		let handle = BufferHandle(self.allocate());
+		self.buffers.insert(handle, buffer);
+
+		Some(handle)
+	}
+
+	fn create_framebuffer(&mut self) -> Option<FramebufferHandle> {
+		let framebuffer = self.context.create_framebuffer()?;
+		let handle = FramebufferHandle(self.allocate());
+		self.framebuffers.insert(handle, framebuffer);
+
+		Some(handle)
+	}
+
+	fn create_renderbuffer(&mut self) -> Option<RenderbufferHandle> {
+		let renderbuffer = self.context.create_renderbuffer()?;
+		let handle = RenderbufferHandle(self.allocate());
+		self.renderbuffers.insert(handle, renderbuffer);
+
+		Some(handle)
+	}
+
+	fn create_texture(&mut self) -> Option<TextureHandle> {
+		let texture = self.context.create_texture()?;
+		let handle = TextureHandle(self.allocate());
+		self.textures.insert(handle, texture);
+
+		Some(handle)
+	}
+
+	fn delete_buffer(&mut self, buffer: BufferHandle) {
+		if let Some(buffer) = self.buffers.remove(&buffer) {
+			self.context.delete_buffer(Some(&buffer));
+		}
+	}
+
+	fn delete_framebuffer(&mut self, framebuffer: FramebufferHandle) {
+		if let Some(framebuffer) = self.framebuffers.remove(&framebuffer) {
+			self.context.delete_framebuffer(Some(&framebuffer));
+		}
+	}
+
+	fn delete_program(&mut self, program: ProgramHandle) {
+		if let Some(program) = self.programs.remove(&program) {
+			self.context.delete_program(Some(&program));
+		}
+	}
+
+	fn delete_renderbuffer(&mut self, renderbuffer: RenderbufferHandle) {
+		if let Some(renderbuffer) = self.renderbuffers.remove(&renderbuffer) {
+			self.context.delete_renderbuffer(Some(&renderbuffer));
+		}
+	}
+
+	fn delete_shader(&mut self, shader: ShaderHandle) {
+		if let Some(shader) = self.shaders.remove(&shader) {
+			self.context.delete_shader(Some(&shader));
+		}
+	}
+
+	fn delete_texture(&mut self, texture: TextureHandle) {
+		if let Some(texture) = self.textures.remove(&texture) {
+			self.context.delete_texture(Some(&texture));
+		}
+	}
+
+	fn depth_renderbuffer_storage(&mut self, renderbuffer: RenderbufferHandle, width: u32, height: u32) {
+		let renderbuffer = self.renderbuffers.get(&renderbuffer);
+		let target = WebGlRenderingContext::RENDERBUFFER;
+		self.context.bind_renderbuffer(target, renderbuffer);
+		self.context.renderbuffer_storage(target, WebGlRenderingContext::DEPTH_COMPONENT16, width as i32, height as i32);
+		self.context.bind_renderbuffer(target, None);
+	}
+
+	fn disable_vertex_attrib_array(&mut self, index: u32) {
+		self.context.disable_vertex_attrib_array(index);
+	}
+
+	fn draw_arrays(&mut self, primitive: Primitive, first: i32, count: i32) {
+		self.context.draw_arrays(primitive_mode(primitive), first, count);
+	}
+
+	fn draw_elements(&mut self, primitive: Primitive, count: i32, index_type: IndexType, offset: i32) {
+		self.context.draw_elements_with_i32(primitive_mode(primitive), count, gl_index_type(index_type), offset);
+	}
+
+	fn draw_elements_instanced(
+		&mut self,
+		primitive: Primitive,
+		count: i32,
+		index_type: IndexType,
+		offset: i32,
+		instance_count: i32
+	) {
+		let mode = primitive_mode(primitive);
+		let index_type = gl_index_type(index_type);
+
+		// WebGL2 has it built in, WebGL1 needs the extension
+		if self.capabilities.webgl2 {
+			self.context.unchecked_ref::<WebGl2RenderingContext>()
+				.draw_elements_instanced_with_i32(mode, count, index_type, offset, instance_count);
+		} else if let Some(instanced_arrays) = &self.instanced_arrays {
+			instanced_arrays.draw_elements_instanced_angle_with_i32(mode, count, index_type, offset, instance_count);
+		}
+	}
+
+	fn drawing_buffer_size(&self) -> (u32, u32) {
+		let width = self.context.drawing_buffer_width() as u32;
+		let height = self.context.drawing_buffer_height() as u32;
+
+		(width, height)
+	}
+
+	fn enable_vertex_attrib_array(&mut self, index: u32) {
+		self.context.enable_vertex_attrib_array(index);
+	}
+
+	fn framebuffer_renderbuffer(&mut self, renderbuffer: Option<RenderbufferHandle>) {
+		let renderbuffer = renderbuffer.and_then(|handle| self.renderbuffers.get(&handle));
+		self.context.framebuffer_renderbuffer(
+			WebGlRenderingContext::FRAMEBUFFER,
+			WebGlRenderingContext::DEPTH_ATTACHMENT,
+			WebGlRenderingContext::RENDERBUFFER,
+			renderbuffer
+		);
+	}
+
+	fn framebuffer_texture(&mut self, attachment: Attachment, texture: Option<TextureHandle>) {
+		let texture = texture.and_then(|handle| self.textures.get(&handle));
+		let attachment = match attachment {
+			Attachment::Color => WebGlRenderingContext::COLOR_ATTACHMENT0,
+			Attachment::Depth => WebGlRenderingContext::DEPTH_ATTACHMENT
+		};
+		self.context.framebuffer_texture_2d(
+			WebGlRenderingContext::FRAMEBUFFER,
+			attachment,
+			WebGlRenderingContext::TEXTURE_2D,
+			texture,
+			0
+		);
+	}
+
+	fn generate_mipmap(&mut self, target: TextureTarget) {
+		self.context.generate_mipmap(texture_target(target));
+	}
+
+	fn get_uniform_location(&mut self, program: ProgramHandle, name: &str) -> Option<UniformHandle> {
+		let program = self.programs.get(&program)?;
+		let location = self.context.get_uniform_location(program, name)?;
+
+		let handle = UniformHandle(self.allocate());
+		self.uniforms.insert(handle, location);
+
+		Some(handle)
+	}
+
+	fn link_program(
+		&mut self,
+		vertex_shader: ShaderHandle,
+		fragment_shader: ShaderHandle,
+		attributes: &[(u32, &str)]
+	) -> Result<ProgramHandle, String> {
+		let context = &self.context;
+
+		// Find the compiled shaders
+		let vertex_shader = self.shaders.get(&vertex_shader)
+			.ok_or_else(|| String::from("Unknown vertex shader"))?;
+		let frag_shader = self.shaders.get(&fragment_shader)
+			.ok_or_else(|| String::from("Unknown fragment shader"))?;
+
+		// Create the program
+		let program = context
+			.create_program()
+			.ok_or_else(|| String::from("Unable to create shader object"))?;
+
+		// Add and link the shaders
+		context.attach_shader(&program, vertex_shader);
+		context.attach_shader(&program, frag_shader);
+
+		// Pin attributes to their locations
+		for &(location, name) in attributes {
+			context.bind_attrib_location(&program, location, name);
+		}
+
+		context.link_program(&program);
+
+		// Check if it went well
+		if context
+			.get_program_parameter(&program, WebGlRenderingContext::LINK_STATUS)
+			.as_bool()
+			.unwrap_or(false)
+		{
+			let handle = ProgramHandle(self.allocate());
+			self.programs.insert(handle, program);
+
+			Ok(handle)
+		} else {
+			// Return the error
+			let log = context
+				.get_program_info_log(&program)
+				.unwrap_or_else(|| String::from("Unknown error creating program object"));
+			context.delete_program(Some(&program));
+
+			Err(log)
+		}
+	}
+
+	fn set_render_state(&mut self, state: &RenderState) {
+		let gl = &self.context;
+
+		// Blending
+		let factors = match state.blend_mode {
+			BlendMode::None => None,
+			BlendMode::Alpha => Some((WebGlRenderingContext::SRC_ALPHA, WebGlRenderingContext::ONE_MINUS_SRC_ALPHA)),
+			BlendMode::Additive => Some((WebGlRenderingContext::ONE, WebGlRenderingContext::ONE)),
+			BlendMode::Multiply => Some((WebGlRenderingContext::DST_COLOR, WebGlRenderingContext::ZERO)),
+			BlendMode::Premultiplied => Some((WebGlRenderingContext::ONE, WebGlRenderingContext::ONE_MINUS_SRC_ALPHA))
+		};
+		match factors {
+			Some((source, destination)) => {
+				gl.enable(WebGlRenderingContext::BLEND);
+				gl.blend_func(source, destination);
+			},
+			None => gl.disable(WebGlRenderingContext::BLEND)
+		}
+
+		// Depth
+		set_capability(gl, WebGlRenderingContext::DEPTH_TEST, state.depth_test);
+		gl.depth_mask(state.depth_write);
+		gl.depth_func(match state.depth_func {
+			DepthFunc::Never => WebGlRenderingContext::NEVER,
+			DepthFunc::Less => WebGlRenderingContext::LESS,
+			DepthFunc::Equal => WebGlRenderingContext::EQUAL,
+			DepthFunc::LessEqual => WebGlRenderingContext::LEQUAL,
+			DepthFunc::Greater => WebGlRenderingContext::GREATER,
+			DepthFunc::NotEqual => WebGlRenderingContext::NOTEQUAL,
+			DepthFunc::GreaterEqual => WebGlRenderingContext::GEQUAL,
+			DepthFunc::Always => WebGlRenderingContext::ALWAYS
+		});
+
+		// Culling
+		match state.cull_mode {
+			CullMode::None => gl.disable(WebGlRenderingContext::CULL_FACE),
+			CullMode::Front => {
+				gl.enable(WebGlRenderingContext::CULL_FACE);
+				gl.cull_face(WebGlRenderingContext::FRONT);
+			},
+			CullMode::Back => {
+				gl.enable(WebGlRenderingContext::CULL_FACE);
+				gl.cull_face(WebGlRenderingContext::BACK);
+			},
+			CullMode::FrontAndBack => {
+				gl.enable(WebGlRenderingContext::CULL_FACE);
+				gl.cull_face(WebGlRenderingContext::FRONT_AND_BACK);
+			}
+		}
+		gl.front_face(match state.front_face {
+			FrontFace::Ccw => WebGlRenderingContext::CCW,
+			FrontFace::Cw => WebGlRenderingContext::CW
+		});
+	}
+
+	fn resize_drawing_buffer(&mut self, width: u32, height: u32) {
+		self.canvas.set_width(width);
+		self.canvas.set_height(height);
+	}
+
+	fn set_uniform(&mut self, location: UniformHandle, value: &UniformValue) {
+		let gl = &self.context;
+		let location = self.uniforms.get(&location);
+
+		match value {
+			UniformValue::Float(x) => gl.uniform1f(location, *x),
+			UniformValue::Int(x) => gl.uniform1i(location, *x),
+			UniformValue::Bool(x) => gl.uniform1i(location, *x as i32),
+			UniformValue::Vec2(v) => gl.uniform2fv_with_f32_array(location, v),
+			UniformValue::Vec3(v) => gl.uniform3fv_with_f32_array(location, v),
+			UniformValue::Vec4(v) => gl.uniform4fv_with_f32_array(location, v),
+			UniformValue::IVec2(v) => gl.uniform2iv_with_i32_array(location, v),
+			UniformValue::IVec3(v) => gl.uniform3iv_with_i32_array(location, v),
+			UniformValue::IVec4(v) => gl.uniform4iv_with_i32_array(location, v),
+			UniformValue::Mat3(m) => gl.uniform_matrix3fv_with_f32_array(location, false, m),
+			UniformValue::Mat4(m) => gl.uniform_matrix4fv_with_f32_array(location, false, m),
+			UniformValue::Sampler2D(unit) => gl.uniform1i(location, *unit),
+			UniformValue::SamplerCube(unit) => gl.uniform1i(location, *unit),
+			UniformValue::Array(elements) => {
+				// Arrays go up in one call, flattened by element type
+				let mut floats = Vec::new();
+				let mut ints = Vec::new();
+				for element in elements {
+					flatten_uniform(element, &mut floats, &mut ints);
+				}
+
+				match elements.first() {
+					Some(UniformValue::Float(_)) => gl.uniform1fv_with_f32_array(location, &floats),
+					Some(UniformValue::Vec2(_)) => gl.uniform2fv_with_f32_array(location, &floats),
+					Some(UniformValue::Vec3(_)) => gl.uniform3fv_with_f32_array(location, &floats),
+					Some(UniformValue::Vec4(_)) => gl.uniform4fv_with_f32_array(location, &floats),
+					Some(UniformValue::Mat3(_)) => gl.uniform_matrix3fv_with_f32_array(location, false, &floats),
+					Some(UniformValue::Mat4(_)) => gl.uniform_matrix4fv_with_f32_array(location, false, &floats),
+					Some(UniformValue::IVec2(_)) => gl.uniform2iv_with_i32_array(location, &ints),
+					Some(UniformValue::IVec3(_)) => gl.uniform3iv_with_i32_array(location, &ints),
+					Some(UniformValue::IVec4(_)) => gl.uniform4iv_with_i32_array(location, &ints),
+					Some(_) => gl.uniform1iv_with_i32_array(location, &ints),
+					None => ()
+				}
+			}
+		}
+	}
+
+	fn set_texture_options(&mut self, target: TextureTarget, options: &TextureOptions) {
+		let gl = &self.context;
+		let target = texture_target(target);
+
+		// Wrapping
+		gl.tex_parameteri(target, WebGlRenderingContext::TEXTURE_WRAP_S, wrap_mode(options.wrap_s));
+		gl.tex_parameteri(target, WebGlRenderingContext::TEXTURE_WRAP_T, wrap_mode(options.wrap_t));
+
+		// Filtering
+		let min_filter = match (options.min_filter, options.mipmaps) {
+			(FilterMode::Nearest, false) => WebGlRenderingContext::NEAREST,
+			(FilterMode::Linear, false) => WebGlRenderingContext::LINEAR,
+			(FilterMode::Nearest, true) => WebGlRenderingContext::NEAREST_MIPMAP_NEAREST,
+			(FilterMode::Linear, true) => WebGlRenderingContext::LINEAR_MIPMAP_LINEAR
+		};
+		let mag_filter = match options.mag_filter {
+			FilterMode::Nearest => WebGlRenderingContext::NEAREST,
+			FilterMode::Linear => WebGlRenderingContext::LINEAR
+		};
+		gl.tex_parameteri(target, WebGlRenderingContext::TEXTURE_MIN_FILTER, min_filter as i32);
+		gl.tex_parameteri(target, WebGlRenderingContext::TEXTURE_MAG_FILTER, mag_filter as i32);
+	}
+
+	fn tex_image_2d(&mut self, width: u32, height: u32, pixels: &[u8]) {
+		let rgba = WebGlRenderingContext::RGBA;
+
+		// Only fails on bad arguments, which would be a bug here
+		self.context
+			.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+				WebGlRenderingContext::TEXTURE_2D,
+				0,
+				rgba as i32,
+				width as i32,
+				height as i32,
+				0,
+				rgba,
+				WebGlRenderingContext::UNSIGNED_BYTE,
+				Some(pixels)
+			)
+			.expect("Invalid texture upload");
+	}
+
+	fn tex_image_cube_face(&mut self, face: CubeFace, width: u32, height: u32, pixels: &[u8]) {
+		let rgba = WebGlRenderingContext::RGBA;
+
+		// Only fails on bad arguments, which would be a bug here
+		self.context
+			.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+				cube_face(face),
+				0,
+				rgba as i32,
+				width as i32,
+				height as i32,
+				0,
+				rgba,
+				WebGlRenderingContext::UNSIGNED_BYTE,
+				Some(pixels)
+			)
+			.expect("Invalid cube map upload");
+	}
+
+	fn use_program(&mut self, program: Option<ProgramHandle>) {
+		let program = program.and_then(|handle| self.programs.get(&handle));
+		self.context.use_program(program);
+	}
+
+	fn vertex_attrib_pointer(
+		&mut self,
+		index: u32,
+		size: i32,
+		component: VertexComponent,
+		normalized: bool,
+		stride: i32,
+		offset: i32
+	) {
+		let component = match component {
+			VertexComponent::Float => WebGlRenderingContext::FLOAT
+		};
+
+		self.context.vertex_attrib_pointer_with_i32(index, size, component, normalized, stride, offset);
+	}
+
+	fn vertex_attrib_divisor(&mut self, index: u32, divisor: u32) {
+		if self.capabilities.webgl2 {
+			self.context.unchecked_ref::<WebGl2RenderingContext>().vertex_attrib_divisor(index, divisor);
+		} else if let Some(instanced_arrays) = &self.instanced_arrays {
+			instanced_arrays.vertex_attrib_divisor_angle(index, divisor);
+		}
+	}
+
+	fn viewport(&mut self, x: u32, y: u32, width: u32, height: u32) {
+		self.context.viewport(x as i32, y as i32, width as i32, height as i32);
+	}
+}
+
+/// Converts a buffer target to its GL enum
+fn buffer_target(target: BufferTarget) -> u32 {
+	match target {
+		BufferTarget::Array => WebGlRenderingContext::ARRAY_BUFFER,
+		BufferTarget::ElementArray => WebGlRenderingContext::ELEMENT_ARRAY_BUFFER
+	}
+}
+
+/// Converts a cube map face to the GL enum its uploads go to
+fn cube_face(face: CubeFace) -> u32 {
+	match face {
+		CubeFace::PositiveX => WebGlRenderingContext::TEXTURE_CUBE_MAP_POSITIVE_X,
+		CubeFace::NegativeX => WebGlRenderingContext::TEXTURE_CUBE_MAP_NEGATIVE_X,
+		CubeFace::PositiveY => WebGlRenderingContext::TEXTURE_CUBE_MAP_POSITIVE_Y,
+		CubeFace::NegativeY => WebGlRenderingContext::TEXTURE_CUBE_MAP_NEGATIVE_Y,
+		CubeFace::PositiveZ => WebGlRenderingContext::TEXTURE_CUBE_MAP_POSITIVE_Z,
+		CubeFace::NegativeZ => WebGlRenderingContext::TEXTURE_CUBE_MAP_NEGATIVE_Z
+	}
+}
+
+/// Appends the components of a uniform value to flat float or int arrays
+fn flatten_uniform(value: &UniformValue, floats: &mut Vec<f32>, ints: &mut Vec<i32>) {
+	match value {
+		UniformValue::Float(x) => floats.push(*x),
+		UniformValue::Int(x) => ints.push(*x),
+		UniformValue::Bool(x) => ints.push(*x as i32),
+		UniformValue::Vec2(v) => floats.extend_from_slice(v),
+		UniformValue::Vec3(v) => floats.extend_from_slice(v),
+		UniformValue::Vec4(v) => floats.extend_from_slice(v),
+		UniformValue::IVec2(v) => ints.extend_from_slice(v),
+		UniformValue::IVec3(v) => ints.extend_from_slice(v),
+		UniformValue::IVec4(v) => ints.extend_from_slice(v),
+		UniformValue::Mat3(m) => floats.extend_from_slice(m),
+		UniformValue::Mat4(m) => floats.extend_from_slice(m),
+		UniformValue::Sampler2D(unit) => ints.push(*unit),
+		UniformValue::SamplerCube(unit) => ints.push(*unit),
+		UniformValue::Array(elements) => {
+			for element in elements {
+				flatten_uniform(element, floats, ints);
+			}
+		}
+	}
+}
+
+/// Converts an index type to its GL enum
+fn gl_index_type(index_type: IndexType) -> u32 {
+	match index_type {
+		IndexType::U8 => WebGlRenderingContext::UNSIGNED_BYTE,
+		IndexType::U16 => WebGlRenderingContext::UNSIGNED_SHORT,
+		IndexType::U32 => WebGlRenderingContext::UNSIGNED_INT
+	}
+}
+
+/// Asks how big something is allowed to get
+///
+/// * `parameter` - which limit, like MAX_TEXTURE_SIZE
+/// * `fallback` - what to assume if the browser doesn't say
+fn get_limit(context: &WebGlRenderingContext, parameter: u32, fallback: u32) -> u32 {
+	context.get_parameter(parameter).ok()
+		.and_then(|value| value.as_f64())
+		.map_or(fallback, |value| value as u32)
+}
+
+/// Turns on a WebGL extension, if the browser has it
+///
+/// Returns - whether the extension is available
+fn has_extension(context: &WebGlRenderingContext, name: &str) -> bool {
+	matches!(context.get_extension(name), Ok(Some(_)))
+}
+
+/// Turns a GL capability on or off
+fn set_capability(context: &WebGlRenderingContext, capability: u32, enabled: bool) {
+	if enabled {
+		context.enable(capability);
+	} else {
+		context.disable(capability);
+	}
+}
+
+/// Converts a primitive to the GL mode that draws it
+fn primitive_mode(primitive: Primitive) -> u32 {
+	match primitive {
+		Primitive::Triangles => WebGlRenderingContext::TRIANGLES,
+		Primitive::Lines => WebGlRenderingContext::LINES
+	}
+}
+
+/// Converts a buffer usage hint to its GL enum
+fn buffer_usage(usage: BufferUsage) -> u32 {
+	match usage {
+		BufferUsage::Static => WebGlRenderingContext::STATIC_DRAW,
+		BufferUsage::Dynamic => WebGlRenderingContext::DYNAMIC_DRAW,
+		BufferUsage::Stream => WebGlRenderingContext::STREAM_DRAW
+	}
+}
+
+/// Converts a texture target to its GL enum
+fn texture_target(target: TextureTarget) -> u32 {
+	match target {
+		TextureTarget::Texture2D => WebGlRenderingContext::TEXTURE_2D,
+		TextureTarget::CubeMap => WebGlRenderingContext::TEXTURE_CUBE_MAP
+	}
+}
+
+/// Converts a wrap mode to its GL enum
+fn wrap_mode(mode: WrapMode) -> i32 {
+	let mode = match mode {
+		WrapMode::Repeat => WebGlRenderingContext::REPEAT,
+		WrapMode::ClampToEdge => WebGlRenderingContext::CLAMP_TO_EDGE,
+		WrapMode::MirroredRepeat => WebGlRenderingContext::MIRRORED_REPEAT
+	};
+
+	mode as i32
+}
@@ -0,0 +1,111 @@
+use nalgebra::{Matrix4, Point3, Vector3};
+
+/// A box lined up with the axes that something fits inside
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+	/// The corner with the smallest x, y, and z
+	pub min: Vector3<f32>,
+	/// The corner with the largest x, y, and z
+	pub max: Vector3<f32>
+}
+
+impl Aabb {
+	/// Gets the point in the middle of the box
+	pub fn center(&self) -> Vector3<f32> {
+		(self.min + self.max) / 2.0
+	}
+
+	/// Fits a box around some points
+	///
+	/// * `points` - the points to fit, where none gives an empty box at the origin
+	pub fn from_points(points: &[[f32; 3]]) -> Aabb {
+		if points.is_empty() {
+			return Aabb {
+				min: Vector3::zeros(),
+				max: Vector3::zeros()
+			};
+		}
+
+		let mut min = Vector3::repeat(f32::INFINITY);
+		let mut max = Vector3::repeat(f32::NEG_INFINITY);
+		for point in points {
+			let point = Vector3::from(*point);
+			min = min.inf(&point);
+			max = max.sup(&point);
+		}
+
+		Aabb { min, max }
+	}
+
+	/// Gets how far the box reaches from its center along each axis
+	pub fn half_extents(&self) -> Vector3<f32> {
+		(self.max - self.min) / 2.0
+	}
+
+	/// Fits a box around this one after it's been moved by a matrix
+	///
+	/// * `matrix` - an affine transform, like a model matrix
+	pub fn transformed(&self, matrix: &Matrix4<f32>) -> Aabb {
+		// Each axis of the new box reaches as far as the old axes, turned,
+		// reach along it
+		let center = matrix.transform_point(&Point3::from(self.center())).coords;
+		let half_extents = self.half_extents();
+		let half_extents = axes(matrix).iter()
+			.zip(half_extents.iter())
+			.fold(Vector3::zeros(), |sum, (axis, reach)| sum + axis.abs() * *reach);
+
+		Aabb {
+			min: center - half_extents,
+			max: center + half_extents
+		}
+	}
+}
+
+/// A sphere that something fits inside
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingSphere {
+	/// The middle of the sphere
+	pub center: Vector3<f32>,
+	/// How far the sphere reaches from its center
+	pub radius: f32
+}
+
+impl BoundingSphere {
+	/// Fits a sphere around some points, centered on the box around them
+	///
+	/// Not the smallest sphere there is, but close and quick to find.
+	///
+	/// * `points` - the points to fit, where none gives an empty sphere
+	pub fn from_points(points: &[[f32; 3]]) -> BoundingSphere {
+		let center = Aabb::from_points(points).center();
+		let radius = points.iter()
+			.map(|point| (Vector3::from(*point) - center).norm())
+			.fold(0.0, f32::max);
+
+		BoundingSphere { center, radius }
+	}
+
+	/// Fits a sphere around this one after it's been moved by a matrix
+	///
+	/// * `matrix` - an affine transform, like a model matrix
+	pub fn transformed(&self, matrix: &Matrix4<f32>) -> BoundingSphere {
+		// Stretched on any axis, it has to grow by the most it was stretched
+		let scale = axes(matrix).iter().map(|axis| axis.norm()).fold(0.0, f32::max);
+
+		BoundingSphere {
+			center: matrix.transform_point(&Point3::from(self.center)).coords,
+			radius: self.radius * scale
+		}
+	}
+}
+
+/// Gets where a matrix sends the x, y, and z axes, ignoring translation
+///
+/// * `matrix` - an affine transform, like a model matrix
+fn axes(matrix: &Matrix4<f32>) -> [Vector3<f32>; 3] {
+	[
+		matrix.transform_vector(&Vector3::x()),
+		matrix.transform_vector(&Vector3::y()),
+		matrix.transform_vector(&Vector3::z())
+	]
+}
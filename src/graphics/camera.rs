@@ -1,11 +1,124 @@
+use std::f32::consts::PI;
+
 use nalgebra::{Matrix4, Point3, Vector3};
 
+/// The closest a near plane is allowed to get to the camera
+///
+/// A near plane at 0 squashes the entire depth range into nothing.
+const MIN_NEAR: f32 = 0.001;
+
+/// Describes how a camera projects the world onto the screen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Projection {
+	/// Width over height of the view
+	aspect_ratio: f32,
+	/// Distance to the far clipping plane
+	far: f32,
+	/// Vertical field of view (radians)
+	fov_y: f32,
+	/// Projection matrix for rendering
+	matrix: Matrix4<f32>,
+	/// Distance to the near clipping plane
+	near: f32
+}
+
+#[allow(dead_code)]
+impl Projection {
+	/// Get the aspect ratio of this projection
+	pub fn get_aspect_ratio(&self) -> f32 {
+		self.aspect_ratio
+	}
+
+	/// Get the distances to the near and far clipping planes
+	pub fn get_clip_planes(&self) -> (f32, f32) {
+		(self.near, self.far)
+	}
+
+	/// Get the vertical field of view (radians)
+	pub fn get_fov_y(&self) -> f32 {
+		self.fov_y
+	}
+
+	/// Get the projection matrix for rendering
+	pub fn get_matrix(&self) -> &Matrix4<f32> {
+		&self.matrix
+	}
+
+	/// Creates a new perspective projection
+	///
+	/// The clipping planes are kept sane, see set_clip_planes.
+	///
+	/// * `aspect_ratio` - width over height of the view
+	/// * `fov_y` - vertical field of view (radians)
+	/// * `near` - distance to the near clipping plane
+	/// * `far` - distance to the far clipping plane
+	pub fn new(aspect_ratio: f32, fov_y: f32, near: f32, far: f32) -> Projection {
+		let mut projection = Projection {
+			aspect_ratio,
+			far,
+			fov_y,
+			matrix: Matrix4::identity(),
+			near
+		};
+		projection.set_clip_planes(near, far);
+
+		projection
+	}
+
+	/// Changes the aspect ratio, like after the view gets resized
+	///
+	/// * `aspect_ratio` - width over height of the view
+	pub fn set_aspect_ratio(&mut self, aspect_ratio: f32) {
+		self.aspect_ratio = aspect_ratio;
+		self.update_matrix();
+	}
+
+	/// Changes the clipping planes
+	///
+	/// The near plane is pushed out to at least MIN_NEAR, and the far plane is
+	/// pushed past the near plane, so the depth range never collapses.
+	///
+	/// * `near` - distance to the near clipping plane
+	/// * `far` - distance to the far clipping plane
+	pub fn set_clip_planes(&mut self, near: f32, far: f32) {
+		self.near = near.max(MIN_NEAR);
+		self.far = far.max(self.near * 2.0);
+		self.update_matrix();
+	}
+
+	/// Changes the vertical field of view
+	///
+	/// * `fov_y` - vertical field of view (radians)
+	pub fn set_fov_y(&mut self, fov_y: f32) {
+		self.fov_y = fov_y;
+		self.update_matrix();
+	}
+
+	/// Do the math to update the projection matrix
+	fn update_matrix(&mut self) {
+		self.matrix = Matrix4::new_perspective(self.aspect_ratio, self.fov_y, self.near, self.far);
+	}
+}
+
+impl Default for Projection {
+	/// A 16:9 view with a 90 degree horizontal field of view, clipped from
+	/// 0.1 to 100 units away
+	fn default() -> Projection {
+		// Convert the 16:9 x FOV to a y FOV in radians
+		let fov_y = 90.0 * 9.0 / 16.0 * PI / 180.0;
+
+		Projection::new(16.0 / 9.0, fov_y, 0.1, 100.0)
+	}
+}
+
 /// Represents a camera that can render a view of the world
 pub struct Camera {
 	/// The coordinates this camera is at
 	location: Point3<f32>,
 	/// View matrix specific to this camera
 	matrix: Matrix4<f32>,
+	/// How this camera projects what it sees
+	projection: Projection,
 	/// The amount (in radians) this camera has rolled clockwise from world up
 	roll: f32,
 	/// The coordinates this camera is aiming at
@@ -38,6 +151,21 @@ impl Camera {
 		self.target = new.target;
 	}
 
+	/// Get the coordinates this camera is at
+	pub fn get_location(&self) -> &Point3<f32> {
+		&self.location
+	}
+
+	/// Get the projection of this camera
+	pub fn get_projection(&self) -> &Projection {
+		&self.projection
+	}
+
+	/// Get the projection of this camera, mutably
+	pub fn get_projection_mut(&mut self) -> &mut Projection {
+		&mut self.projection
+	}
+
 	/// Get the view matrix from this camera for rendering
 	pub fn get_view_matrix(&self) -> &Matrix4<f32> {
 		&self.matrix
@@ -68,6 +196,7 @@ impl Camera {
 		Camera {
 			location: location,
 			matrix: matrix,
+			projection: Projection::default(),
 			roll: roll,
 			target: target
 		}
@@ -90,6 +219,7 @@ impl Camera {
 		Camera {
 			location: location,
 			matrix: matrix,
+			projection: Projection::default(),
 			roll: roll,
 			target: target
 		}
@@ -0,0 +1,217 @@
+use nalgebra::{Matrix4, RowVector4, Vector3};
+
+use super::bounds::{Aabb, BoundingSphere};
+
+/// How many objects frustum culling threw out and kept
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CullStats {
+	/// Objects skipped because the camera couldn't see them
+	pub culled: u32,
+	/// Objects that were drawn
+	pub visible: u32
+}
+
+/// A flat surface that splits space in two
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plane {
+	/// Points to the side that counts as inside, one unit long
+	pub normal: Vector3<f32>,
+	/// Added to the dot product of a point and the normal, gives how far
+	/// inside the point is
+	pub distance: f32
+}
+
+impl Plane {
+	/// Gets how far a point is on the inside of the plane, negative if it's
+	/// outside
+	///
+	/// * `point` - the point to measure
+	pub fn distance_to(&self, point: &Vector3<f32>) -> f32 {
+		self.normal.dot(point) + self.distance
+	}
+
+	/// Makes a plane from the a, b, c, and d of ax + by + cz + d = 0
+	///
+	/// * `row` - the four coefficients, which don't need to be normalized
+	fn from_row(row: RowVector4<f32>) -> Plane {
+		let normal = Vector3::new(row[0], row[1], row[2]);
+		let length = normal.norm();
+
+		// A degenerate matrix makes a plane everything is inside of
+		if length <= f32::EPSILON {
+			return Plane {
+				normal: Vector3::zeros(),
+				distance: 0.0
+			};
+		}
+
+		Plane {
+			normal: normal / length,
+			distance: row[3] / length
+		}
+	}
+}
+
+/// The space a camera can see, bounded by six planes facing inwards
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+	/// Left, right, bottom, top, near, then far
+	planes: [Plane; 6]
+}
+
+impl Frustum {
+	/// Finds the planes of what a camera sees
+	///
+	/// * `view_projection` - projection times view, from world to clip space
+	pub fn from_matrix(view_projection: &Matrix4<f32>) -> Frustum {
+		// Inside is where each clip coordinate is between -w and w
+		let x = view_projection.row(0).into_owned();
+		let y = view_projection.row(1).into_owned();
+		let z = view_projection.row(2).into_owned();
+		let w = view_projection.row(3).into_owned();
+
+		Frustum {
+			planes: [
+				Plane::from_row(w + x),
+				Plane::from_row(w - x),
+				Plane::from_row(w + y),
+				Plane::from_row(w - y),
+				Plane::from_row(w + z),
+				Plane::from_row(w - z)
+			]
+		}
+	}
+
+	/// Gets the six planes, left, right, bottom, top, near, then far
+	pub fn get_planes(&self) -> &[Plane; 6] {
+		&self.planes
+	}
+
+	/// Whether any of a box could be seen
+	///
+	/// Can say yes for boxes just outside a corner, but never says no to one
+	/// that can be seen.
+	///
+	/// * `aabb` - the box in world space
+	pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+		let center = aabb.center();
+		let half_extents = aabb.half_extents();
+
+		// The box is outside a plane if even its corner furthest in is
+		self.planes.iter().all(|plane| {
+			let reach = plane.normal.abs().dot(&half_extents);
+			plane.distance_to(&center) + reach >= 0.0
+		})
+	}
+
+	/// Whether any of a sphere could be seen
+	///
+	/// Can say yes for spheres just outside a corner, but never says no to
+	/// one that can be seen.
+	///
+	/// * `sphere` - the sphere in world space
+	pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+		self.planes.iter().all(|plane| plane.distance_to(&sphere.center) >= -sphere.radius)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{f32::consts::FRAC_PI_2, rc::Rc};
+
+	use crate::{
+		graphics::{
+			backend::headless::HeadlessBackend,
+			material::Material,
+			mesh::Indices,
+			shadows::ShadowSettings,
+			Graphics
+		},
+		logic::{object::Object, world::World}
+	};
+	use super::*;
+
+	/// A camera at the origin looking down -z, seeing 90 degrees each way,
+	/// from 1 to 100 units away
+	fn frustum() -> Frustum {
+		Frustum::from_matrix(&Matrix4::new_perspective(1.0, FRAC_PI_2, 1.0, 100.0))
+	}
+
+	fn sphere(x: f32, y: f32, z: f32) -> BoundingSphere {
+		BoundingSphere {
+			center: Vector3::new(x, y, z),
+			radius: 1.0
+		}
+	}
+
+	fn cube(x: f32, y: f32, z: f32) -> Aabb {
+		Aabb::from_points(&[[x - 1.0, y - 1.0, z - 1.0], [x + 1.0, y + 1.0, z + 1.0]])
+	}
+
+	#[test]
+	fn planes_face_inwards() {
+		let frustum = frustum();
+		let planes = frustum.get_planes();
+		let inside = Vector3::new(0.0, 0.0, -10.0);
+		assert!(planes.iter().all(|plane| plane.distance_to(&inside) > 0.0));
+
+		// Near and far sit at their distances down -z
+		let (near, far) = (planes[4], planes[5]);
+		assert!(near.distance_to(&Vector3::new(0.0, 0.0, -1.0)).abs() < 1e-4);
+		assert!(far.distance_to(&Vector3::new(0.0, 0.0, -100.0)).abs() < 1e-2);
+		assert!((near.normal - Vector3::new(0.0, 0.0, -1.0)).norm() < 1e-4);
+	}
+
+	#[test]
+	fn keeps_things_in_front() {
+		assert!(frustum().intersects_sphere(&sphere(0.0, 0.0, -10.0)));
+		assert!(frustum().intersects_aabb(&cube(0.0, 0.0, -10.0)));
+	}
+
+	#[test]
+	fn culls_things_behind() {
+		assert!(!frustum().intersects_sphere(&sphere(0.0, 0.0, 10.0)));
+		assert!(!frustum().intersects_aabb(&cube(0.0, 0.0, 10.0)));
+
+		// Just past the left side is out too
+		assert!(!frustum().intersects_sphere(&sphere(-13.0, 0.0, -10.0)));
+		assert!(!frustum().intersects_aabb(&cube(-13.0, 0.0, -10.0)));
+	}
+
+	#[test]
+	fn keeps_things_straddling_a_plane() {
+		// The left plane is at x = -10 this far down
+		assert!(frustum().intersects_sphere(&sphere(-10.5, 0.0, -10.0)));
+		assert!(frustum().intersects_aabb(&cube(-10.5, 0.0, -10.0)));
+
+		// And the near plane at z = -1
+		assert!(frustum().intersects_sphere(&sphere(0.0, 0.0, -0.5)));
+		assert!(frustum().intersects_aabb(&cube(0.0, 0.0, -0.5)));
+	}
+
+	#[test]
+	fn render_skips_objects_off_screen() {
+		let mut graphics = Graphics::new(HeadlessBackend::new(800, 600));
+		graphics.set_shadow_settings(ShadowSettings { enabled: false, ..ShadowSettings::default() });
+		assert!(graphics.compile_shaders().is_empty());
+
+		// The camera looks at the origin from 4 units down z
+		let object = |x| {
+			let vertices = vec![-0.1, 0.0, 0.0, 0.1, 0.0, 0.0, 0.0, 0.1, 0.0];
+			Object::new(Vector3::new(x, 0.0, 0.0), 0.0, 0.0, 0.0, 1.0, Rc::new(Material::new("3d orange")), Indices::U8(vec![0, 1, 2]), vertices)
+		};
+		let world = World::new(vec![object(100.0)]);
+
+		graphics.backend_mut().clear_commands();
+		graphics.render(&world);
+		assert!(graphics.backend().draw_calls().is_empty());
+		assert_eq!(*graphics.get_cull_stats(), CullStats { culled: 1, visible: 0 });
+
+		// Bringing one on screen draws just that one
+		let world = World::new(vec![object(100.0), object(0.0)]);
+		graphics.backend_mut().clear_commands();
+		graphics.render(&world);
+		assert_eq!(graphics.backend().draw_calls().len(), 1);
+		assert_eq!(*graphics.get_cull_stats(), CullStats { culled: 1, visible: 1 });
+	}
+}
@@ -0,0 +1,323 @@
+#[cfg(debug_assertions)]
+use std::cell::RefCell;
+use std::f32::consts::PI;
+
+use nalgebra::{Matrix4, Point3, Vector3};
+
+use super::{
+	backend::{BufferHandle, BufferTarget, BufferUsage, Primitive, RenderBackend, VertexComponent},
+	bounds::Aabb,
+	vertex_layout::VertexAttribute
+};
+
+/// The shader debug lines are drawn with
+pub const DEBUG_LINES_SHADER: &str = "debug lines";
+
+/// How many lines make up each circle of a sphere
+const SPHERE_SEGMENTS: usize = 24;
+
+/// Floats per vertex in the line buffer, a position then an rgba color
+const FLOATS_PER_VERTEX: usize = 7;
+
+/// Lines waiting to be drawn, with the color and duration they were asked for
+#[cfg(debug_assertions)]
+struct DebugLines {
+	/// Seconds left to draw them for, or None for just the next frame
+	remaining: Option<f32>,
+	/// Interleaved positions and colors, two vertices per line
+	vertices: Vec<f32>
+}
+
+#[cfg(debug_assertions)]
+thread_local! {
+	/// Every shape waiting to be drawn
+	static PENDING: RefCell<Vec<DebugLines>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Draws lines and shapes for seeing where things are, from anywhere
+///
+/// Anything can call these while the world updates, and what they ask for is
+/// drawn over the main view. Shapes without a duration are drawn for the next
+/// frame only, so calling every update keeps them up. In release builds
+/// nothing is kept or drawn, and the calls cost nothing.
+///
+/// Colors are rgb from 0 to 1, and durations are in seconds.
+pub struct DebugDraw;
+
+impl DebugDraw {
+	/// Draws a box lined up with the axes
+	///
+	/// * `aabb` - the box in world space
+	/// * `color` - color of the lines
+	/// * `duration` - how long to keep drawing it, or None for one frame
+	pub fn aabb(aabb: &Aabb, color: Vector3<f32>, duration: Option<f32>) {
+		let (min, max) = (aabb.min, aabb.max);
+		push(color, duration, |lines| {
+			let corners: Vec<Vector3<f32>> = (0..8)
+				.map(|corner| Vector3::new(
+					if corner & 1 == 0 { min.x } else { max.x },
+					if corner & 2 == 0 { min.y } else { max.y },
+					if corner & 4 == 0 { min.z } else { max.z }
+				))
+				.collect();
+			box_edges(&corners, lines);
+		});
+	}
+
+	/// Counts down shapes that have a duration, and forgets the ones that ran
+	/// out
+	///
+	/// * `dt` - seconds since this last ran
+	pub fn advance(dt: f32) {
+		#[cfg(debug_assertions)]
+		PENDING.with(|pending| {
+			pending.borrow_mut().retain_mut(|shape| match &mut shape.remaining {
+				Some(remaining) => {
+					*remaining -= dt;
+					*remaining > 0.0
+				},
+				None => true
+			});
+		});
+
+		#[cfg(not(debug_assertions))]
+		let _ = dt;
+	}
+
+	/// Draws the x, y, and z axes of a transform in red, green, and blue
+	///
+	/// * `transform` - where the axes start and which way they point
+	/// * `size` - how long to draw each axis
+	/// * `duration` - how long to keep drawing them, or None for one frame
+	pub fn axes(transform: &Matrix4<f32>, size: f32, duration: Option<f32>) {
+		// Each axis is colored like the axis it is
+		for axis in [Vector3::x(), Vector3::y(), Vector3::z()] {
+			push(axis, duration, |lines| {
+				let origin = transform.transform_point(&Point3::origin()).coords;
+				let direction = transform.transform_vector(&axis).try_normalize(f32::EPSILON).unwrap_or(axis);
+				lines.extend([origin, origin + direction * size]);
+			});
+		}
+	}
+
+	/// Forgets every shape, including ones with time left
+	pub fn clear() {
+		#[cfg(debug_assertions)]
+		PENDING.with(|pending| pending.borrow_mut().clear());
+	}
+
+	/// Draws the edges of what a camera can see
+	///
+	/// * `view_projection` - the camera's projection times its view
+	/// * `color` - color of the lines
+	/// * `duration` - how long to keep drawing it, or None for one frame
+	pub fn frustum(view_projection: &Matrix4<f32>, color: Vector3<f32>, duration: Option<f32>) {
+		push(color, duration, |lines| {
+			let inverse = match view_projection.try_inverse() {
+				Some(inverse) => inverse,
+				None => return
+			};
+
+			// The corners of clip space, moved back into the world
+			let corners: Vec<Vector3<f32>> = (0..8)
+				.map(|corner| {
+					let x = if corner & 1 == 0 { -1.0 } else { 1.0 };
+					let y = if corner & 2 == 0 { -1.0 } else { 1.0 };
+					let z = if corner & 4 == 0 { -1.0 } else { 1.0 };
+					inverse.transform_point(&Point3::new(x, y, z)).coords
+				})
+				.collect();
+			box_edges(&corners, lines);
+		});
+	}
+
+	/// Draws a flat square grid, facing up
+	///
+	/// * `center` - the middle of the grid
+	/// * `size` - how wide the grid is
+	/// * `divisions` - how many squares wide the grid is
+	/// * `color` - color of the lines
+	/// * `duration` - how long to keep drawing it, or None for one frame
+	pub fn grid(center: Vector3<f32>, size: f32, divisions: u32, color: Vector3<f32>, duration: Option<f32>) {
+		let divisions = divisions.max(1);
+		push(color, duration, |lines| {
+			let half = size / 2.0;
+			for line in 0..=divisions {
+				let offset = -half + size * line as f32 / divisions as f32;
+				lines.extend([
+					center + Vector3::new(offset, 0.0, -half),
+					center + Vector3::new(offset, 0.0, half),
+					center + Vector3::new(-half, 0.0, offset),
+					center + Vector3::new(half, 0.0, offset)
+				]);
+			}
+		});
+	}
+
+	/// Draws a line between two points
+	///
+	/// * `start` - where the line starts in world space
+	/// * `end` - where the line ends in world space
+	/// * `color` - color of the line
+	/// * `duration` - how long to keep drawing it, or None for one frame
+	pub fn line(start: Vector3<f32>, end: Vector3<f32>, color: Vector3<f32>, duration: Option<f32>) {
+		push(color, duration, |lines| lines.extend([start, end]));
+	}
+
+	/// Draws a line out from a point
+	///
+	/// * `origin` - where the ray starts in world space
+	/// * `direction` - which way the ray goes, as long as the line should be
+	/// * `color` - color of the line
+	/// * `duration` - how long to keep drawing it, or None for one frame
+	pub fn ray(origin: Vector3<f32>, direction: Vector3<f32>, color: Vector3<f32>, duration: Option<f32>) {
+		Self::line(origin, origin + direction, color, duration);
+	}
+
+	/// Draws a sphere as a circle around each axis
+	///
+	/// * `center` - the middle of the sphere in world space
+	/// * `radius` - how far the sphere reaches from its center
+	/// * `color` - color of the lines
+	/// * `duration` - how long to keep drawing it, or None for one frame
+	pub fn sphere(center: Vector3<f32>, radius: f32, color: Vector3<f32>, duration: Option<f32>) {
+		push(color, duration, |lines| {
+			let point = |segment: usize, axis: usize| {
+				let angle = segment as f32 / SPHERE_SEGMENTS as f32 * PI * 2.0;
+				let (sin, cos) = angle.sin_cos();
+				let offset = match axis {
+					0 => Vector3::new(0.0, cos, sin),
+					1 => Vector3::new(cos, 0.0, sin),
+					_ => Vector3::new(cos, sin, 0.0)
+				};
+				center + offset * radius
+			};
+			for axis in 0..3 {
+				for segment in 0..SPHERE_SEGMENTS {
+					lines.extend([point(segment, axis), point(segment + 1, axis)]);
+				}
+			}
+		});
+	}
+
+	/// Gets the vertices of every shape to draw this frame, and forgets the
+	/// ones that were only for this frame
+	///
+	/// Returns - interleaved positions and rgba colors, two vertices per line
+	#[cfg(debug_assertions)]
+	pub fn take_vertices() -> Vec<f32> {
+		PENDING.with(|pending| {
+			let mut pending = pending.borrow_mut();
+			let vertices = pending.iter().flat_map(|shape| shape.vertices.iter().copied()).collect();
+			pending.retain(|shape| shape.remaining.is_some());
+
+			vertices
+		})
+	}
+}
+
+/// A dynamic buffer that all the debug lines get drawn from, in one call
+pub struct DebugLineBuffer {
+	/// The GPU buffer, created the first time it's needed
+	buffer: Option<BufferHandle>
+}
+
+impl DebugLineBuffer {
+	/// Frees the buffer from the GPU
+	///
+	/// * `gl` - the backend the buffer was created with
+	pub fn delete<B: RenderBackend>(&mut self, gl: &mut B) {
+		if let Some(buffer) = self.buffer.take() {
+			gl.delete_buffer(buffer);
+		}
+	}
+
+	/// Uploads lines and draws them
+	///
+	/// The debug lines shader has to be in use, with its matrices set.
+	///
+	/// * `gl` - the backend to render with
+	/// * `vertices` - interleaved positions and rgba colors, two per line
+	pub fn draw<B: RenderBackend>(&mut self, gl: &mut B, vertices: &[f32]) {
+		if self.buffer.is_none() {
+			self.buffer = gl.create_buffer();
+		}
+		let buffer = match self.buffer {
+			Some(buffer) => buffer,
+			None => return
+		};
+
+		// Upload
+		gl.bind_buffer(BufferTarget::Array, Some(buffer));
+		gl.buffer_data_f32(BufferTarget::Array, vertices, BufferUsage::Dynamic);
+
+		// Read positions and colors, and nothing else
+		let stride = (FLOATS_PER_VERTEX * 4) as i32;
+		for attribute in VertexAttribute::ALL {
+			let location = attribute.location();
+			let offset = match attribute {
+				VertexAttribute::Position => 0,
+				VertexAttribute::Color => 12,
+				_ => {
+					gl.disable_vertex_attrib_array(location);
+					continue;
+				}
+			};
+			gl.vertex_attrib_pointer(location, attribute.components() as i32, VertexComponent::Float, false, stride, offset);
+			gl.enable_vertex_attrib_array(location);
+		}
+
+		let count = (vertices.len() / FLOATS_PER_VERTEX) as i32;
+		gl.draw_arrays(Primitive::Lines, 0, count);
+	}
+
+	/// Creates an empty line buffer
+	pub fn new() -> DebugLineBuffer {
+		DebugLineBuffer {
+			buffer: None
+		}
+	}
+}
+
+impl Default for DebugLineBuffer {
+	fn default() -> DebugLineBuffer {
+		Self::new()
+	}
+}
+
+/// Joins eight corners into the twelve edges of a box
+///
+/// * `corners` - numbered so bits 0, 1, and 2 pick the high x, y, and z
+/// * `lines` - where to put the two ends of each edge
+fn box_edges(corners: &[Vector3<f32>], lines: &mut Vec<Vector3<f32>>) {
+	for corner in 0..8 {
+		for bit in [1, 2, 4] {
+			// Each edge once, from the corner with the bit unset
+			if corner & bit == 0 {
+				lines.extend([corners[corner], corners[corner | bit]]);
+			}
+		}
+	}
+}
+
+/// Keeps lines to be drawn
+///
+/// * `color` - color of the lines
+/// * `duration` - how long to keep drawing them, or None for one frame
+/// * `build` - puts the two ends of each line in the list it's given
+#[cfg(debug_assertions)]
+fn push<F: FnOnce(&mut Vec<Vector3<f32>>)>(color: Vector3<f32>, duration: Option<f32>, build: F) {
+	let mut points = Vec::new();
+	build(&mut points);
+
+	let mut vertices = Vec::with_capacity(points.len() * FLOATS_PER_VERTEX);
+	for point in points {
+		vertices.extend_from_slice(&[point.x, point.y, point.z, color.x, color.y, color.z, 1.0]);
+	}
+
+	PENDING.with(|pending| pending.borrow_mut().push(DebugLines { remaining: duration, vertices }));
+}
+
+/// Throws lines away, since release builds don't draw them
+#[cfg(not(debug_assertions))]
+fn push<F: FnOnce(&mut Vec<Vector3<f32>>)>(_color: Vector3<f32>, _duration: Option<f32>, _build: F) {}
@@ -0,0 +1,146 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+use wasm_bindgen::prelude::*;
+
+use super::{backend::state_cache::RenderStats, culling::CullStats};
+
+/// What the last frame drew, and how long each part of it took on the CPU
+///
+/// Times are in milliseconds. They only cover making the calls, the GPU can
+/// still be working on them after the frame is done.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+	/// Objects skipped because a camera couldn't see them
+	pub culled_objects: u32,
+	/// Draw calls made
+	pub draw_calls: u32,
+	/// Time spent drawing the main view, and debug lines over it
+	pub main_time: f64,
+	/// Time spent applying post-processing effects
+	pub post_process_time: f64,
+	/// Time spent building shader permutations
+	pub prepare_time: f64,
+	/// Times the program in use actually changed
+	pub program_switches: u32,
+	/// Buffer, framebuffer, program, and texture binds skipped because they
+	/// were already bound
+	pub saved_binds: u32,
+	/// Render state, viewport, clear color, and vertex attribute changes
+	/// skipped because nothing would have changed
+	pub saved_state_changes: u32,
+	/// Uniform sets skipped because the uniform already had the value
+	pub saved_uniform_sets: u32,
+	/// Time spent drawing shadow maps
+	pub shadow_time: f64,
+	/// Time spent drawing into render targets with a camera
+	pub target_time: f64,
+	/// Time spent on the whole frame
+	pub total_time: f64,
+	/// Triangles drawn, counting every instance
+	pub triangles: u32,
+	/// Bytes uploaded to buffers and textures
+	pub upload_bytes: u32,
+	/// Vertices drawn, counting every instance
+	pub vertices: u32,
+	/// Objects a camera could see
+	pub visible_objects: u32
+}
+
+impl FrameStats {
+	/// Gathers what the backend and culling counted during a frame
+	///
+	/// Times are left at zero.
+	///
+	/// * `render` - the calls the frame made
+	/// * `cull` - the objects the frame culled and kept
+	pub fn from_counts(render: &RenderStats, cull: &CullStats) -> FrameStats {
+		FrameStats {
+			culled_objects: cull.culled,
+			draw_calls: render.draw_calls,
+			program_switches: render.program_switches,
+			saved_binds: render.saved_binds(),
+			saved_state_changes: render.saved_state_changes,
+			saved_uniform_sets: render.saved_uniform_sets,
+			triangles: render.triangles,
+			upload_bytes: render.upload_bytes,
+			vertices: render.vertices,
+			visible_objects: cull.visible,
+			..FrameStats::default()
+		}
+	}
+}
+
+/// Gets the time in milliseconds, from the page's performance clock
+///
+/// Only differences between two times mean anything.
+#[cfg(target_arch = "wasm32")]
+pub fn now() -> f64 {
+	web_sys::window()
+		.and_then(|window| window.performance())
+		.map(|performance| performance.now())
+		.unwrap_or(0.0)
+}
+
+/// Gets the time in milliseconds, from when it was first asked for
+///
+/// Only differences between two times mean anything.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now() -> f64 {
+	thread_local! {
+		static START: Instant = Instant::now();
+	}
+
+	START.with(|start| start.elapsed().as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::rc::Rc;
+
+	use nalgebra::Vector3;
+
+	use crate::{
+		graphics::{
+			backend::headless::HeadlessBackend,
+			material::Material,
+			mesh::Indices,
+			shadows::ShadowSettings,
+			Graphics
+		},
+		logic::{object::Object, world::World}
+	};
+
+	#[test]
+	fn render_counts_what_it_drew() {
+		let mut graphics = Graphics::new(HeadlessBackend::new(800, 600));
+		graphics.set_shadow_settings(ShadowSettings { enabled: false, ..ShadowSettings::default() });
+		assert!(graphics.compile_shaders().is_empty());
+
+		// Two triangles, one far off to the side of the camera
+		let object = |x| {
+			let vertices = vec![-0.1, 0.0, 0.0, 0.1, 0.0, 0.0, 0.0, 0.1, 0.0];
+			Object::new(Vector3::new(x, 0.0, 0.0), 0.0, 0.0, 0.0, 1.0, Rc::new(Material::new("3d orange")), Indices::U8(vec![0, 1, 2]), vertices)
+		};
+		let world = World::new(vec![object(0.0), object(100.0)]);
+
+		// The first frame uploads the mesh it draws
+		graphics.render(&world);
+		let stats = *graphics.get_frame_stats();
+		assert_eq!(stats.draw_calls, 1);
+		assert_eq!(stats.triangles, 1);
+		assert_eq!(stats.vertices, 3);
+		assert_eq!((stats.culled_objects, stats.visible_objects), (1, 1));
+		assert!(stats.upload_bytes > 0);
+
+		// The next one draws the same without uploading anything
+		graphics.render(&world);
+		let stats = *graphics.get_frame_stats();
+		assert_eq!(stats.draw_calls, 1);
+		assert_eq!(stats.triangles, 1);
+		assert_eq!(stats.vertices, 3);
+		assert_eq!((stats.culled_objects, stats.visible_objects), (1, 1));
+		assert_eq!(stats.upload_bytes, 0);
+	}
+}
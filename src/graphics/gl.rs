@@ -1,156 +1,107 @@
+use nalgebra::Matrix4;
 use wasm_bindgen::JsCast;
-use web_sys::{WebGlProgram, WebGlRenderingContext, WebGlShader};
+use web_sys::HtmlCanvasElement;
 
-use super::shaders::{ShaderSource, Uniform};
+use super::backend::{ProgramHandle, RenderBackend, ShaderHandle, ShaderStage};
+use super::shaders::{ShaderError, ShaderSource, Uniform, UniformValue};
+use super::instancing::can_instance;
+use super::vertex_layout::INSTANCE_MODEL_LOCATION;
 
 /// Compiles and links a shader program
 ///
-/// * `context` - the webGL rendering context for this program
-/// * `index` - the index of the program to use
+/// * `backend` - the backend to build this program with
+/// * `name` - the name of the shader, for reporting errors
+/// * `source` - the source code of the program
 ///
-/// Returns - the built program
-pub fn build_program(context: &WebGlRenderingContext, source: &ShaderSource) -> Option<WebGlProgram> {
-
-	// Compile the vertex shader if it exists
-	let vertex_shader = match source.vertex_shader {
-		Some(shader) => compile_shader(
-			context,
-			WebGlRenderingContext::VERTEX_SHADER,
-			shader
-		).ok(),
-		_ => None
-	};
-
-	// Compile the fragment shader if it exists
-	let fragment_shader = match source.fragment_shader {
-		Some(shader) => compile_shader(
-			context,
-			WebGlRenderingContext::FRAGMENT_SHADER,
-			shader
-		).ok(),
-		_ => None
+/// Returns - the built program, or what went wrong with the first stage that
+/// failed
+pub fn build_program<B: RenderBackend>(
+	backend: &mut B,
+	name: &str,
+	source: &ShaderSource
+) -> Result<ProgramHandle, ShaderError> {
+	// Compile the vertex shader
+	let vertex_shader = compile_stage(backend, name, ShaderStage::Vertex, source.vertex_shader)?;
+
+	// Compile the fragment shader, cleaning up the vertex shader if it fails
+	let fragment_shader = match compile_stage(backend, name, ShaderStage::Fragment, source.fragment_shader) {
+		Ok(shader) => shader,
+		Err(error) => {
+			backend.delete_shader(vertex_shader);
+			return Err(error);
+		}
 	};
 
-	// Link the program
-	match (vertex_shader, fragment_shader) {
-		(Some(vert), Some(frag)) => link_program(&context, &vert, &frag).ok(),
-		_ => None
+	// Link the program with attributes bound by name
+	// The instance matrix only gets a location if there's room for it
+	let mut attributes: Vec<(u32, &str)> = source.attributes.iter()
+		.map(|&(name, attribute)| (attribute.location(), name))
+		.collect();
+	if source.instancing && can_instance(backend.capabilities()) {
+		attributes.push((INSTANCE_MODEL_LOCATION, "instance_model"));
 	}
-}
-
-/// Compiles a GLSL shader
-///
-/// * `context` - the GL context this is rendering in
-/// * `shader_type` - the type of shader being compiled
-/// * `source` - the source code for this shader
-///
-/// Returns - A result that may either have a compiled shader or a compile error
-pub fn compile_shader(
-	context: &WebGlRenderingContext,
-	shader_type: u32,
-	source: &str,
-) -> Result<WebGlShader, String> {
+	let program = backend.link_program(vertex_shader, fragment_shader, &attributes)
+		.map_err(|log| ShaderError::from_log(name, None, &log, None));
 
-	// Create the shader
-	let shader = context
-		.create_shader(shader_type)
-		.ok_or_else(|| String::from("Unable to create shader object"))?;
+	// The stages aren't needed once they're linked
+	backend.delete_shader(vertex_shader);
+	backend.delete_shader(fragment_shader);
 
-	// Load and compile
-	context.shader_source(&shader, source);
-	context.compile_shader(&shader);
-
-	// Check if it went well
-	if context
-		.get_shader_parameter(&shader, WebGlRenderingContext::COMPILE_STATUS)
-		.as_bool()
-		.unwrap_or(false)
-	{
-		Ok(shader)
-	} else {
-		// Return the error
-		Err(context
-			.get_shader_info_log(&shader)
-			.unwrap_or_else(|| String::from("Unknown error creating shader")))
-	}
+	program
 }
 
-/// Links a webGL program
+/// Compiles one stage of a shader program
 ///
-/// * `context` - the GL context this is rendering in
-/// * `vertex_shader` - the vertex shader to link
-/// * `frag_shader` - the fragment shader to link
-///
-/// Returns - a result containing the webGL program
-pub fn link_program(
-	context: &WebGlRenderingContext,
-	vertex_shader: &WebGlShader,
-	frag_shader: &WebGlShader,
-) -> Result<WebGlProgram, String> {
-
-	// Create the program
-	let program = context
-		.create_program()
-		.ok_or_else(|| String::from("Unable to create shader object"))?;
-
-	// Add and link the shaders
-	context.attach_shader(&program, vertex_shader);
-	context.attach_shader(&program, frag_shader);
-	context.link_program(&program);
-
-	// Check if it went well
-	if context
-		.get_program_parameter(&program, WebGlRenderingContext::LINK_STATUS)
-		.as_bool()
-		.unwrap_or(false)
-	{
-		Ok(program)
-	} else {
-		// Return the error
-		Err(context
-			.get_program_info_log(&program)
-			.unwrap_or_else(|| String::from("Unknown error creating program object")))
-	}
+/// * `backend` - the backend to compile with
+/// * `name` - the name of the shader, for reporting errors
+/// * `stage` - the stage being compiled
+/// * `source` - the source code of the stage, if there is any
+fn compile_stage<B: RenderBackend>(
+	backend: &mut B,
+	name: &str,
+	stage: ShaderStage,
+	source: Option<&str>
+) -> Result<ShaderHandle, ShaderError> {
+	let source = source.ok_or_else(|| ShaderError::new(name, Some(stage), "No source code"))?;
+
+	backend.compile_shader(stage, source)
+		.map_err(|log| ShaderError::from_log(name, Some(stage), &log, Some(source)))
 }
 
 /// Sets a mat4 type uniform
 ///
-/// * `context` - rendering context to set uniform in
+/// * `backend` - backend to set uniform in
 /// * `location` - the location of the uniform
 /// * `matrix` - the value to be set
-pub fn set_mat4_uniform(context: &WebGlRenderingContext, location: &Option<Uniform>, matrix: &[f32]) {
-	match location {
-		Some(uniform) => {
-			let location = uniform.location.as_ref();
-			context.uniform_matrix4fv_with_f32_array(location, false, matrix);
-		}
-		_ => ()
+pub fn set_mat4_uniform<B: RenderBackend>(backend: &mut B, location: &Option<Uniform>, matrix: &Matrix4<f32>) {
+	if let Some(Uniform { location: Some(location), .. }) = location {
+		backend.set_uniform(*location, &UniformValue::from(matrix));
 	}
 }
 
 /// Set up front end canvas
 ///
-/// Sets up the window, canvas, and returns a valid rendering context for webGL
+/// Sets up the window and sizes the canvas to fill it, with a pixel in the
+/// drawing buffer for every pixel on the screen
 /// Shits the bed when it fails, because there's no reason to continue without it
 ///
-/// returns - rendering context
-pub fn set_up_canvas() -> WebGlRenderingContext {
+/// returns - the canvas to render to
+pub fn set_up_canvas() -> HtmlCanvasElement {
 	// Get to the canvas object
 	let window = web_sys::window().unwrap();
 	let document = window.document().unwrap();
 	let canvas = document.get_element_by_id("webGL")
 		.unwrap()
-		.dyn_into::<web_sys::HtmlCanvasElement>()
+		.dyn_into::<HtmlCanvasElement>()
 		.unwrap();
 
 	// Set canvas to full window size
-	let width = window.inner_width().unwrap().as_f64().unwrap() as u32;
-	let height = window.inner_height().unwrap().as_f64().unwrap() as u32;
+	let dpr = window.device_pixel_ratio();
+	let width = (window.inner_width().unwrap().as_f64().unwrap() * dpr).round() as u32;
+	let height = (window.inner_height().unwrap().as_f64().unwrap() * dpr).round() as u32;
 
 	canvas.set_width(width);
 	canvas.set_height(height);
 
-	// Get context
-	canvas.get_context("webgl").unwrap().unwrap()
-		.dyn_into::<WebGlRenderingContext>().unwrap()
+	canvas
 }
@@ -0,0 +1,127 @@
+use std::{collections::HashMap, hash::Hash};
+
+use crate::logic::object::Object;
+use super::{
+	backend::{BufferHandle, BufferTarget, BufferUsage, Capabilities, RenderBackend, VertexComponent},
+	vertex_layout::INSTANCE_MODEL_LOCATION
+};
+
+/// The define that builds the permutation of a shader that draws instances
+pub const INSTANCED_DEFINE: &str = "INSTANCED";
+
+/// Whether instanced draws can be made at all
+///
+/// Besides the API supporting it, the model matrix needs four attribute
+/// locations past the ones meshes use.
+///
+/// * `capabilities` - what the graphics API can do
+pub fn can_instance(capabilities: &Capabilities) -> bool {
+	capabilities.instancing && capabilities.max_vertex_attribs >= INSTANCE_MODEL_LOCATION + 4
+}
+
+/// Splits objects into groups that can each be drawn with one instanced call
+///
+/// Groups come out in the order their first object does, and objects keep
+/// their order inside a group.
+///
+/// * `objects` - the objects to split up
+/// * `key` - what has to match for two objects to share a group
+///
+/// Returns - every group, none of them empty
+pub fn group_instances<'a, I, K, F>(objects: I, key: F) -> Vec<Vec<&'a Object>>
+where
+	I: IntoIterator<Item = &'a Object>,
+	K: Eq + Hash,
+	F: Fn(&'a Object) -> K
+{
+	let mut groups: Vec<Vec<&Object>> = Vec::new();
+	let mut group_indices = HashMap::new();
+	for object in objects {
+		let index = *group_indices.entry(key(object)).or_insert_with(|| {
+			groups.push(Vec::new());
+			groups.len() - 1
+		});
+		groups[index].push(object);
+	}
+
+	groups
+}
+
+/// A buffer of model matrices, one for each instance an instanced draw makes
+pub struct InstanceBuffer {
+	/// The GPU buffer, created the first time it's needed
+	buffer: Option<BufferHandle>,
+	/// Where the matrices get flattened before uploading, kept to save
+	/// allocating every draw
+	data: Vec<f32>
+}
+
+impl InstanceBuffer {
+	/// Uploads the model matrix of each object and reads one per instance
+	///
+	/// * `gl` - the backend to render with
+	/// * `objects` - the objects being drawn, one instance each
+	///
+	/// Returns - whether the matrices are ready to draw with
+	pub fn bind<B: RenderBackend>(&mut self, gl: &mut B, objects: &[&Object]) -> bool {
+		if self.buffer.is_none() {
+			self.buffer = gl.create_buffer();
+		}
+		let buffer = match self.buffer {
+			Some(buffer) => buffer,
+			None => return false
+		};
+
+		// Upload
+		self.data.clear();
+		for object in objects {
+			self.data.extend_from_slice(object.get_model_matrix().as_slice());
+		}
+		gl.bind_buffer(BufferTarget::Array, Some(buffer));
+		gl.buffer_data_f32(BufferTarget::Array, &self.data, BufferUsage::Stream);
+
+		// A mat4 attribute gets read as four columns
+		for column in 0..4 {
+			let location = INSTANCE_MODEL_LOCATION + column;
+			gl.vertex_attrib_pointer(location, 4, VertexComponent::Float, false, 64, column as i32 * 16);
+			gl.enable_vertex_attrib_array(location);
+			gl.vertex_attrib_divisor(location, 1);
+		}
+
+		true
+	}
+
+	/// Frees the buffer from the GPU
+	///
+	/// * `gl` - the backend the buffer was created with
+	pub fn delete<B: RenderBackend>(&mut self, gl: &mut B) {
+		if let Some(buffer) = self.buffer.take() {
+			gl.delete_buffer(buffer);
+		}
+	}
+
+	/// Creates an empty instance buffer
+	pub fn new() -> InstanceBuffer {
+		InstanceBuffer {
+			buffer: None,
+			data: Vec::new()
+		}
+	}
+
+	/// Stops reading matrices per instance, so the draws after aren't affected
+	///
+	/// * `gl` - the backend to render with
+	pub fn unbind<B: RenderBackend>(&self, gl: &mut B) {
+		for column in 0..4 {
+			let location = INSTANCE_MODEL_LOCATION + column;
+			gl.vertex_attrib_divisor(location, 0);
+			gl.disable_vertex_attrib_array(location);
+		}
+	}
+}
+
+impl Default for InstanceBuffer {
+	fn default() -> InstanceBuffer {
+		Self::new()
+	}
+}
@@ -0,0 +1,112 @@
+use std::cmp::Ordering;
+
+use nalgebra::Vector3;
+
+use crate::logic::light::{Light, LightKind};
+use super::{backend::RenderBackend, shaders::{CompiledShader, UniformError, UniformValue}};
+
+/// The most lights a lit shader takes at once
+///
+/// Lit shaders get it as the MAX_LIGHTS define.
+pub const MAX_LIGHTS: usize = 4;
+
+/// Picks the lights that matter most to something at a position
+///
+/// Directional lights reach everywhere, so they come first. The rest are
+/// sorted by distance.
+///
+/// * `lights` - every light in the world
+/// * `position` - where the thing being lit is in world space
+/// * `count` - how many lights to pick at most
+///
+/// Returns - up to count lights, most important first
+pub fn nearest_lights<'a>(lights: &'a [Light], position: &Vector3<f32>, count: usize) -> Vec<&'a Light> {
+	let distance = |light: &Light| match light.get_kind() {
+		LightKind::Directional {..} => 0.0,
+		_ => (light.get_position() - position).norm_squared()
+	};
+
+	let mut nearest: Vec<&Light> = lights.iter().collect();
+	nearest.sort_by(|a, b| distance(a).partial_cmp(&distance(b)).unwrap_or(Ordering::Equal));
+	nearest.truncate(count);
+
+	nearest
+}
+
+/// Uploads lights to a lit shader
+///
+/// Shaders without a light_count uniform aren't lit and are left alone.
+///
+/// * `gl` - the backend to render with
+/// * `shader` - the shader being rendered with, already in use
+/// * `lights` - the lights to upload, at most MAX_LIGHTS
+/// * `camera_position` - where the camera is in world space, for specular
+/// * `errors` - where to put any problems setting the uniforms
+pub fn set_light_uniforms<B: RenderBackend>(
+	gl: &mut B,
+	shader: &CompiledShader,
+	lights: &[&Light],
+	camera_position: &Vector3<f32>,
+	errors: &mut Vec<UniformError>
+) {
+	if !shader.uniforms.contains_key("light_count") {
+		return;
+	}
+
+	// Split the lights into parallel arrays
+	let mut types = Vec::new();
+	let mut positions = Vec::new();
+	let mut directions = Vec::new();
+	let mut colors = Vec::new();
+	let mut attenuations = Vec::new();
+	let mut cones = Vec::new();
+	for light in lights.iter().take(MAX_LIGHTS) {
+		// Type, direction, falloff, and cos of the cone angles
+		let (light_type, direction, attenuation, cone) = match *light.get_kind() {
+			LightKind::Directional { direction } => (0, direction, [1.0, 0.0, 0.0], [-1.0, -1.0]),
+			LightKind::Point { attenuation } => (
+				1,
+				Vector3::zeros(),
+				[attenuation.constant, attenuation.linear, attenuation.quadratic],
+				[-1.0, -1.0]
+			),
+			LightKind::Spot { attenuation, direction, inner_angle, outer_angle } => (
+				2,
+				direction,
+				[attenuation.constant, attenuation.linear, attenuation.quadratic],
+				[inner_angle.cos(), outer_angle.cos()]
+			)
+		};
+		let color = light.get_color() * light.get_intensity();
+
+		types.push(UniformValue::Int(light_type));
+		positions.push(UniformValue::from(*light.get_position()));
+		directions.push(UniformValue::from(direction));
+		colors.push(UniformValue::from(color));
+		attenuations.push(UniformValue::Vec3(attenuation));
+		cones.push(UniformValue::Vec2(cone));
+	}
+
+	// Upload
+	let count = types.len() as i32;
+	let values = [
+		("light_count", UniformValue::Int(count)),
+		("camera_position", UniformValue::from(*camera_position)),
+		("light_types", UniformValue::Array(types)),
+		("light_positions", UniformValue::Array(positions)),
+		("light_directions", UniformValue::Array(directions)),
+		("light_colors", UniformValue::Array(colors)),
+		("light_attenuations", UniformValue::Array(attenuations)),
+		("light_cones", UniformValue::Array(cones))
+	];
+	for (name, value) in &values {
+		// Empty arrays have nothing to upload
+		if matches!(value, UniformValue::Array(elements) if elements.is_empty()) {
+			continue;
+		}
+
+		if let Err(error) = shader.set_uniform(gl, name, value) {
+			errors.push(error);
+		}
+	}
+}
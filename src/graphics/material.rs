@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use super::{render_state::{BlendMode, RenderState}, shaders::{preprocessor::Defines, UniformValue}};
+
+/// Describes how a surface looks
+///
+/// A shader plus the values to feed it. Many materials can share one shader
+/// program, each with its own uniform values, and many objects can share one
+/// material.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+	/// Defines to build the shader with, picking which permutation is used
+	defines: Defines,
+	/// Depth and culling state, if this material needs something other than
+	/// what the pass uses
+	render_state: Option<RenderState>,
+	/// The name of the shader to render with
+	shader_name: &'static str,
+	/// Names of textures to sample, by sampler uniform name
+	textures: HashMap<String, String>,
+	/// Values for the shader's uniforms, by uniform name
+	uniforms: HashMap<String, UniformValue>
+}
+
+impl Material {
+	/// Get how this material's colors combine with what's behind them
+	pub fn get_blend_mode(&self) -> BlendMode {
+		self.render_state.map_or(BlendMode::None, |render_state| render_state.blend_mode)
+	}
+
+	/// Get the defines the shader gets built with
+	pub fn get_defines(&self) -> &Defines {
+		&self.defines
+	}
+
+	/// Get the depth and culling state this material overrides the pass with
+	pub fn get_render_state(&self) -> Option<&RenderState> {
+		self.render_state.as_ref()
+	}
+
+	/// Get the name of the shader to render with
+	pub fn get_shader_name(&self) -> &'static str {
+		self.shader_name
+	}
+
+	/// Get the names of textures to sample, by sampler uniform name
+	pub fn get_textures(&self) -> &HashMap<String, String> {
+		&self.textures
+	}
+
+	/// Get the value set for a uniform, if there is one
+	///
+	/// * `name` - the name of the uniform
+	pub fn get_uniform(&self, name: &str) -> Option<&UniformValue> {
+		self.uniforms.get(name)
+	}
+
+	/// Get the values for the shader's uniforms, by uniform name
+	pub fn get_uniforms(&self) -> &HashMap<String, UniformValue> {
+		&self.uniforms
+	}
+
+	/// Creates a material that renders with a shader and its default values
+	///
+	/// * `shader_name` - the name of the shader to render with
+	pub fn new(shader_name: &'static str) -> Material {
+		Material {
+			defines: Defines::new(),
+			render_state: None,
+			shader_name,
+			textures: HashMap::new(),
+			uniforms: HashMap::new()
+		}
+	}
+
+	/// Sets how this material's colors combine with what's behind them
+	///
+	/// Blending materials stop writing depth and get drawn after everything
+	/// solid. Only the blending and depth writes change, so any culling or
+	/// depth test the material already had is kept. A material using the
+	/// pass's state gets the state for things that can be seen through.
+	///
+	/// * `blend_mode` - how colors combine with what's behind
+	pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+		let transparent = blend_mode.is_transparent();
+		if let Some(render_state) = &mut self.render_state {
+			render_state.blend_mode = blend_mode;
+			render_state.depth_write = !transparent;
+		} else if transparent {
+			self.render_state = Some(RenderState::transparent(blend_mode));
+		}
+	}
+
+	/// Sets a define to build the shader with
+	///
+	/// Each different set of defines builds its own permutation of the shader.
+	///
+	/// * `name` - the name of the define
+	/// * `value` - what it expands to
+	pub fn set_define(&mut self, name: &str, value: &str) {
+		self.defines.insert(String::from(name), String::from(value));
+	}
+
+	/// Turns a feature flag like HAS_NORMAL_MAP or SKINNED on or off
+	///
+	/// Flags are defined as 1 when on and left undefined when off.
+	///
+	/// * `name` - the name of the flag
+	/// * `enabled` - whether to turn it on
+	pub fn set_feature(&mut self, name: &str, enabled: bool) {
+		if enabled {
+			self.set_define(name, "1");
+		} else {
+			self.defines.remove(name);
+		}
+	}
+
+	/// Sets the depth and culling state for this material
+	///
+	/// * `render_state` - the state to use, or None to use the pass's state
+	pub fn set_render_state(&mut self, render_state: Option<RenderState>) {
+		self.render_state = render_state;
+	}
+
+	/// Sets the texture a sampler uniform reads from
+	///
+	/// * `sampler` - the name of the sampler uniform
+	/// * `texture_name` - the name of the texture to sample
+	pub fn set_texture(&mut self, sampler: &str, texture_name: &str) {
+		self.textures.insert(String::from(sampler), String::from(texture_name));
+	}
+
+	/// Sets the value of a uniform
+	///
+	/// The value is checked against the shader when it gets rendered.
+	///
+	/// * `name` - the name of the uniform
+	/// * `value` - the value to set
+	pub fn set_uniform(&mut self, name: &str, value: UniformValue) {
+		self.uniforms.insert(String::from(name), value);
+	}
+}
@@ -0,0 +1,557 @@
+use std::{
+	collections::{HashMap, HashSet},
+	rc::{Rc, Weak},
+	sync::atomic::{AtomicU32, Ordering}
+};
+
+use super::{
+	backend::{BufferHandle, BufferTarget, BufferUsage, IndexType, RenderBackend},
+	bounds::{Aabb, BoundingSphere},
+	vertex_layout::{VertexAttribute, VertexLayout}
+};
+
+/// The most vertices a 16 bit index can reach
+///
+/// The largest index is left out, since WebGL2 always treats it as a
+/// primitive restart.
+pub const MAX_U16_VERTICES: usize = u16::MAX as usize;
+
+/// Source of unique mesh ids
+static NEXT_MESH_ID: AtomicU32 = AtomicU32::new(1);
+
+/// An index array stored in whichever width the mesh needs
+#[derive(Clone, Debug, PartialEq)]
+pub enum Indices {
+	/// Up to 255 vertices
+	U8(Vec<u8>),
+	/// Up to 65535 vertices
+	U16(Vec<u16>),
+	/// Any number of vertices, if the GPU supports it
+	U32(Vec<u32>)
+}
+
+impl Indices {
+	/// Gets the type each index is uploaded as
+	pub fn get_index_type(&self) -> IndexType {
+		match self {
+			Indices::U8(_) => IndexType::U8,
+			Indices::U16(_) => IndexType::U16,
+			Indices::U32(_) => IndexType::U32
+		}
+	}
+
+	/// Whether there are no indices at all
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Gets the number of indices
+	pub fn len(&self) -> usize {
+		match self {
+			Indices::U8(indices) => indices.len(),
+			Indices::U16(indices) => indices.len(),
+			Indices::U32(indices) => indices.len()
+		}
+	}
+
+	/// Stores indices in the narrowest width that fits all of them
+	///
+	/// The largest value of each width is skipped, since WebGL2 treats it as
+	/// a primitive restart.
+	///
+	/// * `indices` - the full width indices
+	pub fn narrowest(indices: Vec<u32>) -> Indices {
+		let max = indices.iter().copied().max().unwrap_or(0);
+
+		if max < u8::MAX as u32 {
+			Indices::U8(indices.into_iter().map(|index| index as u8).collect())
+		} else if max < u16::MAX as u32 {
+			Indices::U16(indices.into_iter().map(|index| index as u16).collect())
+		} else {
+			Indices::U32(indices)
+		}
+	}
+
+	/// Finds every edge of the triangles, for drawing them as lines
+	///
+	/// Edges shared by two triangles only show up once, in the order they're
+	/// first found.
+	///
+	/// Returns - two indices for each edge
+	pub fn to_edges(&self) -> Indices {
+		let mut seen = HashSet::new();
+		let mut edges = Vec::new();
+		for triangle in self.to_u32().chunks_exact(3) {
+			for (start, end) in [(triangle[0], triangle[1]), (triangle[1], triangle[2]), (triangle[2], triangle[0])] {
+				if seen.insert((start.min(end), start.max(end))) {
+					edges.extend([start, end]);
+				}
+			}
+		}
+
+		Indices::narrowest(edges)
+	}
+
+	/// Copies the indices out at full width
+	pub fn to_u32(&self) -> Vec<u32> {
+		match self {
+			Indices::U8(indices) => indices.iter().map(|&index| index as u32).collect(),
+			Indices::U16(indices) => indices.iter().map(|&index| index as u32).collect(),
+			Indices::U32(indices) => indices.clone()
+		}
+	}
+}
+
+/// Geometry that can be uploaded to the GPU
+///
+/// Objects hold these behind an Rc so many objects can share one mesh, and
+/// one set of GPU buffers.
+pub struct Mesh {
+	/// A sphere around every vertex
+	bounding_sphere: BoundingSphere,
+	/// A box around every vertex
+	bounds: Aabb,
+	/// Unique id used to find this mesh's GPU buffers
+	id: u32,
+	/// How the vertex data is split into streams
+	layout: VertexLayout,
+	/// The vertex data of each stream in the layout
+	streams: Vec<Vec<f32>>,
+	/// Contains an index array for rendering
+	triangle_indices: Indices,
+	/// Goes up every time the geometry changes
+	version: u32
+}
+
+impl Mesh {
+	/// Gets a sphere around every vertex, in the mesh's own space
+	pub fn get_bounding_sphere(&self) -> &BoundingSphere {
+		&self.bounding_sphere
+	}
+
+	/// Gets a box around every vertex, in the mesh's own space
+	pub fn get_bounds(&self) -> &Aabb {
+		&self.bounds
+	}
+
+	/// Gets the unique id of this mesh
+	pub fn get_id(&self) -> u32 {
+		self.id
+	}
+
+	/// Gets how the vertex data is split into streams
+	pub fn get_layout(&self) -> &VertexLayout {
+		&self.layout
+	}
+
+	/// Gets the position of every vertex
+	pub fn get_positions(&self) -> Vec<[f32; 3]> {
+		Self::positions_of(&self.layout, &self.streams)
+	}
+
+	/// Gets the raw vertex data of every stream
+	pub fn get_streams(&self) -> &[Vec<f32>] {
+		&self.streams
+	}
+
+	/// Gets the index array for rendering
+	pub fn get_triangle_indices(&self) -> &Indices {
+		&self.triangle_indices
+	}
+
+	/// Gets the version of the geometry, which changes whenever it is edited
+	pub fn get_version(&self) -> u32 {
+		self.version
+	}
+
+	/// Creates a new mesh that only has positions
+	///
+	/// * `triangle_indices` - Contains an index array for rendering
+	/// * `vertices` - A vector of vertices optimized for rendering vs physics
+	///   stored like [x1, y1, z1, x2, y2, z2]
+	pub fn new(triangle_indices: Indices, vertices: Vec<f32>) -> Mesh {
+		Self::with_layout(triangle_indices, VertexLayout::position_only(), vec![vertices])
+	}
+
+	/// Replaces the index array
+	///
+	/// * `triangle_indices` - Contains an index array for rendering
+	pub fn set_triangle_indices(&mut self, triangle_indices: Indices) {
+		self.triangle_indices = triangle_indices;
+		self.version += 1;
+	}
+
+	/// Replaces the vertex data of every stream
+	///
+	/// * `streams` - the vertex data of each stream in the layout
+	pub fn set_streams(&mut self, streams: Vec<Vec<f32>>) {
+		assert_eq!(streams.len(), self.layout.streams.len(), "Mesh needs one vertex array per stream");
+		self.streams = streams;
+		self.version += 1;
+		self.update_bounds();
+	}
+
+	/// Replaces the vertex data of the first stream
+	///
+	/// For a mesh made with new that is every vertex.
+	///
+	/// * `vertices` - A vector of vertices optimized for rendering vs physics
+	///   stored like [x1, y1, z1, x2, y2, z2]
+	pub fn set_vertices(&mut self, vertices: Vec<f32>) {
+		self.streams[0] = vertices;
+		self.version += 1;
+		self.update_bounds();
+	}
+
+	/// Copies the mesh with each edge of its triangles as a line instead
+	///
+	/// The copy is a new mesh, so it doesn't follow later edits to this one.
+	pub fn to_wireframe(&self) -> Mesh {
+		Mesh::with_layout(self.triangle_indices.to_edges(), self.layout.clone(), self.streams.clone())
+	}
+
+	/// Creates a new mesh with any vertex layout
+	///
+	/// * `triangle_indices` - Contains an index array for rendering
+	/// * `layout` - how the vertex data is split into streams
+	/// * `streams` - the vertex data of each stream in the layout
+	pub fn with_layout(triangle_indices: Indices, layout: VertexLayout, streams: Vec<Vec<f32>>) -> Mesh {
+		assert_eq!(streams.len(), layout.streams.len(), "Mesh needs one vertex array per stream");
+
+		let positions = Self::positions_of(&layout, &streams);
+
+		Mesh {
+			bounding_sphere: BoundingSphere::from_points(&positions),
+			bounds: Aabb::from_points(&positions),
+			id: NEXT_MESH_ID.fetch_add(1, Ordering::Relaxed),
+			layout,
+			streams,
+			triangle_indices,
+			version: 0
+		}
+	}
+
+	/// Pulls the position of every vertex out of vertex data
+	///
+	/// * `layout` - how the vertex data is split into streams
+	/// * `streams` - the vertex data of each stream in the layout
+	fn positions_of(layout: &VertexLayout, streams: &[Vec<f32>]) -> Vec<[f32; 3]> {
+		let (stream_index, stream) = match layout.find(VertexAttribute::Position) {
+			Some(found) => found,
+			None => return Vec::new()
+		};
+		let offset = stream.offset_of(VertexAttribute::Position).unwrap_or(0);
+
+		streams[stream_index]
+			.chunks_exact(stream.stride())
+			.map(|vertex| [vertex[offset], vertex[offset + 1], vertex[offset + 2]])
+			.collect()
+	}
+
+	/// Fits the bounds around the vertices again after they change
+	fn update_bounds(&mut self) {
+		let positions = self.get_positions();
+		self.bounding_sphere = BoundingSphere::from_points(&positions);
+		self.bounds = Aabb::from_points(&positions);
+	}
+}
+
+impl Clone for Mesh {
+	/// Copies the geometry into a brand new mesh
+	///
+	/// The copy gets its own id, so editing it never touches the buffers of
+	/// the mesh it came from.
+	fn clone(&self) -> Mesh {
+		Mesh::with_layout(self.triangle_indices.clone(), self.layout.clone(), self.streams.clone())
+	}
+}
+
+/// The GPU side of a mesh
+pub struct GpuMesh {
+	/// The buffers to draw, more than one if the mesh had to be split
+	pub parts: Vec<GpuMeshPart>,
+	/// The mesh these buffers were uploaded from
+	source: Weak<Mesh>,
+	/// The version of the mesh that was last uploaded, if any
+	version: Option<u32>
+}
+
+/// Vertex and index buffers that can be drawn in one call
+pub struct GpuMeshPart {
+	/// Number of indices to draw
+	pub index_count: i32,
+	/// Buffer holding the index array
+	pub index_buffer: Option<BufferHandle>,
+	/// The type of each index in the index buffer
+	pub index_type: IndexType,
+	/// Buffers holding each stream of vertex data, in layout order
+	pub vertex_buffers: Vec<Option<BufferHandle>>
+}
+
+/// Keeps the GPU buffers of every mesh that has been drawn
+#[derive(Default)]
+pub struct MeshCache {
+	/// GPU meshes by mesh id
+	meshes: HashMap<u32, GpuMesh>
+}
+
+impl MeshCache {
+	/// Frees the buffers of every mesh
+	///
+	/// * `backend` - the backend the buffers were created with
+	pub fn clear<B: RenderBackend>(&mut self, backend: &mut B) {
+		for (_, mut gpu_mesh) in self.meshes.drain() {
+			free_parts(backend, &mut gpu_mesh);
+		}
+	}
+
+	/// Frees the buffers of meshes that no longer exist
+	///
+	/// * `backend` - the backend the buffers were created with
+	pub fn collect_garbage<B: RenderBackend>(&mut self, backend: &mut B) {
+		self.meshes.retain(|_, gpu_mesh| {
+			let alive = gpu_mesh.source.strong_count() > 0;
+			if !alive {
+				free_parts(backend, gpu_mesh);
+			}
+
+			alive
+		});
+	}
+
+	/// Creates an empty cache
+	pub fn new() -> MeshCache {
+		MeshCache {
+			meshes: HashMap::new()
+		}
+	}
+
+	/// Gets the GPU buffers for a mesh, uploading it first if it is new or
+	/// has changed since it was last uploaded
+	///
+	/// * `backend` - the backend to upload with
+	/// * `mesh` - the mesh to prepare
+	///
+	/// Returns - the GPU side of the mesh
+	pub fn prepare<B: RenderBackend>(&mut self, backend: &mut B, mesh: &Rc<Mesh>) -> &GpuMesh {
+		let gpu_mesh = self.meshes.entry(mesh.get_id()).or_insert_with(|| GpuMesh {
+			parts: Vec::new(),
+			source: Weak::new(),
+			version: None
+		});
+
+		// Upload only if the geometry is new or changed
+		// Editing a mesh can move it to a new Rc, so follow it there too
+		if gpu_mesh.version != Some(mesh.get_version()) {
+			free_parts(backend, gpu_mesh);
+			gpu_mesh.parts = upload(backend, mesh);
+			gpu_mesh.source = Rc::downgrade(mesh);
+			gpu_mesh.version = Some(mesh.get_version());
+		}
+
+		gpu_mesh
+	}
+}
+
+/// Splits a mesh into pieces that can each be drawn with 16 bit indices
+///
+/// Indices are taken six at a time, so triangles and the lines of a wireframe
+/// are both kept whole and in order. A new piece starts whenever the next six
+/// would push the current one past MAX_U16_VERTICES.
+///
+/// * `streams` - the vertex data of each stream of the mesh
+/// * `strides` - the number of floats in each vertex of each stream
+/// * `indices` - the full width index array of the mesh
+///
+/// Returns - the vertex data of each stream and the index array of each piece
+pub fn split_for_u16(streams: &[Vec<f32>], strides: &[usize], indices: &[u32]) -> Vec<(Vec<Vec<f32>>, Vec<u16>)> {
+	let mut pieces = Vec::new();
+	let mut piece_streams = vec![Vec::new(); streams.len()];
+	let mut piece_indices = Vec::new();
+	let mut remap: HashMap<u32, u16> = HashMap::new();
+
+	for primitives in indices.chunks(6) {
+		// Start a new piece if these primitives' new vertices don't fit
+		let new_vertices = primitives.iter()
+			.filter(|index| !remap.contains_key(index))
+			.count();
+		if remap.len() + new_vertices > MAX_U16_VERTICES {
+			pieces.push((piece_streams, piece_indices));
+			piece_streams = vec![Vec::new(); streams.len()];
+			piece_indices = Vec::new();
+			remap.clear();
+		}
+
+		// Copy the primitives over, bringing their vertices along
+		for &index in primitives {
+			let next_index = remap.len() as u16;
+			let new_index = *remap.entry(index).or_insert_with(|| {
+				for ((stream, piece_stream), &stride) in streams.iter().zip(piece_streams.iter_mut()).zip(strides) {
+					let start = index as usize * stride;
+					piece_stream.extend_from_slice(&stream[start..start + stride]);
+				}
+
+				next_index
+			});
+			piece_indices.push(new_index);
+		}
+	}
+
+	if !piece_indices.is_empty() {
+		pieces.push((piece_streams, piece_indices));
+	}
+
+	pieces
+}
+
+/// Frees the buffers of every part of a GPU mesh
+fn free_parts<B: RenderBackend>(backend: &mut B, gpu_mesh: &mut GpuMesh) {
+	for part in gpu_mesh.parts.drain(..) {
+		for buffer in part.vertex_buffers.into_iter().flatten() {
+			backend.delete_buffer(buffer);
+		}
+		if let Some(buffer) = part.index_buffer {
+			backend.delete_buffer(buffer);
+		}
+	}
+}
+
+/// Uploads a mesh's geometry into new GPU buffers
+///
+/// Meshes with 32 bit indices get split up if the backend can't draw them.
+fn upload<B: RenderBackend>(backend: &mut B, mesh: &Mesh) -> Vec<GpuMeshPart> {
+	match mesh.get_triangle_indices() {
+		Indices::U32(indices) if !backend.capabilities().uint_indices => {
+			let strides: Vec<usize> = mesh.get_layout().streams.iter()
+				.map(|stream| stream.stride())
+				.collect();
+
+			split_for_u16(mesh.get_streams(), &strides, indices)
+				.into_iter()
+				.map(|(streams, indices)| upload_part(backend, &streams, &Indices::U16(indices)))
+				.collect()
+		},
+		indices => vec![upload_part(backend, mesh.get_streams(), indices)]
+	}
+}
+
+/// Uploads one set of vertex streams and their index array into new GPU buffers
+fn upload_part<B: RenderBackend>(backend: &mut B, streams: &[Vec<f32>], indices: &Indices) -> GpuMeshPart {
+	let vertex_buffers = streams.iter()
+		.map(|stream| {
+			let vertex_buffer = backend.create_buffer();
+			backend.bind_buffer(BufferTarget::Array, vertex_buffer);
+			backend.buffer_data_f32(BufferTarget::Array, stream, BufferUsage::Static);
+
+			vertex_buffer
+		})
+		.collect();
+
+	let index_buffer = backend.create_buffer();
+	backend.bind_buffer(BufferTarget::ElementArray, index_buffer);
+	match indices {
+		Indices::U8(data) => backend.buffer_data_u8(BufferTarget::ElementArray, data, BufferUsage::Static),
+		Indices::U16(data) => backend.buffer_data_u16(BufferTarget::ElementArray, data, BufferUsage::Static),
+		Indices::U32(data) => backend.buffer_data_u32(BufferTarget::ElementArray, data, BufferUsage::Static)
+	}
+
+	GpuMeshPart {
+		index_count: indices.len() as i32,
+		index_buffer,
+		index_type: indices.get_index_type(),
+		vertex_buffers
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use nalgebra::Vector3;
+
+	use crate::{
+		graphics::{
+			backend::{headless::{Command, HeadlessBackend}, Capabilities},
+			material::Material,
+			shadows::ShadowSettings,
+			Graphics
+		},
+		logic::{object::Object, world::World}
+	};
+	use super::*;
+
+	#[test]
+	fn edited_meshes_keep_their_buffers() {
+		let mut graphics = Graphics::new(HeadlessBackend::new(800, 600));
+		graphics.set_shadow_settings(ShadowSettings { enabled: false, ..ShadowSettings::default() });
+		assert!(graphics.compile_shaders().is_empty());
+
+		// The object is the only one holding its mesh, so editing it moves
+		// the mesh rather than copying it
+		let vertices = vec![-0.1, 0.0, 0.0, 0.1, 0.0, 0.0, 0.0, 0.1, 0.0];
+		let object = Object::new(Vector3::zeros(), 0.0, 0.0, 0.0, 1.0, Rc::new(Material::new("3d orange")), Indices::U8(vec![0, 1, 2]), vertices);
+		let mut world = World::new(vec![object]);
+		graphics.render(&world);
+
+		world.get_objects_mut()[0].set_vertices(vec![-0.2, 0.0, 0.0, 0.2, 0.0, 0.0, 0.0, 0.2, 0.0]);
+		graphics.backend_mut().clear_commands();
+		graphics.render(&world);
+
+		// Only the old vertex and index buffers get freed
+		let deleted = |graphics: &Graphics<HeadlessBackend>| graphics.backend().commands().iter()
+			.filter(|command| matches!(command, Command::DeleteBuffer {..}))
+			.count();
+		assert_eq!(deleted(&graphics), 2);
+
+		// And the new ones are still there for the next frame
+		graphics.backend_mut().clear_commands();
+		graphics.render(&world);
+		assert_eq!(deleted(&graphics), 0);
+		assert!(!graphics.backend().commands().iter().any(|command| matches!(command, Command::BufferDataF32 {..} | Command::BufferDataU8 {..})));
+	}
+
+	#[test]
+	fn splits_big_meshes_for_u16_indices() {
+		let mut backend = HeadlessBackend::new(1, 1);
+		backend.set_capabilities(Capabilities { uint_indices: false, ..Capabilities::default() });
+
+		// A strip of triangles over more vertices than 16 bits can index,
+		// with each vertex's x being its index
+		let vertex_count = 70000;
+		let vertices = (0..vertex_count).flat_map(|index| [index as f32, 0.0, 0.0]).collect();
+		let indices: Vec<u32> = (0..vertex_count - 2).flat_map(|first| [first, first + 1, first + 2]).collect();
+		let mesh = Rc::new(Mesh::new(Indices::U32(indices.clone()), vertices));
+
+		let mut cache = MeshCache::new();
+		let parts = cache.prepare(&mut backend, &mesh).parts.len();
+		assert!(parts > 1);
+
+		// Each part uploads its vertices, then its indices
+		let mut part_vertices = Vec::new();
+		let mut drawn = Vec::new();
+		for command in backend.commands() {
+			match command {
+				Command::BufferDataF32 { data, .. } => part_vertices = data.clone(),
+				Command::BufferDataU16 { data, .. } => {
+					let part_vertex_count = part_vertices.len() / 3;
+					assert!(part_vertex_count <= MAX_U16_VERTICES);
+					for &index in data {
+						assert!((index as usize) < part_vertex_count);
+						drawn.push(part_vertices[index as usize * 3] as u32);
+					}
+				},
+				Command::BufferDataU32 {..} => panic!("32 bit indices were uploaded"),
+				_ => ()
+			}
+		}
+
+		// Every triangle is drawn whole, in its original order
+		assert_eq!(drawn, indices);
+	}
+
+	#[test]
+	fn narrowest_skips_restart_indices() {
+		assert!(matches!(Indices::narrowest(vec![0, 254]), Indices::U8(_)));
+		assert!(matches!(Indices::narrowest(vec![0, 255]), Indices::U16(_)));
+		assert!(matches!(Indices::narrowest(vec![0, 65534]), Indices::U16(_)));
+		assert!(matches!(Indices::narrowest(vec![0, 65535]), Indices::U32(_)));
+	}
+}
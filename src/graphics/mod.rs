@@ -1,195 +1,1349 @@
-mod camera;
+pub mod backend;
+pub mod bounds;
+pub mod camera;
+pub mod culling;
+pub mod debug_draw;
+pub mod frame_stats;
 pub mod gl;
+pub mod instancing;
+pub mod lighting;
+pub mod material;
+pub mod mesh;
+pub mod post_process;
+pub mod render_mode;
+pub mod render_queue;
+pub mod render_state;
+pub mod render_target;
 pub mod shaders;
+pub mod shadows;
+pub mod sky;
+pub mod texture;
+pub mod vertex_layout;
 
-use std::collections::HashMap;
+use std::{collections::HashMap, rc::{Rc, Weak}};
 
 use nalgebra::{Matrix4, Point3};
-use wasm_bindgen::JsCast;
-use web_sys::{WebGlRenderingContext, HtmlCanvasElement, WebGlBuffer};
 
-use crate::logic::world::World;
-use self::{shaders::{shader_sources::get_shader_sources, CompiledShader, UniformType, Uniform}, camera::Camera};
+use crate::logic::{light::Light, object::Object, world::World};
+use self::{
+	backend::{
+		state_cache::{RenderStats, StateCache},
+		webgl::WebGlBackend,
+		RenderBackend,
+		TextureHandle,
+		TextureTarget
+	},
+	camera::Camera,
+	culling::{CullStats, Frustum},
+	debug_draw::DebugLineBuffer,
+	frame_stats::{now, FrameStats},
+	instancing::{can_instance, group_instances, InstanceBuffer, INSTANCED_DEFINE},
+	lighting::{nearest_lights, set_light_uniforms, MAX_LIGHTS},
+	material::Material,
+	mesh::{Mesh, MeshCache},
+	post_process::{EffectKind, PostProcess, PostProcessError, BLOOM_TARGETS, PING_PONG_TARGETS, SCENE_TARGET},
+	render_mode::{clip_planes_of, RenderMode},
+	render_queue::{depth_key, DrawItem, RenderPass, RenderQueue, SortKey},
+	render_state::RenderState,
+	render_target::{DepthAttachment, RenderTarget, RenderTargetError, RenderTargetOptions, TargetSize},
+	shaders::{
+		cache::ShaderCache,
+		preprocessor::Defines,
+		CompiledShader,
+		ShaderError,
+		UniformError,
+		UniformType,
+		UniformValue,
+		Uniform
+	},
+	shadows::{
+		plan_shadows,
+		set_shadow_uniforms,
+		ShadowFrame,
+		ShadowSettings,
+		CASCADE_TARGET,
+		MAX_SPOT_SHADOWS,
+		SHADOW_CASCADES,
+		SHADOW_SHADER,
+		SPOT_TARGET
+	},
+	sky::{sky_matrix, Background},
+	texture::{decode_image, FilterMode, Image, Texture, TextureError, TextureOptions}
+};
+#[cfg(debug_assertions)]
+use self::debug_draw::{DebugDraw, DEBUG_LINES_SHADER};
 
-pub struct Graphics {
-	// The rendering buffer
-	array_buffer: Option<WebGlBuffer>,
+pub struct Graphics<B: RenderBackend> {
+	/// The backend all rendering goes through, skipping calls that wouldn't
+	/// change anything
+	backend: StateCache<B>,
+	/// What gets drawn behind everything else
+	background: Background,
 	/// A camera to be rendered from
 	camera: Camera,
-	/// The webgl context to render to
-	context: WebGlRenderingContext,
-	/// The index buffer
-	index_buffer: Option<WebGlBuffer>,
-	/// The projection matrix to apply to renders
-	projection_matrix: Matrix4<f32>,
-	/// The shaders that have been compiled
-	shaders: HashMap<&'static str, CompiledShader>
+	/// How many objects were culled and drawn in the last frame
+	cull_stats: CullStats,
+	/// Where debug lines get uploaded to be drawn
+	debug_lines: DebugLineBuffer,
+	/// What the last frame drew, and how long it took
+	frame_stats: FrameStats,
+	/// Model matrices for instanced draws
+	instances: InstanceBuffer,
+	/// GPU buffers for every mesh that has been rendered
+	meshes: MeshCache,
+	/// Fullscreen effects applied to the main view
+	post_process: PostProcess,
+	/// Whether views are drawn shaded or as a view for finding bugs
+	render_mode: RenderMode,
+	/// State for the main pass, used by objects that don't bring their own
+	render_state: RenderState,
+	/// Framebuffers that can be rendered into and sampled, by name
+	render_targets: HashMap<String, RenderTarget>,
+	/// Every permutation of every shader that has been built
+	shaders: ShaderCache,
+	/// How shadows get drawn
+	shadow_settings: ShadowSettings,
+	/// Textures that have been uploaded, by name
+	textures: HashMap<String, Texture>,
+	/// Problems setting material uniforms during the last frame
+	uniform_errors: Vec<UniformError>,
+	/// Wireframe copies of meshes by the id of the mesh they were made from,
+	/// with that mesh and the version they were made from
+	wireframes: HashMap<u32, (Weak<Mesh>, u32, Rc<Mesh>)>
 }
 
 /// Holds all information regarding the graphics of the application
-impl Graphics {
-	/// Compiles shaders and returns them as a hash map
-///
-/// * `context` - the webGL rendering context these are being compiled in
-pub fn compile_shaders(&mut self) {
-	// Compile shaders
-	let shader_source = get_shader_sources();
-	let mut shaders = HashMap::new();
-
-	// Compile each shader and insert to map
-	for (name, source) in shader_source.iter() {
-		// Compile program
-		let compiled_program = gl::build_program(&self.context, source);
-
-		// Check if it compiled correctly
-		match &compiled_program {
-			Some(program) => {
-				// If success
-				// Find uniform locations
-				// Start with MVP uniforms
-				let model_location = self.context.get_uniform_location(&program, "model");
-				let model_uniform = Uniform {
-					location: model_location,
-					u_type: UniformType::Mat4
-				};
-				let view_location = self.context.get_uniform_location(&program, "view");
-				let view_uniform = Uniform {
-					location: view_location,
-					u_type: UniformType::Mat4
-				};
-				let projection_location = self.context.get_uniform_location(&program, "projection");
-				let projection_uniform = Uniform {
-					location: projection_location,
-					u_type: UniformType::Mat4
-				};
+impl<B: RenderBackend> Graphics<B> {
+	/// Gets the backend this renders through
+	pub fn backend(&self) -> &B {
+		self.backend.get_inner()
+	}
 
-				// Find other uniforms
-				let mut uniforms = HashMap::new();
+	/// Gets the backend this renders through, mutably
+	///
+	/// Anything could be changed through it, so the next frame sets all of
+	/// its state again.
+	pub fn backend_mut(&mut self) -> &mut B {
+		self.backend.invalidate();
+		self.backend.get_inner_mut()
+	}
 
-				// Run through each uniform name
-				// there should be a matching type in uniform_types
-				for i in 0..source.uniform_names.len() {
-					let uniform_name = source.uniform_names[i];
-					let u_type = source.uniform_types[i];
+	/// Gets what gets drawn behind everything else
+	pub fn get_background(&self) -> &Background {
+		&self.background
+	}
 
-					let location = self.context.get_uniform_location(&program, uniform_name);
+	/// Gets the camera being rendered from
+	pub fn get_camera(&self) -> &Camera {
+		&self.camera
+	}
 
-					let uniform = Uniform {location, u_type};
+	/// Gets the camera being rendered from, mutably
+	pub fn get_camera_mut(&mut self) -> &mut Camera {
+		&mut self.camera
+	}
 
-					uniforms.insert(uniform_name, uniform);
-				}
+	/// Gets how many objects were culled and drawn in the last frame, added
+	/// up over every view drawn
+	pub fn get_cull_stats(&self) -> &CullStats {
+		&self.cull_stats
+	}
 
-				// Put it all together
-				let compiled_shader = CompiledShader {
-					model_uniform: Some(model_uniform),
-					program: compiled_program,
-					projection_uniform: Some(projection_uniform),
-					uniforms,
-					view_uniform: Some(view_uniform)
-				};
-				shaders.insert(name as &str, compiled_shader);
-			},
-			_ => ()
+	/// Gets what the last frame drew, and how long each part of it took
+	pub fn get_frame_stats(&self) -> &FrameStats {
+		&self.frame_stats
+	}
+
+	/// Gets the post-processing stack
+	pub fn get_post_process(&self) -> &PostProcess {
+		&self.post_process
+	}
+
+	/// Gets the post-processing stack, mutably
+	pub fn get_post_process_mut(&mut self) -> &mut PostProcess {
+		&mut self.post_process
+	}
+
+	/// Gets a render target
+	///
+	/// * `name` - the name the target was created with
+	pub fn get_render_target(&self, name: &str) -> Option<&RenderTarget> {
+		self.render_targets.get(name)
+	}
+
+	/// Gets a render target mutably, to give it a camera or move it
+	///
+	/// * `name` - the name the target was created with
+	pub fn get_render_target_mut(&mut self, name: &str) -> Option<&mut RenderTarget> {
+		self.render_targets.get_mut(name)
+	}
+
+	/// Gets an uploaded texture
+	///
+	/// * `name` - the name the texture was uploaded with
+	pub fn get_texture(&self, name: &str) -> Option<&Texture> {
+		self.textures.get(name)
+	}
+
+	/// Gets what went wrong with every shader permutation that didn't build
+	pub fn get_shader_errors(&self) -> Vec<&ShaderError> {
+		self.shaders.get_errors()
+	}
+
+	/// Gets the shader cache, to add files shaders can include
+	pub fn get_shaders_mut(&mut self) -> &mut ShaderCache {
+		&mut self.shaders
+	}
+
+	/// Gets how shadows get drawn
+	pub fn get_shadow_settings(&self) -> &ShadowSettings {
+		&self.shadow_settings
+	}
+
+	/// Gets the problems setting material uniforms during the last frame
+	pub fn get_uniform_errors(&self) -> &[UniformError] {
+		&self.uniform_errors
+	}
+
+	/// Gets whether views are drawn shaded or as a view for finding bugs
+	pub fn get_render_mode(&self) -> RenderMode {
+		self.render_mode
+	}
+
+	/// Gets the state the main pass renders with
+	pub fn get_render_state(&self) -> &RenderState {
+		&self.render_state
+	}
+
+	/// Gets how many calls the last frame made, and how many it skipped
+	/// because they wouldn't have changed anything
+	pub fn get_render_stats(&self) -> &RenderStats {
+		self.backend.get_stats()
+	}
+
+	/// Changes what gets drawn behind everything else
+	///
+	/// * `background` - the new background
+	pub fn set_background(&mut self, background: Background) {
+		self.background = background;
+	}
+
+	/// Changes the clipping planes of the camera
+	///
+	/// * `near` - distance to the near clipping plane
+	/// * `far` - distance to the far clipping plane
+	pub fn set_clip_planes(&mut self, near: f32, far: f32) {
+		self.camera.get_projection_mut().set_clip_planes(near, far);
+	}
+
+	/// Changes a parameter of a post-processing effect
+	///
+	/// * `index` - the position of the effect in the stack
+	/// * `name` - the name of the parameter
+	/// * `value` - the value to set
+	///
+	/// Returns - what's wrong if the effect doesn't take this parameter
+	pub fn set_post_effect_param(&mut self, index: usize, name: &str, value: UniformValue) -> Result<(), PostProcessError> {
+		let effect = self.post_process.get_effects_mut()
+			.get_mut(index)
+			.ok_or(PostProcessError::NoEffect(index))?;
+
+		// Check it against the shader, if the shader built
+		let shader_name = effect.get_kind().get_shader_name();
+		if let Some(shader) = self.shaders.get(shader_name, effect.get_material().get_defines()) {
+			let uniform = shader.uniforms.get(name)
+				.ok_or_else(|| PostProcessError::Param(UniformError::Unknown { name: String::from(name) }))?;
+			if !uniform.u_type.accepts(&value) {
+				return Err(PostProcessError::Param(UniformError::TypeMismatch {
+					name: String::from(name),
+					expected: uniform.u_type.clone(),
+					found: value.get_type()
+				}));
+			}
 		}
+
+		effect.set_param(name, value);
+
+		Ok(())
 	}
 
-	self.shaders = shaders;
-}
+	/// Changes whether views are drawn shaded or as a view for finding bugs
+	///
+	/// Leaving wireframe mode frees the wireframes.
+	///
+	/// * `render_mode` - the new mode
+	pub fn set_render_mode(&mut self, render_mode: RenderMode) {
+		self.render_mode = render_mode;
+
+		if render_mode != RenderMode::Wireframe {
+			self.wireframes.clear();
+		}
+	}
 
+	/// Changes the state the main pass renders with
+	///
+	/// * `render_state` - the new state
+	pub fn set_render_state(&mut self, render_state: RenderState) {
+		self.render_state = render_state;
+	}
 
-	/// Initialize graphics
-	pub fn init() -> Graphics {
-		// Set up the front end
-		let context = gl::set_up_canvas();
+	/// Changes how shadows get drawn
+	///
+	/// Turning shadows off frees the shadow maps.
+	///
+	/// * `shadow_settings` - the new settings
+	pub fn set_shadow_settings(&mut self, shadow_settings: ShadowSettings) {
+		self.shadow_settings = shadow_settings;
 
-		// Create generic camera
-		let origin = Point3::new(0.0, 0.0, 0.0);
-		let starting_location = Point3::new(0.0, 0.0, 4.0);
-		let camera = Camera::new_targeted(starting_location, origin, 0.0);
+		if !shadow_settings.enabled {
+			self.delete_render_target(CASCADE_TARGET);
+			self.delete_render_target(SPOT_TARGET);
+		}
+	}
 
-		// Create projection matrix
-		// Find information about the screen being rendered to
-		let canvas: HtmlCanvasElement = context.canvas().unwrap().dyn_into::<HtmlCanvasElement>().unwrap();
-		let width = canvas.client_width() as f32;
-		let height = canvas.client_height() as f32;
-		let aspect_ratio = width / height;
+	/// Creates a framebuffer to render into that materials can sample
+	///
+	/// Materials sample the color texture by the target's name, and the depth
+	/// texture by the name with .depth on the end. Uploaded textures win over
+	/// targets with the same name. A target already using the name gets freed
+	/// and replaced.
+	///
+	/// * `name` - the name to give the target
+	/// * `options` - how to set it up
+	///
+	/// Returns - why the target couldn't be made
+	pub fn create_render_target(&mut self, name: &str, options: &RenderTargetOptions) -> Result<(), RenderTargetError> {
+		let canvas_size = self.backend.drawing_buffer_size();
+		let target = RenderTarget::create(&mut self.backend, options, canvas_size)?;
+		if let Some(old) = self.render_targets.insert(String::from(name), target) {
+			old.delete(&mut self.backend);
+		}
 
-		// If the screen were 16:9, then the x field of view should be 90 degrees
-		let fov_x_degrees_16x9 = 90.0;
-		// Convert to y FOV in radians without worrying about the aspect ratio
-		let fov_y_radians = fov_x_degrees_16x9 * 9.0 / 16.0 * 3.14159 / 180.0;
+		Ok(())
+	}
 
-		let projection_matrix: Matrix4<f32> = Matrix4::new_perspective(aspect_ratio, fov_y_radians, 0.0, 100.0);
+	/// Uploads an image as a named texture
+	///
+	/// Materials sample it by this name. A texture already using the name gets
+	/// freed and replaced.
+	///
+	/// * `name` - the name to give the texture
+	/// * `image` - the pixels to upload
+	/// * `options` - how the texture gets sampled
+	///
+	/// Returns - an error if the image is empty or its pixels don't fit its
+	/// size
+	pub fn create_texture(&mut self, name: &str, image: &Image, options: &TextureOptions) -> Result<(), TextureError> {
+		let texture = Texture::upload(&mut self.backend, image, options)?;
+		if let Some(old) = self.textures.insert(String::from(name), texture) {
+			old.delete(&mut self.backend);
+		}
 
-		// Create and bind array buffer for webGL
-		let array_buffer = context.create_buffer();
-		context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, array_buffer.as_ref());
+		Ok(())
+	}
 
-		// Create index buffer for webGL
-		let index_buffer = context.create_buffer();
+	/// Uploads six images as a named cube map
+	///
+	/// Skyboxes and samplerCube uniforms find it by this name. A texture
+	/// already using the name gets freed and replaced.
+	///
+	/// * `name` - the name to give the cube map
+	/// * `faces` - the images, in the order of CubeFace::ALL
+	/// * `options` - how the cube map gets sampled
+	///
+	/// Returns - an error if the faces aren't all squares of the same size
+	pub fn create_cube_map(&mut self, name: &str, faces: &[Image; 6], options: &TextureOptions) -> Result<(), TextureError> {
+		let texture = Texture::upload_cube(&mut self.backend, faces, options)?;
+		if let Some(old) = self.textures.insert(String::from(name), texture) {
+			old.delete(&mut self.backend);
+		}
 
-		let shaders = HashMap::new();
+		Ok(())
+	}
+
+	/// Frees a render target
+	///
+	/// * `name` - the name the target was created with
+	///
+	/// Returns - whether there was a target to free
+	pub fn delete_render_target(&mut self, name: &str) -> bool {
+		match self.render_targets.remove(name) {
+			Some(target) => {
+				target.delete(&mut self.backend);
+				true
+			},
+			None => false
+		}
+	}
+
+	/// Decodes six PNGs or JPEGs and uploads them as a named cube map
+	///
+	/// * `name` - the name to give the cube map
+	/// * `faces` - the encoded image files, in the order of CubeFace::ALL
+	/// * `options` - how the cube map gets sampled
+	pub fn load_cube_map(&mut self, name: &str, faces: [&[u8]; 6], options: &TextureOptions) -> Result<(), TextureError> {
+		let mut images = Vec::with_capacity(6);
+		for bytes in faces {
+			images.push(decode_image(bytes)?);
+		}
+
+		// Always six, one for each face
+		let images: [Image; 6] = images.try_into().unwrap();
+		self.create_cube_map(name, &images, options)
+	}
+
+	/// Decodes a PNG or JPEG panorama and uploads it as a named cube map
+	///
+	/// Each face gets a quarter of the panorama's width, which is about as
+	/// sharp as the panorama is.
+	///
+	/// * `name` - the name to give the cube map
+	/// * `bytes` - the encoded image file, twice as wide as it is tall
+	/// * `options` - how the cube map gets sampled
+	pub fn load_equirectangular(&mut self, name: &str, bytes: &[u8], options: &TextureOptions) -> Result<(), TextureError> {
+		let image = decode_image(bytes)?;
+		let faces = image.equirectangular_to_cube(image.width / 4);
+		self.create_cube_map(name, &faces, options)
+	}
+
+	/// Decodes a PNG or JPEG and uploads it as a named texture
+	///
+	/// * `name` - the name to give the texture
+	/// * `bytes` - the encoded image file
+	/// * `options` - how the texture gets sampled
+	pub fn load_texture(&mut self, name: &str, bytes: &[u8], options: &TextureOptions) -> Result<(), TextureError> {
+		let image = decode_image(bytes)?;
+		self.create_texture(name, &image, options)
+	}
+
+	/// Compiles every shader source and stores the ones that built
+	///
+	/// Any reloaded source is thrown out, and other permutations get built
+	/// again when they're next needed.
+	///
+	/// Returns - what went wrong with each shader that didn't build
+	pub fn compile_shaders(&mut self) -> Vec<ShaderError> {
+		self.shaders.compile_all(&mut self.backend)
+	}
+
+	/// Creates graphics that render through a backend
+	///
+	/// * `backend` - the backend to render through
+	pub fn new(backend: B) -> Graphics<B> {
+		// Create generic camera
+		let origin = Point3::new(0.0, 0.0, 0.0);
+		let starting_location = Point3::new(0.0, 0.0, 4.0);
+		let mut camera = Camera::new_targeted(starting_location, origin, 0.0);
+
+		// Fit the projection to the screen being rendered to
+		let (width, height) = backend.drawing_buffer_size();
+		let aspect_ratio = width as f32 / height as f32;
+		camera.get_projection_mut().set_aspect_ratio(aspect_ratio);
+
+		let meshes = MeshCache::new();
+		let render_state = RenderState::opaque();
+		let shaders = ShaderCache::new();
 
 		// Return newly created Graphics object
 		Graphics {
-			array_buffer,
-			context,
-			index_buffer,
+			backend: StateCache::new(backend),
+			background: Background::default(),
+			cull_stats: CullStats::default(),
+			debug_lines: DebugLineBuffer::new(),
+			frame_stats: FrameStats::default(),
+			instances: InstanceBuffer::new(),
+			meshes,
+			post_process: PostProcess::new(),
+			render_mode: RenderMode::Shaded,
+			render_state,
+			render_targets: HashMap::new(),
 			shaders,
+			shadow_settings: ShadowSettings::default(),
 			camera,
-			projection_matrix
+			textures: HashMap::new(),
+			uniform_errors: Vec::new(),
+			wireframes: HashMap::new()
 		}
 	}
 
-	/// Renders a frame to the screen
+	/// Recompiles a shader from new source code and swaps it in
+	///
+	/// The shader keeps the attributes and uniforms it was declared with in
+	/// shader_sources. If the new source doesn't build, the old program stays
+	/// in use. Compiling every shader again goes back to the built in source.
+	///
+	/// * `name` - the name of the shader to replace
+	/// * `vertex_shader` - new vertex shader source
+	/// * `fragment_shader` - new fragment shader source
+	///
+	/// Returns - what went wrong if the new source didn't build
+	pub fn reload_shader(&mut self, name: &str, vertex_shader: &str, fragment_shader: &str) -> Result<(), ShaderError> {
+		self.shaders.reload(&mut self.backend, name, vertex_shader, fragment_shader)
+	}
+
+	/// Renders a frame
+	///
+	/// Shadow maps are drawn first, then render targets with a camera, in
+	/// order of name, then the main camera's view is drawn to the canvas.
 	pub fn render(&mut self, world: &World) {
-		// Set gl to the rendering context for easier use
-		let gl = &self.context;
+		let start = now();
+		self.uniform_errors.clear();
+		self.backend.reset_stats();
+		self.cull_stats = CullStats::default();
 
-		// Clear the screen for rendering
-		gl.clear_color(0.0, 0.0, 0.0, 1.0);
-		gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+		// Build any shader permutations that haven't been used yet
+		// Ones that don't build are skipped, and reported by get_shader_errors
+		for object in world.get_objects() {
+			let material = object.get_material();
+			let _ = self.shaders.prepare(&mut self.backend, material.get_shader_name(), material.get_defines());
+		}
+		let prepared = now();
+
+		// Draw the shadows everything else gets lit with
+		let shadows = self.draw_shadows(world);
+		let shadowed = now();
+
+		// Draw into every target with a camera
+		let mut target_names: Vec<String> = self.render_targets.iter()
+			.filter(|(_, target)| target.get_camera().is_some())
+			.map(|(name, _)| name.clone())
+			.collect();
+		target_names.sort();
+		for name in &target_names {
+			let target = &self.render_targets[name];
+			let camera = match target.get_camera() {
+				Some(camera) => camera,
+				None => continue
+			};
+
+			// Fit the projection to the target
+			let (width, height) = target.get_size();
+			let mut projection = *camera.get_projection();
+			projection.set_aspect_ratio(width as f32 / height as f32);
+			let view_matrix = *camera.get_view_matrix();
+			let camera_position = *camera.get_location();
+
+			target.bind(&mut self.backend);
+			self.draw_world(world, &view_matrix, projection.get_matrix(), &camera_position, Some(name), &shadows);
+		}
+		let targets_drawn = now();
+
+		// Draw the main view, offscreen first if it's getting post-processed
+		let post_processing = self.post_process.is_active() && self.prepare_post_targets();
+		let main_target = if post_processing {
+			self.render_targets[SCENE_TARGET].bind(&mut self.backend);
+			Some(SCENE_TARGET)
+		} else {
+			self.bind_canvas();
+			None
+		};
+		let view_matrix = *self.camera.get_view_matrix();
+		let projection_matrix = *self.camera.get_projection().get_matrix();
+		let camera_position = *self.camera.get_location();
+		self.draw_world(world, &view_matrix, &projection_matrix, &camera_position, main_target, &shadows);
+
+		// Draw debug shapes over it, in debug builds
+		#[cfg(debug_assertions)]
+		self.draw_debug_lines(&view_matrix, &projection_matrix);
+		let main_drawn = now();
+
+		if post_processing {
+			self.draw_post_process();
+		}
+		let post_processed = now();
+
+		// Free the buffers of meshes that are gone, and their wireframes
+		self.wireframes.retain(|_, (source, _, _)| source.strong_count() > 0);
+		self.meshes.collect_garbage(&mut self.backend);
+
+		// Keep what the frame did for get_frame_stats
+		self.frame_stats = FrameStats {
+			main_time: main_drawn - targets_drawn,
+			post_process_time: post_processed - main_drawn,
+			prepare_time: prepared - start,
+			shadow_time: shadowed - prepared,
+			target_time: targets_drawn - shadowed,
+			total_time: now() - start,
+			..FrameStats::from_counts(self.backend.get_stats(), &self.cull_stats)
+		};
+	}
+
+	/// Fits rendering to a canvas that changed size
+	///
+	/// The drawing buffer gets a pixel for every pixel on the screen, so it
+	/// stays sharp on high-DPI displays, and the camera keeps the canvas's
+	/// shape so nothing gets stretched.
+	///
+	/// * `width` - width of the canvas in CSS pixels
+	/// * `height` - height of the canvas in CSS pixels
+	/// * `dpr` - screen pixels per CSS pixel
+	pub fn resize(&mut self, width: u32, height: u32, dpr: f32) {
+		// A buffer with no pixels can't be drawn to
+		let width = width.max(1);
+		let height = height.max(1);
+		let buffer_width = ((width as f32 * dpr).round() as u32).max(1);
+		let buffer_height = ((height as f32 * dpr).round() as u32).max(1);
+		self.backend.resize_drawing_buffer(buffer_width, buffer_height);
+
+		// The browser can hand out a smaller buffer than asked for
+		let (buffer_width, buffer_height) = self.backend.drawing_buffer_size();
+		self.backend.viewport(0, 0, buffer_width, buffer_height);
+
+		// Fit the projection to the canvas
+		let aspect_ratio = width as f32 / height as f32;
+		self.camera.get_projection_mut().set_aspect_ratio(aspect_ratio);
+
+		// Resize targets that follow the canvas
+		// Ones that can't be remade are dropped
+		let canvas_size = (buffer_width, buffer_height);
+		let backend = &mut self.backend;
+		self.render_targets.retain(|_, target| target.resize(backend, canvas_size).is_ok());
+	}
+
+	/// Binds the canvas for drawing and sets the viewport to cover it
+	fn bind_canvas(&mut self) {
+		self.backend.bind_framebuffer(None);
+		let (width, height) = self.backend.drawing_buffer_size();
+		self.backend.viewport(0, 0, width, height);
+	}
+
+	/// Draws every debug shape asked for since the last frame
+	///
+	/// * `view_matrix` - the view matrix of the camera drawing them
+	/// * `projection_matrix` - the projection matrix of the camera drawing them
+	#[cfg(debug_assertions)]
+	fn draw_debug_lines(&mut self, view_matrix: &Matrix4<f32>, projection_matrix: &Matrix4<f32>) {
+		let vertices = DebugDraw::take_vertices();
+		if vertices.is_empty() {
+			return;
+		}
+
+		// Find the shader
+		let defines = Defines::new();
+		if self.shaders.prepare(&mut self.backend, DEBUG_LINES_SHADER, &defines).is_err() {
+			return;
+		}
+		let shader = match self.shaders.get(DEBUG_LINES_SHADER, &defines) {
+			Some(shader) => shader,
+			None => return
+		};
+
+		// Lines are already in world space
+		let gl = &mut self.backend;
+		gl.set_render_state(&RenderState::opaque());
+		gl.use_program(shader.program);
+		gl::set_mat4_uniform(gl, &shader.model_uniform, &Matrix4::identity());
+		gl::set_mat4_uniform(gl, &shader.view_uniform, view_matrix);
+		gl::set_mat4_uniform(gl, &shader.projection_uniform, projection_matrix);
+
+		self.debug_lines.draw(gl, &vertices);
+	}
+
+	/// Draws objects with the render mode's shader instead of their own
+	///
+	/// * `objects` - the objects the camera can see
+	/// * `view_matrix` - the view matrix of the camera drawing them
+	/// * `projection_matrix` - the projection matrix of the camera drawing them
+	fn draw_debug_view(&mut self, objects: &[&Object], view_matrix: &Matrix4<f32>, projection_matrix: &Matrix4<f32>) {
+		let mode = self.render_mode;
+		let shader_name = match mode.get_shader_name() {
+			Some(shader_name) => shader_name,
+			None => return
+		};
+
+		// Find the shader
+		let defines = Defines::new();
+		if self.shaders.prepare(&mut self.backend, shader_name, &defines).is_err() {
+			return;
+		}
+		let shader = match self.shaders.get(shader_name, &defines) {
+			Some(shader) => shader,
+			None => return
+		};
+
+		let gl = &mut self.backend;
+		gl.use_program(shader.program);
+		gl::set_mat4_uniform(gl, &shader.view_uniform, view_matrix);
+		gl::set_mat4_uniform(gl, &shader.projection_uniform, projection_matrix);
+
+		// Nothing is sampled, but materials need somewhere to look
+		let textures = TextureLookup {
+			drawing_to: None,
+			render_targets: &self.render_targets,
+			textures: &self.textures
+		};
+
+		// Depth fades out between the clipping planes
+		let (near, far) = clip_planes_of(projection_matrix);
 
-		// Render object by object
-		let objects = world.get_objects();
 		for object in objects {
-			// Get shader to use
-			let shader_name = object.get_shader_name();
-			let shader = self.shaders.get(shader_name);
+			let mut material = match mode.get_material(object) {
+				Some(material) => material,
+				None => continue
+			};
+			if mode == RenderMode::Depth {
+				material.set_uniform("depth_range", UniformValue::Vec2([near, far]));
+			}
+
+			// Keep the culling and depth the object would have had
+			let material_state = object.get_material().get_render_state().unwrap_or(&self.render_state);
+			gl.set_render_state(&mode.get_render_state(material_state));
+			apply_material(gl, shader, &material, &textures, &mut self.uniform_errors);
+
+			if mode == RenderMode::Wireframe {
+				// Make the wireframe the first time it's needed, and again
+				// whenever the mesh changes
+				let mesh = object.get_mesh();
+				let wireframe = match self.wireframes.get_mut(&mesh.get_id()) {
+					Some((source, version, wireframe)) if *version == mesh.get_version() => {
+						// Editing a mesh can move it to a new Rc, so follow it
+						*source = Rc::downgrade(mesh);
+
+						wireframe.clone()
+					},
+					_ => {
+						let wireframe = Rc::new(mesh.to_wireframe());
+						let entry = (Rc::downgrade(mesh), mesh.get_version(), wireframe.clone());
+						self.wireframes.insert(mesh.get_id(), entry);
+
+						wireframe
+					}
+				};
+
+				let gpu_mesh = self.meshes.prepare(gl, &wireframe);
+				object.render_wireframe(gl, shader, gpu_mesh);
+			} else {
+				let gpu_mesh = self.meshes.prepare(gl, object.get_mesh());
+				object.render(gl, shader, gpu_mesh);
+			}
+		}
+	}
+
+	/// Draws one fullscreen pass
+	///
+	/// * `shader_name` - the shader to draw with
+	/// * `material` - parameters and textures for the shader
+	/// * `source` - the render target the pass reads from
+	/// * `destination` - the render target to draw into, or None for the canvas
+	fn draw_pass(&mut self, shader_name: &'static str, material: &Material, source: &str, destination: Option<&str>) {
+		// Bind where it's drawing to
+		match destination.and_then(|name| self.render_targets.get(name)) {
+			Some(target) => target.bind(&mut self.backend),
+			None => self.bind_canvas()
+		}
+
+		// Feed it what was drawn before
+		let (width, height) = self.render_targets.get(source)
+			.map(|target| target.get_size())
+			.unwrap_or((1, 1));
+		let mut material = material.clone();
+		material.set_texture("source", source);
+		material.set_uniform("texel_size", UniformValue::Vec2([1.0 / width as f32, 1.0 / height as f32]));
+
+		// Find the shader
+		let gl = &mut self.backend;
+		if self.shaders.prepare(gl, shader_name, material.get_defines()).is_err() {
+			return;
+		}
+		let shader = match self.shaders.get(shader_name, material.get_defines()) {
+			Some(shader) => shader,
+			None => return
+		};
+
+		// Draw over everything
+		gl.set_render_state(&RenderState::fullscreen());
+		gl.use_program(shader.program);
+		let textures = TextureLookup {
+			drawing_to: destination,
+			render_targets: &self.render_targets,
+			textures: &self.textures
+		};
+		apply_material(gl, shader, &material, &textures, &mut self.uniform_errors);
+
+		let triangle = self.post_process.get_triangle();
+		let gpu_mesh = self.meshes.prepare(gl, triangle.get_mesh());
+		triangle.render(gl, shader, gpu_mesh);
+	}
+
+	/// Runs every enabled post-processing effect over the scene
+	///
+	/// Effects take turns drawing into the ping pong targets, and the last one
+	/// draws to the canvas.
+	fn draw_post_process(&mut self) {
+		let effects: Vec<_> = self.post_process.get_effects()
+			.iter()
+			.filter(|effect| effect.is_enabled())
+			.cloned()
+			.collect();
+
+		let mut source = SCENE_TARGET;
+		for (index, effect) in effects.iter().enumerate() {
+			let destination = if index + 1 == effects.len() {
+				None
+			} else {
+				Some(PING_PONG_TARGETS[index % 2])
+			};
+			let material = effect.get_material();
+
+			match effect.get_kind() {
+				EffectKind::Bloom => {
+					// Pick out the bright parts at half size
+					self.draw_pass("post bloom extract", material, source, Some(BLOOM_TARGETS[0]));
+
+					// Blur them across, then down
+					let mut blur = Material::new("post blur");
+					blur.set_uniform("direction", UniformValue::Vec2([1.0, 0.0]));
+					self.draw_pass("post blur", &blur, BLOOM_TARGETS[0], Some(BLOOM_TARGETS[1]));
+					blur.set_uniform("direction", UniformValue::Vec2([0.0, 1.0]));
+					self.draw_pass("post blur", &blur, BLOOM_TARGETS[1], Some(BLOOM_TARGETS[0]));
+
+					// Add them back on top
+					let mut composite = material.clone();
+					composite.set_texture("bloom", BLOOM_TARGETS[0]);
+					self.draw_pass("post bloom", &composite, source, destination);
+				},
+				kind => self.draw_pass(kind.get_shader_name(), material, source, destination)
+			}
+
+			if let Some(destination) = destination {
+				source = destination;
+			}
+		}
+	}
+
+	/// Draws the shadow maps for this frame
+	///
+	/// Lights whose map can't be drawn go without shadows. Maps get smaller
+	/// than the settings ask for if the atlases wouldn't fit in a texture.
+	///
+	/// * `world` - the world to draw shadows for
+	///
+	/// Returns - the shadows lit shaders should look up
+	fn draw_shadows(&mut self, world: &World) -> ShadowFrame {
+		let settings = self.shadow_settings.fit_to(self.backend.capabilities().max_texture_size);
+		let mut frame = plan_shadows(world.get_lights(), &self.camera, &settings);
+
+		if !frame.cascade_matrices.is_empty() {
+			frame.cascade_map = self.draw_shadow_map(world, CASCADE_TARGET, frame.map_size, SHADOW_CASCADES, &frame.cascade_matrices);
+		}
+
+		if !frame.spot_lights.is_empty() {
+			let matrices: Vec<Matrix4<f32>> = frame.spot_lights.iter().map(|(_, matrix)| *matrix).collect();
+			frame.spot_map = self.draw_shadow_map(world, SPOT_TARGET, frame.map_size, MAX_SPOT_SHADOWS, &matrices);
+		}
+
+		frame
+	}
+
+	/// Draws the background behind everything drawn so far, if it takes more
+	/// than a clear
+	///
+	/// * `view_matrix` - the view matrix of the camera drawing it
+	/// * `projection_matrix` - the projection matrix of the camera drawing it
+	/// * `target` - the name of the render target being drawn into, if any
+	fn draw_sky(&mut self, view_matrix: &Matrix4<f32>, projection_matrix: &Matrix4<f32>, target: Option<&str>) {
+		let mut material = match self.background.get_material() {
+			Some(material) => material,
+			None => return
+		};
+		material.set_uniform("sky_matrix", UniformValue::from(&sky_matrix(view_matrix, projection_matrix)));
+
+		// Find the shader
+		let shader_name = material.get_shader_name();
+		if self.shaders.prepare(&mut self.backend, shader_name, material.get_defines()).is_err() {
+			return;
+		}
+		let shader = match self.shaders.get(shader_name, material.get_defines()) {
+			Some(shader) => shader,
+			None => return
+		};
+
+		let gl = &mut self.backend;
+		gl.set_render_state(&RenderState::sky());
+		gl.use_program(shader.program);
+		let textures = TextureLookup {
+			drawing_to: target,
+			render_targets: &self.render_targets,
+			textures: &self.textures
+		};
+		apply_material(gl, shader, &material, &textures, &mut self.uniform_errors);
+
+		// The screen covering triangle gets pushed back onto the far plane
+		let triangle = self.post_process.get_triangle();
+		let gpu_mesh = self.meshes.prepare(gl, triangle.get_mesh());
+		triangle.render(gl, shader, gpu_mesh);
+	}
 
-			// Only render if it can find the shader
-			match shader {
-				Some(shader) => {
-					// If it found the shader
+	/// Draws the depth of everything that casts shadows into a shadow map
+	///
+	/// Each shadow gets its own tile, side by side along the map.
+	///
+	/// * `world` - the world to draw shadows for
+	/// * `name` - the render target to draw into
+	/// * `size` - width and height in pixels of each tile
+	/// * `tiles` - how many shadows the map has room for
+	/// * `matrices` - world to shadow map space for each shadow
+	///
+	/// Returns - the map, or None if it couldn't be drawn
+	fn draw_shadow_map(
+		&mut self,
+		world: &World,
+		name: &str,
+		size: u32,
+		tiles: usize,
+		matrices: &[Matrix4<f32>]
+	) -> Option<TextureHandle> {
+		// Make the map, or remake it if the settings changed size
+		// Depth is packed into color, since depth textures need an extension
+		let options = RenderTargetOptions {
+			depth: DepthAttachment::Renderbuffer,
+			filter: FilterMode::Nearest,
+			size: TargetSize::Fixed { width: size * tiles as u32, height: size }
+		};
+		let current = self.render_targets.get(name).map(|target| *target.get_options());
+		if current != Some(options) && self.create_render_target(name, &options).is_err() {
+			return None;
+		}
+
+		// Find the shaders, drawing one at a time if instancing isn't there
+		let material = Material::new(SHADOW_SHADER);
+		if self.shaders.prepare(&mut self.backend, SHADOW_SHADER, material.get_defines()).is_err() {
+			return None;
+		}
+		let instanced_defines = self.prepare_instanced(&material);
+		let shader = self.shaders.get(SHADOW_SHADER, material.get_defines())?;
+		let instanced_shader = instanced_defines.and_then(|defines| self.shaders.get(SHADOW_SHADER, &defines));
+
+		// Casters with the same mesh and culling get drawn together, and ones
+		// that blend don't block the light
+		let pass_state = self.render_state;
+		let material_state = |object: &Object| *object.get_material().get_render_state().unwrap_or(&pass_state);
+		let casters = world.get_objects().iter()
+			.filter(|object| object.casts_shadows() && !material_state(object).blend_mode.is_transparent());
+		let groups = group_instances(casters, |object| {
+			let state = material_state(object);
+			(object.get_mesh().get_id(), state.cull_mode, state.front_face)
+		});
+
+		// Anything nothing gets drawn over is as far away as it gets
+		let gl = &mut self.backend;
+		let target = &self.render_targets[name];
+		target.bind(gl);
+		gl.clear_color(1.0, 1.0, 1.0, 1.0);
+		gl.clear(true, true);
+
+		for (tile, matrix) in matrices.iter().enumerate() {
+			gl.viewport(tile as u32 * size, 0, size, size);
+
+			// The light's matrix does the work of both view and projection
+			for shader in std::iter::once(shader).chain(instanced_shader) {
+				gl.use_program(shader.program);
+				gl::set_mat4_uniform(gl, &shader.view_uniform, &Matrix4::identity());
+				gl::set_mat4_uniform(gl, &shader.projection_uniform, matrix);
+			}
+
+			for group in &groups {
+				let object = group[0];
+				let gpu_mesh = self.meshes.prepare(gl, object.get_mesh());
+
+				// Faces the material shows cast shadows, whatever else it does
+				let state = material_state(object);
+				gl.set_render_state(&RenderState {
+					cull_mode: state.cull_mode,
+					front_face: state.front_face,
+					..RenderState::opaque()
+				});
+
+				match instanced_shader.filter(|_| group.len() > 1) {
+					Some(instanced_shader) => {
+						gl.use_program(instanced_shader.program);
+						if self.instances.bind(gl, group) {
+							object.render_instances(gl, instanced_shader, gpu_mesh, group.len() as i32);
+						}
+						self.instances.unbind(gl);
+					},
+					None => {
+						gl.use_program(shader.program);
+						for object in group {
+							object.render(gl, shader, gpu_mesh);
+						}
+					}
+				}
+			}
+		}
+
+		target.get_color().handle
+	}
+
+	/// Clears whatever is bound and draws every object in the world to it
+	///
+	/// Objects the camera can't see are skipped before anything is drawn.
+	/// Solid objects go first, then the background, then objects that blend
+	/// with what's behind them, furthest first.
+	///
+	/// * `world` - the world to draw
+	/// * `view_matrix` - the view matrix of the camera drawing it
+	/// * `projection_matrix` - the projection matrix of the camera drawing it
+	/// * `camera_position` - where the camera drawing it is
+	/// * `target` - the name of the render target being drawn into, if any
+	/// * `shadows` - the shadows drawn this frame
+	fn draw_world(
+		&mut self,
+		world: &World,
+		view_matrix: &Matrix4<f32>,
+		projection_matrix: &Matrix4<f32>,
+		camera_position: &Point3<f32>,
+		target: Option<&str>,
+		shadows: &ShadowFrame
+	) {
+		// Clear the screen for rendering
+		let [red, green, blue] = self.background.get_clear_color();
+		self.backend.clear_color(red, green, blue, 1.0);
+		self.backend.clear(true, true);
+
+		// Skip what the camera can't see, checking the sphere first since it's
+		// quicker
+		let frustum = Frustum::from_matrix(&(projection_matrix * view_matrix));
+		let visible: Vec<&Object> = world.get_objects()
+			.iter()
+			.filter(|object| frustum.intersects_sphere(&object.get_world_sphere()))
+			.filter(|object| frustum.intersects_aabb(&object.get_world_bounds()))
+			.collect();
+		self.cull_stats.visible += visible.len() as u32;
+		self.cull_stats.culled += (world.get_objects().len() - visible.len()) as u32;
+
+		// Views for finding bugs draw everything their own way
+		if self.render_mode != RenderMode::Shaded {
+			self.draw_debug_view(&visible, view_matrix, projection_matrix);
+			return;
+		}
+
+		// Things that can be seen through get drawn one at a time, since they
+		// have to go back to front
+		let pass_state = self.render_state;
+		let (transparent, opaque): (Vec<&Object>, Vec<&Object>) = visible.into_iter().partition(|object| {
+			object.get_material().get_render_state().unwrap_or(&pass_state).blend_mode.is_transparent()
+		});
+
+		// Objects that share a mesh, a material, and lights get drawn together
+		let lights_of = |object: &Object| nearest_lights(world.get_lights(), object.get_position(), MAX_LIGHTS);
+		let groups = group_instances(opaque, |object| {
+			let lights: Vec<*const Light> = lights_of(object).into_iter().map(|light| light as *const Light).collect();
+			(object.get_mesh().get_id(), Rc::as_ptr(object.get_material()), object.receives_shadows(), lights)
+		});
+
+		// Queue every group that has a shader, building the instanced shaders
+		// for groups that need them
+		let mut queue = RenderQueue::new();
+		for group in groups {
+			let instanced = if group.len() > 1 {
+				self.prepare_instanced(group[0].get_material())
+			} else {
+				None
+			};
+
+			let material = group[0].get_material();
+			let defines = instanced.as_ref().unwrap_or_else(|| material.get_defines());
+			let shader = match self.shaders.get(material.get_shader_name(), defines) {
+				Some(shader) => shader,
+				None => continue
+			};
+
+			// Nearest first, so less gets drawn over
+			let distance = group.iter()
+				.map(|object| (object.get_position() - camera_position.coords).norm())
+				.fold(f32::INFINITY, f32::min);
+			let key = SortKey {
+				pass: RenderPass::Opaque,
+				shader: shader.program.map_or(0, |program| program.0),
+				material: Rc::as_ptr(material) as usize,
+				mesh: group[0].get_mesh().get_id(),
+				depth: depth_key(distance)
+			};
+			queue.push(DrawItem { key, objects: group, instanced });
+		}
+		for object in transparent {
+			let material = object.get_material();
+			if self.shaders.get(material.get_shader_name(), material.get_defines()).is_none() {
+				continue;
+			}
+
+			// Furthest first, so what's behind is there to show through
+			let depth = -view_matrix.transform_point(&Point3::from(*object.get_position())).z;
+			queue.push(DrawItem { key: SortKey::transparent(depth), objects: vec![object], instanced: None });
+		}
+		queue.sort();
+
+		// Solid things first, then the sky behind them, then what can be seen
+		// through over both
+		for pass in [RenderPass::Opaque, RenderPass::Transparent] {
+			if pass == RenderPass::Transparent {
+				self.draw_sky(view_matrix, projection_matrix, target);
+			}
 
-					// Prepare to render
-					gl.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, self.index_buffer.as_ref());
+			// Set gl to the backend for easier use
+			let gl = &mut self.backend;
+
+			// A target can't be sampled while it's being drawn into
+			let textures = TextureLookup {
+				drawing_to: target,
+				render_targets: &self.render_targets,
+				textures: &self.textures
+			};
+
+			// Render item by item
+			for item in queue.pass_items(pass) {
+				// Get shader to use
+				let group = &item.objects;
+				let instanced = &item.instanced;
+				let object = group[0];
+				let material = object.get_material();
+				let defines = instanced.as_ref().unwrap_or_else(|| material.get_defines());
+				let shader = self.shaders.get(material.get_shader_name(), defines);
+
+				// Only render if it can find the shader
+				if let Some(shader) = shader {
+					// Make sure the mesh is on the GPU
+					// Every object in the group shares it
+					let gpu_mesh = self.meshes.prepare(gl, object.get_mesh());
+
+					// Set depth and culling
+					// What can be seen through never hides what's behind it
+					let mut render_state = *material.get_render_state().unwrap_or(&self.render_state);
+					if pass == RenderPass::Transparent {
+						render_state.depth_write = false;
+					}
+					gl.set_render_state(&render_state);
 
 					// Set shader
-					gl.use_program(shader.program.as_ref());
+					gl.use_program(shader.program);
 
 					// Set view matrix
-					let view_matrix = self.camera.get_view_matrix().as_slice();
-					gl::set_mat4_uniform(&gl, &shader.view_uniform, view_matrix);
+					gl::set_mat4_uniform(gl, &shader.view_uniform, view_matrix);
 
 					// Set projection matrix
-					let projection_matrix = self.projection_matrix.as_slice();
-					gl::set_mat4_uniform(&gl, &shader.projection_uniform, projection_matrix);
+					gl::set_mat4_uniform(gl, &shader.projection_uniform, projection_matrix);
 
-					// Render
-					object.render(gl, shader);
-				},
-				_ => ()
+					// Set material values
+					let units = apply_material(gl, shader, material, &textures, &mut self.uniform_errors);
+
+					// Set the lights closest to the objects
+					let lights = lights_of(object);
+					set_light_uniforms(gl, shader, &lights, &camera_position.coords, &mut self.uniform_errors);
+
+					// Set their shadows, on the units after the material's
+					let receive = object.receives_shadows();
+					set_shadow_uniforms(gl, shader, shadows, &lights, receive, units, &mut self.uniform_errors);
+
+					// Render, all at once if it can
+					if instanced.is_some() {
+						if self.instances.bind(gl, group) {
+							object.render_instances(gl, shader, gpu_mesh, group.len() as i32);
+						}
+						self.instances.unbind(gl);
+					} else {
+						for object in group {
+							object.render(gl, shader, gpu_mesh);
+						}
+					}
+				}
+			}
+		}
+	}
+
+	/// Builds the permutation of a material's shader that draws instances
+	///
+	/// * `material` - the material a group of objects is drawn with
+	///
+	/// Returns - the defines of the permutation, or None if the objects have
+	/// to be drawn one at a time
+	fn prepare_instanced(&mut self, material: &Material) -> Option<Defines> {
+		if !can_instance(self.backend.capabilities()) {
+			return None;
+		}
+
+		// Shaders that ignore INSTANCED would draw every instance in one spot
+		let shader_name = material.get_shader_name();
+		if !self.shaders.get(shader_name, material.get_defines())?.instancing {
+			return None;
+		}
+
+		let mut defines = material.get_defines().clone();
+		defines.insert(String::from(INSTANCED_DEFINE), String::from("1"));
+		self.shaders.prepare(&mut self.backend, shader_name, &defines).ok()?;
+
+		Some(defines)
+	}
+
+	/// Makes sure the render targets post-processing needs are there
+	///
+	/// Returns - whether they all are, and post-processing can go ahead
+	fn prepare_post_targets(&mut self) -> bool {
+		let bloom = self.post_process.get_effects()
+			.iter()
+			.any(|effect| effect.is_enabled() && effect.get_kind() == EffectKind::Bloom);
+
+		// The scene needs depth, the passes after it don't
+		let full_size = TargetSize::Canvas { scale: 1.0 };
+		let half_size = TargetSize::Canvas { scale: 0.5 };
+		let mut needed = vec![(SCENE_TARGET, DepthAttachment::Renderbuffer, full_size)];
+		needed.extend(PING_PONG_TARGETS.iter().map(|&name| (name, DepthAttachment::None, full_size)));
+		if bloom {
+			needed.extend(BLOOM_TARGETS.iter().map(|&name| (name, DepthAttachment::None, half_size)));
+		}
+
+		for (name, depth, size) in needed {
+			if self.render_targets.contains_key(name) {
+				continue;
+			}
+
+			let options = RenderTargetOptions {
+				depth,
+				size,
+				..RenderTargetOptions::default()
+			};
+			if self.create_render_target(name, &options).is_err() {
+				return false;
+			}
+		}
+
+		true
+	}
+}
+
+/// Sets a material's uniforms on the shader it renders with
+///
+/// Uniforms the material leaves alone go back to the shader's defaults, so
+/// nothing leaks over from the last object drawn with the same program.
+/// Samplers get a texture unit each, with their texture bound to it.
+///
+/// * `gl` - the backend to render with
+/// * `shader` - the shader the material renders with, already in use
+/// * `material` - the material to apply
+/// * `textures` - finds textures by name
+/// * `errors` - where to put any problems with the material's values
+///
+/// Returns - how many texture units the material's samplers took
+fn apply_material<B: RenderBackend>(
+	gl: &mut B,
+	shader: &CompiledShader,
+	material: &Material,
+	textures: &TextureLookup,
+	errors: &mut Vec<UniformError>
+) -> u32 {
+	// Material values
+	for (name, value) in material.get_uniforms() {
+		if let Err(error) = shader.set_uniform(gl, name, value) {
+			errors.push(error);
+		}
+	}
+
+	// Defaults for everything else
+	for (name, value) in &shader.uniform_defaults {
+		if material.get_uniform(name).is_none() {
+			if let Err(error) = shader.set_uniform(gl, name, value) {
+				errors.push(error);
 			}
 		}
 	}
+
+	// Give each sampler its own texture unit
+	let mut samplers: Vec<(&String, &String)> = material.get_textures().iter().collect();
+	samplers.sort();
+	let units = samplers.len() as u32;
+	for (unit, (sampler, texture_name)) in samplers.into_iter().enumerate() {
+		let (target, value) = match shader.uniforms.get(sampler.as_str()) {
+			Some(Uniform { u_type: UniformType::SamplerCube, .. }) => (TextureTarget::CubeMap, UniformValue::SamplerCube(unit as i32)),
+			_ => (TextureTarget::Texture2D, UniformValue::Sampler2D(unit as i32))
+		};
+
+		// Missing textures leave the unit empty, which samples as black
+		let texture = textures.find(texture_name, target);
+		gl.bind_texture(unit as u32, target, texture);
+
+		if let Err(error) = shader.set_uniform(gl, sampler, &value) {
+			errors.push(error);
+		}
+	}
+
+	units
+}
+
+/// Finds the textures materials sample, by name
+struct TextureLookup<'a> {
+	/// The name of the render target being drawn into, if any
+	drawing_to: Option<&'a str>,
+	/// Render targets, sampled by their name or their name with .depth on the end
+	render_targets: &'a HashMap<String, RenderTarget>,
+	/// Uploaded textures
+	textures: &'a HashMap<String, Texture>
+}
+
+impl TextureLookup<'_> {
+	/// Finds a texture by name
+	///
+	/// Uploaded textures win over render targets with the same name. Render
+	/// targets are never cube maps.
+	///
+	/// * `name` - the name of the texture
+	/// * `target` - the kind of texture the sampler takes
+	///
+	/// Returns - None if there's no such texture of that kind, or it's being drawn into
+	fn find(&self, name: &str, target: TextureTarget) -> Option<TextureHandle> {
+		if let Some(texture) = self.textures.get(name) {
+			return texture.handle.filter(|_| texture.target == target);
+		}
+		if target != TextureTarget::Texture2D {
+			return None;
+		}
+
+		// Look through the render targets
+		let (target_name, depth) = match name.strip_suffix(".depth") {
+			Some(target_name) => (target_name, true),
+			None => (name, false)
+		};
+		if self.drawing_to == Some(target_name) {
+			return None;
+		}
+		let target = self.render_targets.get(target_name)?;
+
+		if depth {
+			target.get_depth()?.handle
+		} else {
+			target.get_color().handle
+		}
+	}
+}
+
+impl Graphics<WebGlBackend> {
+	/// Initialize graphics on the page's canvas
+	pub fn init() -> Graphics<WebGlBackend> {
+		// Set up the front end
+		let canvas = gl::set_up_canvas();
+
+		Self::new(WebGlBackend::new(&canvas))
+	}
 }
 
-impl Drop for Graphics {
+impl<B: RenderBackend> Drop for Graphics<B> {
 	/// Destructor
 	fn drop(&mut self) {
-		// Free buffers from webGL memory
-		self.context.delete_buffer(self.array_buffer.as_ref());
-		self.context.delete_buffer(self.index_buffer.as_ref());
+		// Free buffers from GPU memory
+		self.meshes.clear(&mut self.backend);
+		self.instances.delete(&mut self.backend);
+		self.debug_lines.delete(&mut self.backend);
+
+		// Free render targets
+		for target in self.render_targets.values() {
+			target.delete(&mut self.backend);
+		}
+
+		// Free textures
+		for texture in self.textures.values() {
+			texture.delete(&mut self.backend);
+		}
+
+		// Free shader programs
+		self.shaders.clear(&mut self.backend);
 	}
 }
@@ -0,0 +1,234 @@
+use std::{fmt, rc::Rc};
+
+use nalgebra::Vector3;
+
+use crate::logic::object::Object;
+use super::{
+	material::Material,
+	mesh::Indices,
+	shaders::{UniformError, UniformValue}
+};
+
+/// The render target the scene gets drawn into before post-processing
+pub const SCENE_TARGET: &str = "post scene";
+
+/// Render targets effects take turns drawing into
+pub const PING_PONG_TARGETS: [&str; 2] = ["post ping", "post pong"];
+
+/// Half size render targets bloom gets blurred in
+pub const BLOOM_TARGETS: [&str; 2] = ["post bloom a", "post bloom b"];
+
+/// A built in post-processing effect
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EffectKind {
+	/// Adds a glow around bright parts of the image
+	///
+	/// Parameters are intensity, knee, and threshold.
+	Bloom,
+	/// Remaps colors through a lookup table texture bound to lut
+	///
+	/// Parameters are intensity and lut_size.
+	ColorGrading,
+	/// Smooths jagged edges
+	///
+	/// Parameters are reduce_min, reduce_multiplier, and span_max.
+	Fxaa,
+	/// Converts linear color for display
+	///
+	/// The parameter is gamma.
+	Gamma,
+	/// Brings bright colors back into range
+	///
+	/// Parameters are curve, 0 for Reinhard or 1 for ACES, and exposure.
+	Tonemap,
+	/// Darkens the edges of the screen
+	///
+	/// Parameters are intensity, radius, and softness.
+	Vignette
+}
+
+impl EffectKind {
+	/// Finds an effect by the name JS uses for it
+	///
+	/// * `name` - one of bloom, color_grading, fxaa, gamma, tonemap, or vignette
+	pub fn from_name(name: &str) -> Option<EffectKind> {
+		match name {
+			"bloom" => Some(EffectKind::Bloom),
+			"color_grading" => Some(EffectKind::ColorGrading),
+			"fxaa" => Some(EffectKind::Fxaa),
+			"gamma" => Some(EffectKind::Gamma),
+			"tonemap" => Some(EffectKind::Tonemap),
+			"vignette" => Some(EffectKind::Vignette),
+			_ => None
+		}
+	}
+
+	/// Gets the name of the shader that draws this effect
+	///
+	/// Bloom takes a few passes, and this is the last one.
+	pub fn get_shader_name(&self) -> &'static str {
+		match self {
+			EffectKind::Bloom => "post bloom",
+			EffectKind::ColorGrading => "post color grading",
+			EffectKind::Fxaa => "post fxaa",
+			EffectKind::Gamma => "post gamma",
+			EffectKind::Tonemap => "post tonemap",
+			EffectKind::Vignette => "post vignette"
+		}
+	}
+}
+
+/// One fullscreen pass in the post-processing stack
+#[derive(Clone, Debug, PartialEq)]
+pub struct Effect {
+	/// Whether the effect gets drawn
+	enabled: bool,
+	/// Which effect this is
+	kind: EffectKind,
+	/// Parameters and textures for the effect's shader
+	material: Material
+}
+
+impl Effect {
+	/// Get which effect this is
+	pub fn get_kind(&self) -> EffectKind {
+		self.kind
+	}
+
+	/// Get the parameters and textures the effect's shader is fed
+	pub fn get_material(&self) -> &Material {
+		&self.material
+	}
+
+	/// Whether the effect gets drawn
+	pub fn is_enabled(&self) -> bool {
+		self.enabled
+	}
+
+	/// Creates an enabled effect with its default parameters
+	///
+	/// * `kind` - which effect to create
+	pub fn new(kind: EffectKind) -> Effect {
+		Effect {
+			enabled: true,
+			kind,
+			material: Material::new(kind.get_shader_name())
+		}
+	}
+
+	/// Turns the effect on or off
+	pub fn set_enabled(&mut self, enabled: bool) {
+		self.enabled = enabled;
+	}
+
+	/// Sets a parameter of the effect
+	///
+	/// The value is checked against the shader when it gets set through
+	/// Graphics, or when it gets rendered.
+	///
+	/// * `name` - the name of the parameter
+	/// * `value` - the value to set
+	pub fn set_param(&mut self, name: &str, value: UniformValue) {
+		self.material.set_uniform(name, value);
+	}
+
+	/// Sets the texture a sampler of the effect reads from, like lut
+	///
+	/// * `sampler` - the name of the sampler uniform
+	/// * `texture_name` - the name of the texture to sample
+	pub fn set_texture(&mut self, sampler: &str, texture_name: &str) {
+		self.material.set_texture(sampler, texture_name);
+	}
+}
+
+/// Something that went wrong changing the post-processing stack
+#[derive(Clone, Debug, PartialEq)]
+pub enum PostProcessError {
+	/// There's no effect at this position in the stack
+	NoEffect(usize),
+	/// There's no built in effect with this name
+	UnknownKind(String),
+	/// A parameter doesn't fit the effect's shader
+	Param(UniformError)
+}
+
+impl fmt::Display for PostProcessError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			PostProcessError::NoEffect(index) => write!(f, "No post-processing effect at {}", index),
+			PostProcessError::UnknownKind(name) => write!(f, "No post-processing effect named {}", name),
+			PostProcessError::Param(error) => write!(f, "{}", error)
+		}
+	}
+}
+
+impl std::error::Error for PostProcessError {}
+
+/// An ordered list of fullscreen effects applied to the rendered scene
+///
+/// When any effect is enabled the scene gets rendered into a target first,
+/// then each effect reads the result of the one before it, and the last one
+/// draws to the canvas.
+pub struct PostProcess {
+	/// Effects in the order they get applied
+	effects: Vec<Effect>,
+	/// A triangle that covers the whole screen, drawn once for each pass
+	triangle: Object
+}
+
+impl PostProcess {
+	/// Adds an effect to the end of the stack
+	///
+	/// Returns - the position of the effect in the stack
+	pub fn add_effect(&mut self, effect: Effect) -> usize {
+		self.effects.push(effect);
+
+		self.effects.len() - 1
+	}
+
+	/// Get the effects in the order they get applied
+	pub fn get_effects(&self) -> &[Effect] {
+		&self.effects
+	}
+
+	/// Get the effects in the order they get applied, mutably
+	///
+	/// Effects can be reordered or removed through this.
+	pub fn get_effects_mut(&mut self) -> &mut Vec<Effect> {
+		&mut self.effects
+	}
+
+	/// Get the triangle that covers the screen
+	pub fn get_triangle(&self) -> &Object {
+		&self.triangle
+	}
+
+	/// Whether any effect is enabled, so the scene needs rendering offscreen
+	pub fn is_active(&self) -> bool {
+		self.effects.iter().any(|effect| effect.enabled)
+	}
+
+	/// Creates an empty stack
+	pub fn new() -> PostProcess {
+		// Corners at (-1, -1), (3, -1) and (-1, 3) put the screen inside the
+		// triangle, without the seam two triangles would have down the middle
+		let vertices = vec![
+			-1.0, -1.0, 0.0,
+			3.0, -1.0, 0.0,
+			-1.0, 3.0, 0.0
+		];
+		let material = Rc::new(Material::new("post gamma"));
+		let triangle = Object::new(Vector3::zeros(), 0.0, 0.0, 0.0, 1.0, material, Indices::U8(vec![0, 1, 2]), vertices);
+
+		PostProcess {
+			effects: Vec::new(),
+			triangle
+		}
+	}
+}
+
+impl Default for PostProcess {
+	fn default() -> PostProcess {
+		Self::new()
+	}
+}
@@ -0,0 +1,149 @@
+use nalgebra::Matrix4;
+
+use crate::logic::object::Object;
+use super::{
+	material::Material,
+	render_state::{BlendMode, CullMode, RenderState},
+	shaders::UniformValue
+};
+
+/// How much each layer of the overdraw view adds
+const OVERDRAW_STEP: [f32; 4] = [0.1, 0.04, 0.02, 1.0];
+
+/// What the main pass draws, either the lit scene or a view for finding bugs
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum RenderMode {
+	/// Every object with its own material
+	#[default]
+	Shaded,
+	/// The edges of every triangle, with nothing culled
+	Wireframe,
+	/// World space normals as colors, x as red, y as green, and z as blue
+	Normals,
+	/// A checkerboard laid out by the first texture coordinates
+	UvChecker,
+	/// Distance from the camera, white up close fading to black at the far
+	/// plane
+	Depth,
+	/// Every triangle added on top of each other, brighter where more are
+	/// drawn over the same pixel
+	Overdraw,
+	/// Every object in a flat color of its own
+	RandomColor
+}
+
+impl RenderMode {
+	/// Finds a render mode by the name JS uses for it
+	///
+	/// * `name` - shaded, wireframe, normals, uv_checker, depth, overdraw, or random_color
+	pub fn from_name(name: &str) -> Option<RenderMode> {
+		match name {
+			"shaded" => Some(RenderMode::Shaded),
+			"wireframe" => Some(RenderMode::Wireframe),
+			"normals" => Some(RenderMode::Normals),
+			"uv_checker" => Some(RenderMode::UvChecker),
+			"depth" => Some(RenderMode::Depth),
+			"overdraw" => Some(RenderMode::Overdraw),
+			"random_color" => Some(RenderMode::RandomColor),
+			_ => None
+		}
+	}
+
+	/// Gets the material to draw an object with in this mode
+	///
+	/// * `object` - the object being drawn
+	///
+	/// Returns - None in shaded mode, where objects use their own
+	pub fn get_material(&self, object: &Object) -> Option<Material> {
+		let mut material = Material::new(self.get_shader_name()?);
+		match self {
+			RenderMode::Wireframe => material.set_uniform("color", UniformValue::Vec4([1.0, 1.0, 1.0, 1.0])),
+			RenderMode::Overdraw => material.set_uniform("color", UniformValue::Vec4(OVERDRAW_STEP)),
+			RenderMode::RandomColor => material.set_uniform("color", UniformValue::Vec4(random_color(object))),
+			_ => ()
+		}
+
+		Some(material)
+	}
+
+	/// Gets the state to draw an object with in this mode
+	///
+	/// * `material_state` - the state the object's material would draw with
+	pub fn get_render_state(&self, material_state: &RenderState) -> RenderState {
+		match self {
+			// Lines have no faces to cull
+			RenderMode::Wireframe => RenderState {
+				cull_mode: CullMode::None,
+				..*material_state
+			},
+			// Everything counts, even what's hidden behind something else
+			RenderMode::Overdraw => RenderState {
+				blend_mode: BlendMode::Additive,
+				cull_mode: CullMode::None,
+				depth_test: false,
+				depth_write: false,
+				..*material_state
+			},
+			_ => *material_state
+		}
+	}
+
+	/// Gets the name of the shader every object is drawn with in this mode
+	///
+	/// Returns - None in shaded mode, where objects use their own
+	pub fn get_shader_name(&self) -> Option<&'static str> {
+		match self {
+			RenderMode::Shaded => None,
+			RenderMode::Wireframe => Some("3d orange"),
+			RenderMode::Normals => Some("debug normals"),
+			RenderMode::UvChecker => Some("debug uv checker"),
+			RenderMode::Depth => Some("debug depth"),
+			RenderMode::Overdraw => Some("3d orange"),
+			RenderMode::RandomColor => Some("3d orange")
+		}
+	}
+}
+
+/// Finds the near and far clipping planes a projection matrix was made with
+///
+/// * `projection` - a perspective or orthographic projection matrix
+///
+/// Returns - distance to the near plane, then the far plane
+pub fn clip_planes_of(projection: &Matrix4<f32>) -> (f32, f32) {
+	let (scale, offset) = (projection[(2, 2)], projection[(2, 3)]);
+
+	// Perspective matrices copy -z into w, orthographic ones leave w alone
+	if projection[(3, 2)] != 0.0 {
+		(offset / (scale - 1.0), offset / (scale + 1.0))
+	} else {
+		((offset + 1.0) / scale, (offset - 1.0) / scale)
+	}
+}
+
+/// Picks a bright color for an object that stays the same every frame
+///
+/// The color comes from where the object is kept, so it only changes if the
+/// world's objects get moved around.
+///
+/// * `object` - the object to color
+fn random_color(object: &Object) -> [f32; 4] {
+	// Scramble the address so objects next to each other look different
+	let mut hash = object as *const Object as u64;
+	hash ^= hash >> 33;
+	hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+	hash ^= hash >> 33;
+
+	// Take a hue from it, at full saturation
+	let hue = (hash % 360) as f32 / 60.0;
+	let x = 1.0 - (hue % 2.0 - 1.0).abs();
+	let [red, green, blue] = match hue as u32 {
+		0 => [1.0, x, 0.0],
+		1 => [x, 1.0, 0.0],
+		2 => [0.0, 1.0, x],
+		3 => [0.0, x, 1.0],
+		4 => [x, 0.0, 1.0],
+		_ => [1.0, 0.0, x]
+	};
+
+	[red, green, blue, 1.0]
+}
@@ -0,0 +1,136 @@
+use crate::logic::object::Object;
+use super::shaders::preprocessor::Defines;
+
+/// A stage of drawing a view, drawn in the order listed
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum RenderPass {
+	/// Things nothing can be seen through
+	Opaque,
+	/// Things that blend with what's behind them, drawn back to front
+	Transparent
+}
+
+/// What draw items get sorted by, most important first
+///
+/// Sorting by shader, then material, then mesh keeps the items that share
+/// state next to each other, so the state cache can skip setting it again.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct SortKey {
+	/// The pass the item gets drawn in
+	pub pass: RenderPass,
+	/// The program the item gets drawn with
+	pub shader: u32,
+	/// Identifies the material the item gets drawn with
+	pub material: usize,
+	/// The id of the mesh the item draws
+	pub mesh: u32,
+	/// How far the item is from the camera, from depth_key
+	pub depth: u32
+}
+
+impl SortKey {
+	/// Makes the key for something that can be seen through
+	///
+	/// What's behind has to be drawn first for it to show through, so these
+	/// only sort furthest first, and leave the other fields at 0.
+	///
+	/// * `depth` - how far in front of the camera it is, in view space
+	pub fn transparent(depth: f32) -> SortKey {
+		SortKey {
+			pass: RenderPass::Transparent,
+			shader: 0,
+			material: 0,
+			mesh: 0,
+			depth: u32::MAX - depth_key(depth)
+		}
+	}
+}
+
+/// Turns a distance into something that sorts the same way
+///
+/// Positive floats sort the same as their bits, so this is exact.
+///
+/// * `distance` - how far something is from the camera
+pub fn depth_key(distance: f32) -> u32 {
+	// Negatives and NaN would sort after everything
+	if distance > 0.0 {
+		distance.to_bits()
+	} else {
+		0
+	}
+}
+
+/// Objects drawn together with one shader, material, and mesh
+pub struct DrawItem<'a> {
+	/// Where the item goes in the queue
+	pub key: SortKey,
+	/// The objects drawn, all sharing a mesh and material
+	pub objects: Vec<&'a Object>,
+	/// The defines of the instanced permutation, if the objects get drawn in
+	/// one call
+	pub instanced: Option<Defines>
+}
+
+/// Everything to draw in a view, put in the order that changes the least
+/// state between draws
+pub struct RenderQueue<'a> {
+	/// The items to draw, in order once sorted
+	items: Vec<DrawItem<'a>>
+}
+
+impl<'a> RenderQueue<'a> {
+	/// Whether there's nothing to draw
+	pub fn is_empty(&self) -> bool {
+		self.items.is_empty()
+	}
+
+	/// Gets the items to draw, in order if the queue has been sorted
+	pub fn items(&self) -> &[DrawItem<'a>] {
+		&self.items
+	}
+
+	/// Gets how many items there are to draw
+	pub fn len(&self) -> usize {
+		self.items.len()
+	}
+
+	/// Creates an empty queue
+	pub fn new() -> RenderQueue<'a> {
+		RenderQueue {
+			items: Vec::new()
+		}
+	}
+
+	/// Gets the items drawn in one pass
+	///
+	/// Only works once the queue has been sorted, which puts each pass
+	/// together.
+	///
+	/// * `pass` - the pass to get the items of
+	pub fn pass_items(&self, pass: RenderPass) -> &[DrawItem<'a>] {
+		let start = self.items.partition_point(|item| item.key.pass < pass);
+		let end = self.items.partition_point(|item| item.key.pass <= pass);
+
+		&self.items[start..end]
+	}
+
+	/// Adds an item to draw
+	///
+	/// * `item` - the item to draw
+	pub fn push(&mut self, item: DrawItem<'a>) {
+		self.items.push(item);
+	}
+
+	/// Puts the items in the order of their keys
+	///
+	/// Items with the same key keep the order they were pushed in.
+	pub fn sort(&mut self) {
+		self.items.sort_by_key(|item| item.key);
+	}
+}
+
+impl<'a> Default for RenderQueue<'a> {
+	fn default() -> RenderQueue<'a> {
+		Self::new()
+	}
+}
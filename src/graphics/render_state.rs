@@ -0,0 +1,141 @@
+/// How the colors a draw call makes combine with what's already drawn
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlendMode {
+	/// Replace what's there
+	None,
+	/// Mix over what's there by alpha, for glass and fades
+	Alpha,
+	/// Add to what's there, for glows and particles
+	Additive,
+	/// Multiply what's there, for tints and shadows that darken
+	Multiply,
+	/// Mix over what's there by alpha, for colors that were already
+	/// multiplied by it
+	Premultiplied
+}
+
+impl BlendMode {
+	/// Whether anything behind shows through, so it has to be drawn after
+	/// what's behind it
+	pub fn is_transparent(&self) -> bool {
+		*self != BlendMode::None
+	}
+}
+
+/// Which fragments pass the depth test, compared against the depth buffer
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DepthFunc {
+	Never,
+	Less,
+	Equal,
+	LessEqual,
+	Greater,
+	NotEqual,
+	GreaterEqual,
+	Always
+}
+
+/// Which faces get thrown away before rasterizing
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CullMode {
+	/// Draw every face
+	None,
+	Front,
+	Back,
+	FrontAndBack
+}
+
+/// Which winding order makes a triangle front facing
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FrontFace {
+	/// Counter clockwise
+	Ccw,
+	/// Clockwise
+	Cw
+}
+
+/// Fixed function state that applies to a draw call
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderState {
+	/// How colors combine with what's already drawn
+	pub blend_mode: BlendMode,
+	/// How faces are culled
+	pub cull_mode: CullMode,
+	/// How incoming depth is compared to the depth buffer
+	pub depth_func: DepthFunc,
+	/// Whether to test against the depth buffer at all
+	pub depth_test: bool,
+	/// Whether to write to the depth buffer
+	pub depth_write: bool,
+	/// Which winding is the front of a triangle
+	pub front_face: FrontFace
+}
+
+impl RenderState {
+	/// State for passes that cover the whole screen
+	///
+	/// Nothing gets blended, depth tested, depth written, or culled.
+	pub fn fullscreen() -> RenderState {
+		RenderState {
+			blend_mode: BlendMode::None,
+			cull_mode: CullMode::None,
+			depth_func: DepthFunc::Always,
+			depth_test: false,
+			depth_write: false,
+			front_face: FrontFace::Ccw
+		}
+	}
+
+	/// State for solid geometry
+	///
+	/// Not blended, depth tested and written, back faces culled, counter
+	/// clockwise front.
+	pub fn opaque() -> RenderState {
+		RenderState {
+			blend_mode: BlendMode::None,
+			cull_mode: CullMode::Back,
+			depth_func: DepthFunc::Less,
+			depth_test: true,
+			depth_write: true,
+			front_face: FrontFace::Ccw
+		}
+	}
+
+	/// State for the sky, drawn on the far plane after solid geometry
+	///
+	/// Not blended, culled, or depth written, and only drawn where nothing
+	/// nearer already has been.
+	pub fn sky() -> RenderState {
+		RenderState {
+			blend_mode: BlendMode::None,
+			cull_mode: CullMode::None,
+			depth_func: DepthFunc::LessEqual,
+			depth_test: true,
+			depth_write: false,
+			front_face: FrontFace::Ccw
+		}
+	}
+
+	/// State for things that can be seen through
+	///
+	/// Blended, depth tested but not written so what's further back still
+	/// gets drawn, back faces culled, counter clockwise front.
+	///
+	/// * `blend_mode` - how colors combine with what's behind
+	pub fn transparent(blend_mode: BlendMode) -> RenderState {
+		RenderState {
+			blend_mode,
+			cull_mode: CullMode::Back,
+			depth_func: DepthFunc::Less,
+			depth_test: true,
+			depth_write: false,
+			front_face: FrontFace::Ccw
+		}
+	}
+}
+
+impl Default for RenderState {
+	fn default() -> RenderState {
+		Self::opaque()
+	}
+}
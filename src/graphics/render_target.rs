@@ -0,0 +1,294 @@
+use std::fmt;
+
+use super::{
+	backend::{Attachment, FramebufferHandle, RenderBackend, RenderbufferHandle},
+	camera::Camera,
+	texture::{FilterMode, Texture, TextureFormat, TextureOptions, WrapMode}
+};
+
+/// What a render target keeps depth in
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DepthAttachment {
+	/// No depth, so nothing gets depth tested
+	None,
+	/// A renderbuffer, which gets depth tested against but can't be sampled
+	Renderbuffer,
+	/// A texture that materials can sample, which needs depth_textures
+	Texture
+}
+
+/// How big a render target is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TargetSize {
+	/// Follows the drawing buffer, scaled on each side
+	Canvas { scale: f32 },
+	/// Stays the same size no matter what the canvas does
+	Fixed { width: u32, height: u32 }
+}
+
+impl TargetSize {
+	/// Works out the size in pixels
+	///
+	/// * `canvas_size` - the size of the drawing buffer
+	///
+	/// Returns - the width and height, at least 1 pixel each
+	pub fn resolve(&self, canvas_size: (u32, u32)) -> (u32, u32) {
+		let (width, height) = match *self {
+			TargetSize::Canvas { scale } => (
+				(canvas_size.0 as f32 * scale).round() as u32,
+				(canvas_size.1 as f32 * scale).round() as u32
+			),
+			TargetSize::Fixed { width, height } => (width, height)
+		};
+
+		(width.max(1), height.max(1))
+	}
+}
+
+/// How a render target gets set up
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderTargetOptions {
+	/// What depth gets kept in
+	pub depth: DepthAttachment,
+	/// How the color texture gets filtered when it's sampled
+	pub filter: FilterMode,
+	/// How big the target is
+	pub size: TargetSize
+}
+
+impl Default for RenderTargetOptions {
+	/// The same size as the canvas, with depth testing
+	fn default() -> RenderTargetOptions {
+		RenderTargetOptions {
+			depth: DepthAttachment::Renderbuffer,
+			filter: FilterMode::Linear,
+			size: TargetSize::Canvas { scale: 1.0 }
+		}
+	}
+}
+
+/// Something that went wrong setting up a render target
+#[derive(Clone, Debug, PartialEq)]
+pub enum RenderTargetError {
+	/// A depth texture was asked for, but the backend can't render to one
+	DepthTexturesUnsupported,
+	/// The backend couldn't create a framebuffer, renderbuffer, or texture
+	OutOfResources,
+	/// The framebuffer can't be drawn to
+	Incomplete(String),
+	/// There's no render target with this name
+	UnknownTarget(String)
+}
+
+impl fmt::Display for RenderTargetError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			RenderTargetError::DepthTexturesUnsupported => write!(f, "Depth textures are not supported"),
+			RenderTargetError::OutOfResources => write!(f, "Could not create render target"),
+			RenderTargetError::Incomplete(message) => write!(f, "{}", message),
+			RenderTargetError::UnknownTarget(name) => write!(f, "No render target named {}", name)
+		}
+	}
+}
+
+impl std::error::Error for RenderTargetError {}
+
+/// A framebuffer to render into, with textures that can be sampled afterwards
+///
+/// Materials sample the color texture by the target's name, and the depth
+/// texture by the name with .depth on the end.
+pub struct RenderTarget {
+	/// The camera to render the world from each frame, if any
+	camera: Option<Camera>,
+	/// Where color gets rendered to
+	color: Texture,
+	/// Where depth gets rendered to, if it's a texture
+	depth: Option<Texture>,
+	/// Where depth gets rendered to, if it's a renderbuffer
+	depth_buffer: Option<RenderbufferHandle>,
+	/// The framebuffer holding it all together
+	framebuffer: Option<FramebufferHandle>,
+	/// Height in pixels
+	height: u32,
+	/// How this target was set up
+	options: RenderTargetOptions,
+	/// Width in pixels
+	width: u32
+}
+
+impl RenderTarget {
+	/// Binds this target for drawing and sets the viewport to cover it
+	///
+	/// * `backend` - the backend the target was created with
+	pub fn bind<B: RenderBackend>(&self, backend: &mut B) {
+		backend.bind_framebuffer(self.framebuffer);
+		backend.viewport(0, 0, self.width, self.height);
+	}
+
+	/// Creates a render target
+	///
+	/// * `backend` - the backend to create it with
+	/// * `options` - how to set it up
+	/// * `canvas_size` - the size of the drawing buffer, for targets that follow it
+	///
+	/// Returns - the target, or why it couldn't be made
+	pub fn create<B: RenderBackend>(
+		backend: &mut B,
+		options: &RenderTargetOptions,
+		canvas_size: (u32, u32)
+	) -> Result<RenderTarget, RenderTargetError> {
+		if options.depth == DepthAttachment::Texture && !backend.capabilities().depth_textures {
+			return Err(RenderTargetError::DepthTexturesUnsupported);
+		}
+
+		let (width, height) = options.size.resolve(canvas_size);
+
+		// Color gets clamped and left unmipmapped so any size works on WebGL1
+		let color_options = TextureOptions {
+			mag_filter: options.filter,
+			min_filter: options.filter,
+			mipmaps: false,
+			wrap_s: WrapMode::ClampToEdge,
+			wrap_t: WrapMode::ClampToEdge
+		};
+		let color = Texture::allocate(backend, width, height, TextureFormat::Rgba8, &color_options);
+
+		// Depth can only be sampled exactly
+		let depth = match options.depth {
+			DepthAttachment::Texture => {
+				let depth_options = TextureOptions {
+					mag_filter: FilterMode::Nearest,
+					min_filter: FilterMode::Nearest,
+					..color_options
+				};
+				Some(Texture::allocate(backend, width, height, TextureFormat::Depth, &depth_options))
+			},
+			_ => None
+		};
+		let depth_buffer = match options.depth {
+			DepthAttachment::Renderbuffer => backend.create_renderbuffer(),
+			_ => None
+		};
+		if let Some(depth_buffer) = depth_buffer {
+			backend.depth_renderbuffer_storage(depth_buffer, width, height);
+		}
+
+		// Put the framebuffer together
+		let framebuffer = backend.create_framebuffer();
+		let target = RenderTarget {
+			camera: None,
+			color,
+			depth,
+			depth_buffer,
+			framebuffer,
+			height,
+			options: *options,
+			width
+		};
+
+		// Make sure everything was actually created
+		let depth_missing = match options.depth {
+			DepthAttachment::None => false,
+			DepthAttachment::Renderbuffer => target.depth_buffer.is_none(),
+			DepthAttachment::Texture => target.depth.as_ref().and_then(|depth| depth.handle).is_none()
+		};
+		if target.framebuffer.is_none() || target.color.handle.is_none() || depth_missing {
+			target.delete(backend);
+			return Err(RenderTargetError::OutOfResources);
+		}
+
+		// Attach everything
+		backend.bind_framebuffer(target.framebuffer);
+		backend.framebuffer_texture(Attachment::Color, target.color.handle);
+		if let Some(depth) = &target.depth {
+			backend.framebuffer_texture(Attachment::Depth, depth.handle);
+		}
+		if target.depth_buffer.is_some() {
+			backend.framebuffer_renderbuffer(target.depth_buffer);
+		}
+		let status = backend.check_framebuffer_status();
+		backend.bind_framebuffer(None);
+
+		match status {
+			Ok(()) => Ok(target),
+			Err(message) => {
+				target.delete(backend);
+				Err(RenderTargetError::Incomplete(message))
+			}
+		}
+	}
+
+	/// Frees this target from the GPU
+	///
+	/// * `backend` - the backend the target was created with
+	pub fn delete<B: RenderBackend>(&self, backend: &mut B) {
+		if let Some(framebuffer) = self.framebuffer {
+			backend.delete_framebuffer(framebuffer);
+		}
+		if let Some(depth_buffer) = self.depth_buffer {
+			backend.delete_renderbuffer(depth_buffer);
+		}
+		if let Some(depth) = &self.depth {
+			depth.delete(backend);
+		}
+		self.color.delete(backend);
+	}
+
+	/// Gets the camera the world is rendered from each frame
+	pub fn get_camera(&self) -> Option<&Camera> {
+		self.camera.as_ref()
+	}
+
+	/// Gets the camera the world is rendered from each frame, mutably
+	pub fn get_camera_mut(&mut self) -> Option<&mut Camera> {
+		self.camera.as_mut()
+	}
+
+	/// Gets the texture color gets rendered to
+	pub fn get_color(&self) -> &Texture {
+		&self.color
+	}
+
+	/// Gets the texture depth gets rendered to, if depth is kept in a texture
+	pub fn get_depth(&self) -> Option<&Texture> {
+		self.depth.as_ref()
+	}
+
+	/// Gets how this target was set up
+	pub fn get_options(&self) -> &RenderTargetOptions {
+		&self.options
+	}
+
+	/// Gets the width and height in pixels
+	pub fn get_size(&self) -> (u32, u32) {
+		(self.width, self.height)
+	}
+
+	/// Remakes the attachments if the target should be a different size now
+	///
+	/// The camera is kept.
+	///
+	/// * `backend` - the backend the target was created with
+	/// * `canvas_size` - the new size of the drawing buffer
+	///
+	/// Returns - why the target couldn't be remade, which leaves it unusable
+	pub fn resize<B: RenderBackend>(&mut self, backend: &mut B, canvas_size: (u32, u32)) -> Result<(), RenderTargetError> {
+		if self.options.size.resolve(canvas_size) == (self.width, self.height) {
+			return Ok(());
+		}
+
+		self.delete(backend);
+		let mut resized = RenderTarget::create(backend, &self.options, canvas_size)?;
+		resized.camera = self.camera.take();
+		*self = resized;
+
+		Ok(())
+	}
+
+	/// Sets the camera the world is rendered from each frame
+	///
+	/// * `camera` - the camera, or None to leave the target for other passes
+	pub fn set_camera(&mut self, camera: Option<Camera>) {
+		self.camera = camera;
+	}
+}
@@ -2,9 +2,7 @@
 attribute vec4 position;
 
 // Uniforms
-uniform mat4 model;
-uniform mat4 view;
-uniform mat4 projection;
+#include "common.glsl"
 
 // Start shader
 void main() {
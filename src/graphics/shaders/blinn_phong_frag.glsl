@@ -0,0 +1,73 @@
+precision mediump float;
+
+// Inputs
+varying vec3 world_position;
+varying vec3 world_normal;
+
+// Material uniforms
+uniform vec3 ambient;
+uniform vec4 color;
+uniform float shininess;
+uniform vec3 specular;
+
+// Light uniforms
+// Types are 0 for directional, 1 for point, and 2 for spot
+// The engine defines MAX_LIGHTS, from lighting.rs
+uniform vec3 camera_position;
+uniform int light_count;
+uniform int light_types[MAX_LIGHTS];
+uniform vec3 light_positions[MAX_LIGHTS];
+uniform vec3 light_directions[MAX_LIGHTS];
+uniform vec3 light_colors[MAX_LIGHTS];
+uniform vec3 light_attenuations[MAX_LIGHTS];
+uniform vec2 light_cones[MAX_LIGHTS];
+
+#include "shadows.glsl"
+
+// Start shader
+void main() {
+	vec3 normal = normalize(world_normal);
+	vec3 to_camera = normalize(camera_position - world_position);
+
+	vec3 diffuse_total = vec3(0.0);
+	vec3 specular_total = vec3(0.0);
+	for (int i = 0; i < MAX_LIGHTS; i++) {
+		if (i >= light_count) {
+			break;
+		}
+
+		// Direction to the light and how much of it arrives
+		vec3 to_light;
+		float strength = 1.0;
+		if (light_types[i] == 0) {
+			to_light = -light_directions[i];
+		} else {
+			vec3 offset = light_positions[i] - world_position;
+			float distance = length(offset);
+			to_light = offset / distance;
+
+			vec3 attenuation = light_attenuations[i];
+			strength = 1.0 / (attenuation.x + attenuation.y * distance + attenuation.z * distance * distance);
+
+			// Fade out towards the edge of the cone
+			if (light_types[i] == 2) {
+				float cos_angle = dot(-to_light, light_directions[i]);
+				strength *= smoothstep(light_cones[i].y, light_cones[i].x, cos_angle);
+			}
+		}
+
+		// Less of it arrives if something is in the way
+		strength *= shadow(i, world_position);
+
+		// Blinn-Phong
+		float lambert = max(dot(normal, to_light), 0.0);
+		vec3 halfway = normalize(to_light + to_camera);
+		float highlight = lambert > 0.0 ? pow(max(dot(normal, halfway), 0.0), shininess) : 0.0;
+
+		diffuse_total += light_colors[i] * lambert * strength;
+		specular_total += light_colors[i] * highlight * strength;
+	}
+
+	vec3 lit = color.rgb * (ambient + diffuse_total) + specular * specular_total;
+	gl_FragColor = vec4(lit, color.a);
+}
@@ -0,0 +1,24 @@
+precision mediump float;
+
+// Inputs
+varying vec2 uv;
+
+// Uniforms
+// Intensity is only used when the bloom gets added back in
+uniform sampler2D source;
+uniform float intensity;
+uniform float knee;
+uniform float threshold;
+
+// Start shader
+void main() {
+	vec3 color = texture2D(source, uv).rgb;
+
+	// Fade in over the knee instead of cutting off hard at the threshold
+	float brightness = max(color.r, max(color.g, color.b));
+	float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+	soft = soft * soft / (4.0 * knee + 0.00001);
+	float contribution = max(soft, brightness - threshold) / max(brightness, 0.00001);
+
+	gl_FragColor = vec4(color * contribution, 1.0);
+}
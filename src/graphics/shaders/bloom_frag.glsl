@@ -0,0 +1,18 @@
+precision mediump float;
+
+// Inputs
+varying vec2 uv;
+
+// Uniforms
+// Knee and threshold are only used when the bright parts get picked out
+uniform sampler2D source;
+uniform sampler2D bloom;
+uniform float intensity;
+uniform float knee;
+uniform float threshold;
+
+// Start shader
+void main() {
+	vec4 color = texture2D(source, uv);
+	gl_FragColor = vec4(color.rgb + texture2D(bloom, uv).rgb * intensity, color.a);
+}
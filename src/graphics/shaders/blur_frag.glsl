@@ -0,0 +1,23 @@
+precision mediump float;
+
+// Inputs
+varying vec2 uv;
+
+// Uniforms
+// Direction is (1, 0) to blur across and (0, 1) to blur down
+uniform sampler2D source;
+uniform vec2 direction;
+uniform vec2 texel_size;
+
+// Start shader
+void main() {
+	// 9 tap gaussian, in 5 samples by leaning on linear filtering
+	vec2 offset = direction * texel_size;
+	vec4 sum = texture2D(source, uv) * 0.2270270270;
+	sum += texture2D(source, uv + offset * 1.3846153846) * 0.3162162162;
+	sum += texture2D(source, uv - offset * 1.3846153846) * 0.3162162162;
+	sum += texture2D(source, uv + offset * 3.2307692308) * 0.0702702703;
+	sum += texture2D(source, uv - offset * 3.2307692308) * 0.0702702703;
+
+	gl_FragColor = sum;
+}
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+
+use crate::graphics::{backend::{ProgramHandle, RenderBackend, ShaderStage}, gl};
+use super::{
+	preprocessor::{Defines, PreprocessError, Preprocessor},
+	shader_sources::{get_shader_defines, get_shader_includes, get_shader_sources},
+	CompiledShader,
+	ShaderError,
+	ShaderSource,
+	Uniform,
+	UniformType
+};
+
+/// Every permutation of every shader that has been built
+///
+/// A permutation is a shader built with a particular set of defines. They get
+/// built the first time something asks for them and kept after that, along
+/// with what went wrong if they didn't build, so a broken permutation isn't
+/// rebuilt every frame.
+pub struct ShaderCache {
+	/// Built permutations by shader name, then by defines
+	permutations: HashMap<&'static str, HashMap<Defines, Result<CompiledShader, ShaderError>>>,
+	/// Resolves includes in every shader
+	preprocessor: Preprocessor,
+	/// Vertex and fragment source that replaced the built in source, by name
+	reloaded: HashMap<&'static str, (String, String)>,
+	/// What every shader is declared with, by name
+	sources: HashMap<&'static str, ShaderSource<'static>>
+}
+
+impl ShaderCache {
+	/// Frees every permutation and goes back to the built in source
+	///
+	/// * `backend` - the backend the programs were built with
+	pub fn clear<B: RenderBackend>(&mut self, backend: &mut B) {
+		for (_, permutations) in self.permutations.drain() {
+			delete_permutations(backend, permutations);
+		}
+		self.reloaded.clear();
+	}
+
+	/// Builds the plain permutation of every shader
+	///
+	/// * `backend` - the backend to build with
+	///
+	/// Returns - what went wrong with each shader that didn't build
+	pub fn compile_all<B: RenderBackend>(&mut self, backend: &mut B) -> Vec<ShaderError> {
+		self.clear(backend);
+
+		let names: Vec<&'static str> = self.sources.keys().copied().collect();
+		let defines = Defines::new();
+		names.into_iter()
+			.filter_map(|name| self.prepare(backend, name, &defines).err())
+			.collect()
+	}
+
+	/// Gets a permutation that has already been built
+	///
+	/// * `name` - the name of the shader
+	/// * `defines` - the defines it was built with
+	///
+	/// Returns - None if it hasn't been built or didn't build
+	pub fn get(&self, name: &str, defines: &Defines) -> Option<&CompiledShader> {
+		self.permutations.get(name)?
+			.get(defines)?
+			.as_ref()
+			.ok()
+	}
+
+	/// Gets what went wrong with every permutation that didn't build
+	pub fn get_errors(&self) -> Vec<&ShaderError> {
+		self.permutations.values()
+			.flat_map(|permutations| permutations.values())
+			.filter_map(|built| built.as_ref().err())
+			.collect()
+	}
+
+	/// Gets the preprocessor shaders are expanded with
+	pub fn get_preprocessor(&self) -> &Preprocessor {
+		&self.preprocessor
+	}
+
+	/// Gets the preprocessor shaders are expanded with, mutably
+	///
+	/// Permutations that are already built don't see changes to the files.
+	pub fn get_preprocessor_mut(&mut self) -> &mut Preprocessor {
+		&mut self.preprocessor
+	}
+
+	/// Creates a cache of the built in shaders, with nothing built yet
+	pub fn new() -> ShaderCache {
+		let mut preprocessor = Preprocessor::new();
+		for (name, value) in get_shader_defines() {
+			preprocessor.add_define(name, &value);
+		}
+		for (name, source) in get_shader_includes() {
+			preprocessor.add_file(name, source);
+		}
+
+		ShaderCache {
+			permutations: HashMap::new(),
+			preprocessor,
+			reloaded: HashMap::new(),
+			sources: get_shader_sources()
+		}
+	}
+
+	/// Makes sure a permutation has been built
+	///
+	/// * `backend` - the backend to build with
+	/// * `name` - the name of the shader
+	/// * `defines` - the defines to build it with
+	///
+	/// Returns - what went wrong if it didn't build, now or before
+	pub fn prepare<B: RenderBackend>(
+		&mut self,
+		backend: &mut B,
+		name: &str,
+		defines: &Defines
+	) -> Result<(), ShaderError> {
+		let (&name, source) = self.sources.get_key_value(name)
+			.ok_or_else(|| ShaderError::new(name, None, "No shader with this name"))?;
+
+		// Already tried
+		if let Some(built) = self.permutations.get(name).and_then(|permutations| permutations.get(defines)) {
+			return built.as_ref().map(|_| ()).map_err(|error| error.clone());
+		}
+
+		// Use reloaded source if there is any
+		let (vertex_shader, fragment_shader) = match self.reloaded.get(name) {
+			Some((vertex, fragment)) => (Some(vertex.as_str()), Some(fragment.as_str())),
+			None => (source.vertex_shader, source.fragment_shader)
+		};
+
+		let built = build(backend, &self.preprocessor, name, source, vertex_shader, fragment_shader, defines);
+		let result = built.as_ref().map(|_| ()).map_err(|error| error.clone());
+		self.permutations.entry(name)
+			.or_default()
+			.insert(defines.clone(), built);
+
+		result
+	}
+
+	/// Replaces the source of a shader and rebuilds it
+	///
+	/// The plain permutation gets built straight away. If it doesn't build,
+	/// nothing changes. If it does, every other permutation is thrown out to
+	/// be rebuilt from the new source when it's next needed.
+	///
+	/// * `backend` - the backend to build with
+	/// * `name` - the name of the shader
+	/// * `vertex_shader` - new vertex shader source
+	/// * `fragment_shader` - new fragment shader source
+	///
+	/// Returns - what went wrong if the new source didn't build
+	pub fn reload<B: RenderBackend>(
+		&mut self,
+		backend: &mut B,
+		name: &str,
+		vertex_shader: &str,
+		fragment_shader: &str
+	) -> Result<(), ShaderError> {
+		let (&name, source) = self.sources.get_key_value(name)
+			.ok_or_else(|| ShaderError::new(name, None, "No shader with this name"))?;
+
+		// Try the new source first
+		let defines = Defines::new();
+		let built = build(
+			backend,
+			&self.preprocessor,
+			name,
+			source,
+			Some(vertex_shader),
+			Some(fragment_shader),
+			&defines
+		)?;
+
+		// Swap it in
+		if let Some(old) = self.permutations.remove(name) {
+			delete_permutations(backend, old);
+		}
+		let mut permutations = HashMap::new();
+		permutations.insert(defines, Ok(built));
+		self.permutations.insert(name, permutations);
+		self.reloaded.insert(name, (String::from(vertex_shader), String::from(fragment_shader)));
+
+		Ok(())
+	}
+}
+
+impl Default for ShaderCache {
+	fn default() -> ShaderCache {
+		Self::new()
+	}
+}
+
+/// Preprocesses, compiles, and links one permutation of a shader
+///
+/// * `backend` - the backend to build with
+/// * `preprocessor` - expands includes
+/// * `name` - the name of the shader
+/// * `source` - what the shader is declared with
+/// * `vertex_shader` - the vertex source to use instead of the declared one
+/// * `fragment_shader` - the fragment source to use instead of the declared one
+/// * `defines` - the defines to build with
+fn build<B: RenderBackend>(
+	backend: &mut B,
+	preprocessor: &Preprocessor,
+	name: &'static str,
+	source: &ShaderSource<'static>,
+	vertex_shader: Option<&str>,
+	fragment_shader: Option<&str>,
+	defines: &Defines
+) -> Result<CompiledShader, ShaderError> {
+	// Expand both stages
+	let expand = |stage, code: Option<&str>| match code {
+		Some(code) => preprocessor.process(code, defines)
+			.map(Some)
+			.map_err(|error| preprocess_error(name, stage, error)),
+		None => Ok(None)
+	};
+	let vertex_shader = expand(ShaderStage::Vertex, vertex_shader)?;
+	let fragment_shader = expand(ShaderStage::Fragment, fragment_shader)?;
+
+	// Build it
+	let expanded = ShaderSource {
+		vertex_shader: vertex_shader.as_deref(),
+		fragment_shader: fragment_shader.as_deref(),
+		..source.clone()
+	};
+	let program = gl::build_program(backend, name, &expanded)?;
+
+	Ok(find_shader_inputs(backend, program, source))
+}
+
+/// Frees every permutation of a shader that built
+fn delete_permutations<B: RenderBackend>(backend: &mut B, permutations: HashMap<Defines, Result<CompiledShader, ShaderError>>) {
+	for shader in permutations.into_values().flatten() {
+		shader.delete(backend);
+	}
+}
+
+/// Finds the uniforms of a freshly built program
+///
+/// * `backend` - the backend the program was built with
+/// * `program` - the linked program
+/// * `source` - what the shader was declared with
+///
+/// Returns - the program ready to render with
+fn find_shader_inputs<B: RenderBackend>(
+	backend: &mut B,
+	program: ProgramHandle,
+	source: &ShaderSource<'static>
+) -> CompiledShader {
+	// Find uniform locations
+	// Start with MVP uniforms
+	let model_location = backend.get_uniform_location(program, "model");
+	let model_uniform = Uniform {
+		location: model_location,
+		u_type: UniformType::Mat4
+	};
+	let view_location = backend.get_uniform_location(program, "view");
+	let view_uniform = Uniform {
+		location: view_location,
+		u_type: UniformType::Mat4
+	};
+	let projection_location = backend.get_uniform_location(program, "projection");
+	let projection_uniform = Uniform {
+		location: projection_location,
+		u_type: UniformType::Mat4
+	};
+
+	// Find other uniforms
+	let mut uniforms = HashMap::new();
+
+	// Run through each uniform name
+	// there should be a matching type in uniform_types
+	for i in 0..source.uniform_names.len() {
+		let uniform_name = source.uniform_names[i];
+		let u_type = source.uniform_types[i].clone();
+
+		let location = backend.get_uniform_location(program, uniform_name);
+
+		let uniform = Uniform {location, u_type};
+
+		uniforms.insert(uniform_name, uniform);
+	}
+
+	// Put it all together
+	let attributes = source.attributes.iter()
+		.map(|&(_, attribute)| attribute)
+		.collect();
+
+	CompiledShader {
+		attributes,
+		instancing: source.instancing,
+		model_uniform: Some(model_uniform),
+		program: Some(program),
+		projection_uniform: Some(projection_uniform),
+		uniform_defaults: source.uniform_defaults.clone(),
+		uniforms,
+		view_uniform: Some(view_uniform)
+	}
+}
+
+/// Turns a preprocessing problem into a shader error
+fn preprocess_error(name: &str, stage: ShaderStage, error: PreprocessError) -> ShaderError {
+	let message = match &error.file {
+		Some(file) => format!("In {}: {}", file, error.message),
+		None => error.message
+	};
+
+	ShaderError {
+		line: Some(error.line),
+		..ShaderError::new(name, Some(stage), &message)
+	}
+}
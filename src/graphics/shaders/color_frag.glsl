@@ -0,0 +1,9 @@
+precision mediump float;
+
+// Uniforms
+uniform vec4 color;
+
+// Start shader
+void main() {
+	gl_FragColor = color;
+}
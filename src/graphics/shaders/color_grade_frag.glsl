@@ -0,0 +1,36 @@
+precision mediump float;
+
+// Inputs
+varying vec2 uv;
+
+// Uniforms
+// The LUT is a strip of lut_size squares from left to right, one for each
+// level of blue, with red across each square and green down it
+uniform sampler2D source;
+uniform sampler2D lut;
+uniform float intensity;
+uniform float lut_size;
+
+// Looks a color up in the LUT, blending between the two nearest squares
+vec3 grade(vec3 color) {
+	float last = lut_size - 1.0;
+	float slice = color.b * last;
+	float slice_low = floor(slice);
+	float slice_high = min(slice_low + 1.0, last);
+
+	// Stay on texel centers so squares don't bleed into each other
+	float x = (color.r * last + 0.5) / (lut_size * lut_size);
+	float y = (color.g * last + 0.5) / lut_size;
+	vec3 low = texture2D(lut, vec2(x + slice_low / lut_size, y)).rgb;
+	vec3 high = texture2D(lut, vec2(x + slice_high / lut_size, y)).rgb;
+
+	return mix(low, high, slice - slice_low);
+}
+
+// Start shader
+void main() {
+	vec4 color = texture2D(source, uv);
+	vec3 graded = grade(clamp(color.rgb, 0.0, 1.0));
+
+	gl_FragColor = vec4(mix(color.rgb, graded, intensity), color.a);
+}
@@ -0,0 +1,10 @@
+// Uniforms every 3d shader gets
+// Instanced draws take the model matrix per instance instead
+#ifdef INSTANCED
+attribute mat4 instance_model;
+#define model instance_model
+#else
+uniform mat4 model;
+#endif
+uniform mat4 view;
+uniform mat4 projection;
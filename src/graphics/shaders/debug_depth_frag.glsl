@@ -0,0 +1,15 @@
+precision mediump float;
+
+// Inputs
+varying float view_depth;
+
+// Uniforms
+uniform vec2 depth_range;
+
+// Start shader
+void main() {
+	// White at the near plane, black at the far plane
+	float depth = clamp((view_depth - depth_range.x) / (depth_range.y - depth_range.x), 0.0, 1.0);
+
+	gl_FragColor = vec4(vec3(1.0 - depth), 1.0);
+}
@@ -0,0 +1,16 @@
+// Attributes
+attribute vec4 position;
+
+// Uniforms
+#include "common.glsl"
+
+// Outputs
+varying float view_depth;
+
+// Start shader
+void main() {
+	vec4 view_position = view * model * position;
+
+	view_depth = -view_position.z;
+	gl_Position = projection * view_position;
+}
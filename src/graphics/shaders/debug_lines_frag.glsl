@@ -0,0 +1,9 @@
+precision mediump float;
+
+// Inputs
+varying vec4 line_color;
+
+// Start shader
+void main() {
+	gl_FragColor = line_color;
+}
@@ -0,0 +1,17 @@
+// Attributes
+attribute vec4 position;
+attribute vec4 color;
+
+// Uniforms
+#include "common.glsl"
+
+// Outputs
+varying vec4 line_color;
+
+// Start shader
+void main() {
+	mat4 mvp_matrix = projection * view * model;
+
+	line_color = color;
+	gl_Position = mvp_matrix * position;
+}
@@ -0,0 +1,11 @@
+precision mediump float;
+
+// Inputs
+varying vec3 world_position;
+varying vec3 world_normal;
+
+// Start shader
+void main() {
+	// Map -1 to 1 onto 0 to 1, so every direction gets a color
+	gl_FragColor = vec4(normalize(world_normal) * 0.5 + 0.5, 1.0);
+}
@@ -0,0 +1,17 @@
+precision mediump float;
+
+// Inputs
+varying vec2 uv;
+
+// Uniforms
+uniform float checker_scale;
+
+// Start shader
+void main() {
+	// Alternate squares, tinted by the coordinates so flips and seams show
+	vec2 cell = floor(uv * checker_scale);
+	float checker = mod(cell.x + cell.y, 2.0);
+	vec3 tint = vec3(fract(uv), 1.0);
+
+	gl_FragColor = vec4(tint * mix(0.35, 1.0, checker), 1.0);
+}
@@ -0,0 +1,12 @@
+// Attributes
+// One triangle big enough to cover the whole screen
+attribute vec4 position;
+
+// Outputs
+varying vec2 uv;
+
+// Start shader
+void main() {
+	uv = position.xy * 0.5 + 0.5;
+	gl_Position = position;
+}
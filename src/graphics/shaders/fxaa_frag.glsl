@@ -0,0 +1,52 @@
+precision mediump float;
+
+// Inputs
+varying vec2 uv;
+
+// Uniforms
+uniform sampler2D source;
+uniform vec2 texel_size;
+uniform float reduce_min;
+uniform float reduce_multiplier;
+uniform float span_max;
+
+float luma(vec3 color) {
+	return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+// Start shader
+void main() {
+	// Look at the corners around this pixel
+	vec4 middle = texture2D(source, uv);
+	float luma_middle = luma(middle.rgb);
+	float luma_nw = luma(texture2D(source, uv + vec2(-1.0, -1.0) * texel_size).rgb);
+	float luma_ne = luma(texture2D(source, uv + vec2(1.0, -1.0) * texel_size).rgb);
+	float luma_sw = luma(texture2D(source, uv + vec2(-1.0, 1.0) * texel_size).rgb);
+	float luma_se = luma(texture2D(source, uv + vec2(1.0, 1.0) * texel_size).rgb);
+	float luma_min = min(luma_middle, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+	float luma_max = max(luma_middle, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+	// Find which way the edge runs
+	vec2 direction = vec2(
+		-((luma_nw + luma_ne) - (luma_sw + luma_se)),
+		(luma_nw + luma_sw) - (luma_ne + luma_se)
+	);
+	float reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * reduce_multiplier, reduce_min);
+	float scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+	direction = clamp(direction * scale, vec2(-span_max), vec2(span_max)) * texel_size;
+
+	// Blur along the edge
+	vec3 near = 0.5 * (
+		texture2D(source, uv + direction * (1.0 / 3.0 - 0.5)).rgb +
+		texture2D(source, uv + direction * (2.0 / 3.0 - 0.5)).rgb
+	);
+	vec3 far = near * 0.5 + 0.25 * (
+		texture2D(source, uv - direction * 0.5).rgb +
+		texture2D(source, uv + direction * 0.5).rgb
+	);
+
+	// Going too far crosses into something else, so fall back
+	float luma_far = luma(far);
+	vec3 color = luma_far < luma_min || luma_far > luma_max ? near : far;
+	gl_FragColor = vec4(color, middle.a);
+}
@@ -0,0 +1,14 @@
+precision mediump float;
+
+// Inputs
+varying vec2 uv;
+
+// Uniforms
+uniform sampler2D source;
+uniform float gamma;
+
+// Start shader
+void main() {
+	vec4 color = texture2D(source, uv);
+	gl_FragColor = vec4(pow(color.rgb, vec3(1.0 / gamma)), color.a);
+}
@@ -0,0 +1,21 @@
+// Attributes
+attribute vec4 position;
+attribute vec3 normal;
+
+// Uniforms
+#include "common.glsl"
+
+// Outputs
+varying vec3 world_position;
+varying vec3 world_normal;
+
+// Start shader
+void main() {
+	vec4 world = model * position;
+
+	// Objects only scale evenly, so the model matrix works for normals too
+	// GLSL ES 1.00 can't make a mat3 out of a mat4, so build it from columns
+	world_position = world.xyz;
+	world_normal = mat3(model[0].xyz, model[1].xyz, model[2].xyz) * normal;
+	gl_Position = projection * view * world;
+}
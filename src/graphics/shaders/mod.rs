@@ -1,28 +1,222 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
-use web_sys::{WebGlProgram, WebGlUniformLocation};
+use nalgebra::{Matrix3, Matrix4, Vector2, Vector3, Vector4};
 
+use super::{backend::{ProgramHandle, RenderBackend, ShaderStage, UniformHandle}, vertex_layout::VertexAttribute};
+use self::preprocessor::find_line;
+
+pub mod cache;
+pub mod preprocessor;
 pub mod shader_sources;
 
 /// Holds information regarding a compiled shader
 pub struct CompiledShader {
+	/// The vertex attributes this shader reads
+	pub attributes: Vec<VertexAttribute>,
+	/// Whether the shader takes a model matrix per instance when INSTANCED is
+	/// defined
+	pub instancing: bool,
 	/// Location of the model uniform
 	pub model_uniform: Option<Uniform>,
 	/// The compiled shader program
-	pub program: Option<WebGlProgram>,
+	pub program: Option<ProgramHandle>,
 	/// Location of the projection uniform
 	pub projection_uniform: Option<Uniform>,
+	/// Values for uniforms that a material doesn't set
+	pub uniform_defaults: HashMap<&'static str, UniformValue>,
 	/// Map of other uniform locations
 	pub uniforms: HashMap<&'static str, Uniform>,
 	/// Location of the view uniform
 	pub view_uniform: Option<Uniform>
 }
 
+impl CompiledShader {
+	/// Frees this shader's program from the GPU
+	///
+	/// * `backend` - the backend the program was built with
+	pub fn delete<B: RenderBackend>(&self, backend: &mut B) {
+		if let Some(program) = self.program {
+			backend.delete_program(program);
+		}
+	}
+
+	/// Sets one of this shader's declared uniforms
+	///
+	/// The program has to be in use.
+	///
+	/// * `backend` - the backend the program was built with
+	/// * `name` - the name of the uniform
+	/// * `value` - the value to set, which has to match the declared type
+	///
+	/// Returns - an error if the uniform isn't declared or the type is wrong
+	pub fn set_uniform<B: RenderBackend>(
+		&self,
+		backend: &mut B,
+		name: &str,
+		value: &UniformValue
+	) -> Result<(), UniformError> {
+		let uniform = self.uniforms.get(name)
+			.ok_or_else(|| UniformError::Unknown { name: String::from(name) })?;
+
+		uniform.set(backend, name, value)
+	}
+}
+
+/// Something that went wrong building a shader program
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderError {
+	/// Column of the first error, if the driver reported one
+	pub column: Option<u32>,
+	/// Line of the first error, if the driver reported one
+	pub line: Option<u32>,
+	/// The first error in the log, or the whole log if it couldn't be parsed
+	pub message: String,
+	/// The name of the shader that failed
+	pub name: String,
+	/// The line of source code the error is on
+	pub source_line: Option<String>,
+	/// The stage that failed to compile, or None if linking failed
+	pub stage: Option<ShaderStage>
+}
+
+impl ShaderError {
+	/// Creates an error that didn't come from the driver
+	///
+	/// * `name` - the name of the shader that failed
+	/// * `stage` - the stage that failed, or None for linking
+	/// * `message` - what went wrong
+	pub fn new(name: &str, stage: Option<ShaderStage>, message: &str) -> ShaderError {
+		ShaderError {
+			column: None,
+			line: None,
+			message: String::from(message),
+			name: String::from(name),
+			source_line: None,
+			stage
+		}
+	}
+
+	/// Builds an error out of a GLSL info log
+	///
+	/// Logs look like "ERROR: 0:12: 'x' : undeclared identifier", and some
+	/// drivers add a column like "0:12(5): error: ...". Only the first error is
+	/// kept, since the rest usually follow from it. Errors in an included file
+	/// say which file in the message.
+	///
+	/// * `name` - the name of the shader that failed
+	/// * `stage` - the stage that failed, or None for linking
+	/// * `log` - the info log from the driver
+	/// * `source` - the expanded source code of the stage, to pull the bad line from
+	pub fn from_log(name: &str, stage: Option<ShaderStage>, log: &str, source: Option<&str>) -> ShaderError {
+		let (position, message) = log.lines()
+			.find_map(parse_log_line)
+			.unwrap_or_else(|| (LogPosition::default(), String::from(log.trim())));
+		let LogPosition { column, line, string_number } = position;
+
+		// Follow the #line directives back to the file the line came from
+		let found = match (line, source) {
+			(Some(line), Some(source)) => find_line(source, string_number.unwrap_or(0), line),
+			_ => None
+		};
+		let source_line = found.map(|(_, text)| String::from(text.trim()));
+		let message = match found {
+			Some((Some(file), _)) => format!("In {}: {}", file, message),
+			_ => message
+		};
+
+		ShaderError {
+			column,
+			line,
+			message,
+			name: String::from(name),
+			source_line,
+			stage
+		}
+	}
+}
+
+impl fmt::Display for ShaderError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.stage {
+			Some(ShaderStage::Vertex) => write!(f, "Vertex shader of {}", self.name)?,
+			Some(ShaderStage::Fragment) => write!(f, "Fragment shader of {}", self.name)?,
+			None => write!(f, "Linking {}", self.name)?
+		}
+
+		match (self.line, self.column) {
+			(Some(line), Some(column)) => write!(f, " failed at {}:{}: {}", line, column, self.message)?,
+			(Some(line), None) => write!(f, " failed at line {}: {}", line, self.message)?,
+			_ => write!(f, " failed: {}", self.message)?
+		}
+
+		if let Some(source_line) = &self.source_line {
+			write!(f, "\n    {}", source_line)?;
+		}
+
+		Ok(())
+	}
+}
+
+impl std::error::Error for ShaderError {}
+
+/// Where an info log says an error is, as far as the driver reported it
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct LogPosition {
+	column: Option<u32>,
+	line: Option<u32>,
+	/// Which source string, set by #line directives, 0 for the shader itself
+	string_number: Option<u32>
+}
+
+/// Pulls the position and message out of one line of an info log
+///
+/// Returns - where the error is and what it says, or None if this isn't an
+/// error
+fn parse_log_line(log_line: &str) -> Option<(LogPosition, String)> {
+	// ANGLE puts the severity first, Mesa puts it after the position
+	let log_line = log_line.trim();
+	let (rest, prefixed) = match log_line.strip_prefix("ERROR:") {
+		Some(rest) => (rest.trim_start(), true),
+		None => (log_line, false)
+	};
+
+	// Source string number, then line with an optional (column)
+	let (string_number, rest) = rest.split_once(':')?;
+	let (position, message) = rest.split_once(':')?;
+	let message = message.trim();
+	let message = match message.strip_prefix("error:") {
+		Some(message) => message.trim_start(),
+		None if prefixed => message,
+		// Warnings and anything else that isn't an error
+		None => return None
+	};
+
+	let (line, column) = match position.split_once('(') {
+		Some((line, column)) => (line, column.trim_end_matches(')').parse().ok()),
+		None => (position, None)
+	};
+	let position = LogPosition {
+		column,
+		line: line.trim().parse().ok(),
+		string_number: string_number.trim().parse().ok()
+	};
+
+	Some((position, String::from(message)))
+}
+
 /// Holds source code for a shader
 /// Made up of Options to strs
+#[derive(Clone)]
 pub struct ShaderSource<'a> {
+	/// names of the attributes the vertex shader reads, and what they hold
+	pub attributes: Vec<(&'a str, VertexAttribute)>,
+	/// whether the vertex shader reads instance_model instead of the model
+	/// uniform when INSTANCED is defined
+	pub instancing: bool,
 	pub vertex_shader: Option<&'a str>,
 	pub fragment_shader: Option<&'a str>,
+	/// values for uniforms that a material doesn't set
+	pub uniform_defaults: HashMap<&'a str, UniformValue>,
 	/// names for uniforms other than model, view, and projection
 	pub uniform_names: Vec<&'a str>,
 	/// types for uniforms other than model, view, and projection
@@ -31,11 +225,220 @@ pub struct ShaderSource<'a> {
 
 /// Describes the location and type of a uniform
 pub struct Uniform {
-	pub location: Option<WebGlUniformLocation>,
+	pub location: Option<UniformHandle>,
 	pub u_type: UniformType
 }
 
-#[derive(Eq, PartialEq, Clone, Copy)]
+impl Uniform {
+	/// Sets this uniform after checking the value against its type
+	///
+	/// Uniforms the driver optimized out have no location and are skipped.
+	///
+	/// * `backend` - the backend the program was built with
+	/// * `name` - the name of the uniform, for reporting errors
+	/// * `value` - the value to set
+	///
+	/// Returns - an error if the value doesn't match the type
+	pub fn set<B: RenderBackend>(&self, backend: &mut B, name: &str, value: &UniformValue) -> Result<(), UniformError> {
+		if !self.u_type.accepts(value) {
+			return Err(UniformError::TypeMismatch {
+				name: String::from(name),
+				expected: self.u_type.clone(),
+				found: value.get_type()
+			});
+		}
+
+		if let Some(location) = self.location {
+			backend.set_uniform(location, value);
+		}
+
+		Ok(())
+	}
+}
+
+/// The GLSL type of a uniform
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum UniformType {
-	Mat4
+	Float,
+	Int,
+	Bool,
+	Vec2,
+	Vec3,
+	Vec4,
+	IVec2,
+	IVec3,
+	IVec4,
+	Mat3,
+	Mat4,
+	Sampler2D,
+	SamplerCube,
+	/// A fixed length array of another type
+	Array(Box<UniformType>, usize)
+}
+
+impl UniformType {
+	/// Whether a value can be set on a uniform of this type
+	///
+	/// Arrays accept anything up to their declared length.
+	pub fn accepts(&self, value: &UniformValue) -> bool {
+		match (self, value) {
+			(UniformType::Array(element_type, length), UniformValue::Array(elements)) => {
+				elements.len() <= *length
+					&& elements.iter().all(|element| element_type.accepts(element))
+			},
+			(UniformType::Array(..), _) => false,
+			(u_type, value) => *u_type == value.get_type()
+		}
+	}
 }
+
+impl fmt::Display for UniformType {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			UniformType::Float => write!(f, "float"),
+			UniformType::Int => write!(f, "int"),
+			UniformType::Bool => write!(f, "bool"),
+			UniformType::Vec2 => write!(f, "vec2"),
+			UniformType::Vec3 => write!(f, "vec3"),
+			UniformType::Vec4 => write!(f, "vec4"),
+			UniformType::IVec2 => write!(f, "ivec2"),
+			UniformType::IVec3 => write!(f, "ivec3"),
+			UniformType::IVec4 => write!(f, "ivec4"),
+			UniformType::Mat3 => write!(f, "mat3"),
+			UniformType::Mat4 => write!(f, "mat4"),
+			UniformType::Sampler2D => write!(f, "sampler2D"),
+			UniformType::SamplerCube => write!(f, "samplerCube"),
+			UniformType::Array(element_type, length) => write!(f, "{}[{}]", element_type, length)
+		}
+	}
+}
+
+/// A value that can be set on a uniform
+///
+/// Samplers hold the texture unit they read from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UniformValue {
+	Float(f32),
+	Int(i32),
+	Bool(bool),
+	Vec2([f32; 2]),
+	Vec3([f32; 3]),
+	Vec4([f32; 4]),
+	IVec2([i32; 2]),
+	IVec3([i32; 3]),
+	IVec4([i32; 4]),
+	/// Column major
+	Mat3([f32; 9]),
+	/// Column major
+	Mat4([f32; 16]),
+	Sampler2D(i32),
+	SamplerCube(i32),
+	/// Elements all of the same type
+	Array(Vec<UniformValue>)
+}
+
+impl UniformValue {
+	/// Gets the type of uniform this value fits
+	///
+	/// Arrays are typed by their first element and sized by their length.
+	pub fn get_type(&self) -> UniformType {
+		match self {
+			UniformValue::Float(_) => UniformType::Float,
+			UniformValue::Int(_) => UniformType::Int,
+			UniformValue::Bool(_) => UniformType::Bool,
+			UniformValue::Vec2(_) => UniformType::Vec2,
+			UniformValue::Vec3(_) => UniformType::Vec3,
+			UniformValue::Vec4(_) => UniformType::Vec4,
+			UniformValue::IVec2(_) => UniformType::IVec2,
+			UniformValue::IVec3(_) => UniformType::IVec3,
+			UniformValue::IVec4(_) => UniformType::IVec4,
+			UniformValue::Mat3(_) => UniformType::Mat3,
+			UniformValue::Mat4(_) => UniformType::Mat4,
+			UniformValue::Sampler2D(_) => UniformType::Sampler2D,
+			UniformValue::SamplerCube(_) => UniformType::SamplerCube,
+			UniformValue::Array(elements) => {
+				let element_type = elements.first()
+					.map(|element| element.get_type())
+					.unwrap_or(UniformType::Float);
+
+				UniformType::Array(Box::new(element_type), elements.len())
+			}
+		}
+	}
+}
+
+impl From<f32> for UniformValue {
+	fn from(value: f32) -> UniformValue {
+		UniformValue::Float(value)
+	}
+}
+
+impl From<i32> for UniformValue {
+	fn from(value: i32) -> UniformValue {
+		UniformValue::Int(value)
+	}
+}
+
+impl From<bool> for UniformValue {
+	fn from(value: bool) -> UniformValue {
+		UniformValue::Bool(value)
+	}
+}
+
+impl From<Vector2<f32>> for UniformValue {
+	fn from(value: Vector2<f32>) -> UniformValue {
+		UniformValue::Vec2(value.into())
+	}
+}
+
+impl From<Vector3<f32>> for UniformValue {
+	fn from(value: Vector3<f32>) -> UniformValue {
+		UniformValue::Vec3(value.into())
+	}
+}
+
+impl From<Vector4<f32>> for UniformValue {
+	fn from(value: Vector4<f32>) -> UniformValue {
+		UniformValue::Vec4(value.into())
+	}
+}
+
+impl From<&Matrix3<f32>> for UniformValue {
+	fn from(value: &Matrix3<f32>) -> UniformValue {
+		let mut matrix = [0.0; 9];
+		matrix.copy_from_slice(value.as_slice());
+
+		UniformValue::Mat3(matrix)
+	}
+}
+
+impl From<&Matrix4<f32>> for UniformValue {
+	fn from(value: &Matrix4<f32>) -> UniformValue {
+		let mut matrix = [0.0; 16];
+		matrix.copy_from_slice(value.as_slice());
+
+		UniformValue::Mat4(matrix)
+	}
+}
+
+/// Something that went wrong setting a uniform
+#[derive(Clone, Debug, PartialEq)]
+pub enum UniformError {
+	/// The shader doesn't declare a uniform with this name
+	Unknown { name: String },
+	/// The value doesn't fit the type the uniform was declared with
+	TypeMismatch { name: String, expected: UniformType, found: UniformType }
+}
+
+impl fmt::Display for UniformError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			UniformError::Unknown { name } => write!(f, "No uniform named {}", name),
+			UniformError::TypeMismatch { name, expected, found } => {
+				write!(f, "Uniform {} is a {}, but got a {}", name, expected, found)
+			}
+		}
+	}
+}
+
+impl std::error::Error for UniformError {}
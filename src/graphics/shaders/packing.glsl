@@ -0,0 +1,12 @@
+// Stores depth from 0 to 1 across the four channels of a color
+// Color targets work everywhere, where depth textures need an extension
+vec4 pack_depth(float depth) {
+	vec4 encoded = fract(depth * vec4(1.0, 255.0, 65025.0, 16581375.0));
+	encoded -= encoded.yzww * vec4(1.0 / 255.0, 1.0 / 255.0, 1.0 / 255.0, 0.0);
+	return encoded;
+}
+
+// Gets back depth stored by pack_depth
+float unpack_depth(vec4 encoded) {
+	return dot(encoded, vec4(1.0, 1.0 / 255.0, 1.0 / 65025.0, 1.0 / 16581375.0));
+}
@@ -0,0 +1,312 @@
+use std::{collections::{BTreeMap, HashMap}, fmt};
+
+/// Preprocessor defines by name, like HAS_NORMAL_MAP or MAX_LIGHTS
+///
+/// Sorted, so the same set of defines always makes the same key.
+pub type Defines = BTreeMap<String, String>;
+
+/// Something that went wrong preprocessing a shader
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreprocessError {
+	/// The file the problem is in, or None for the shader itself
+	pub file: Option<String>,
+	/// The line the problem is on, counted from 1
+	pub line: u32,
+	/// What went wrong
+	pub message: String
+}
+
+impl fmt::Display for PreprocessError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match &self.file {
+			Some(file) => write!(f, "{} line {}: {}", file, self.line, self.message),
+			None => write!(f, "Line {}: {}", self.line, self.message)
+		}
+	}
+}
+
+impl std::error::Error for PreprocessError {}
+
+/// Resolves includes and injects defines into GLSL source
+///
+/// Includes come from a table of virtual files instead of the file system,
+/// since there isn't one in the browser. Each file is only pasted in once per
+/// shader, so files can include each other without guards.
+///
+/// #line directives keep the line numbers the driver reports matching the
+/// file being edited. The shader itself is source string 0, and each included
+/// file gets the next number as it's pasted in, with its name in a comment
+/// after the directive so find_line can report it.
+#[derive(Clone, Debug, Default)]
+pub struct Preprocessor {
+	/// Defines every shader gets, on top of the ones it's processed with
+	defines: Defines,
+	/// Source code of each file that can be included, by name
+	files: HashMap<String, String>
+}
+
+impl Preprocessor {
+	/// Adds a define every shader gets, replacing any with the same name
+	///
+	/// Defines a shader is processed with win over these.
+	///
+	/// * `name` - the name to define, like "MAX_LIGHTS"
+	/// * `value` - what it expands to
+	pub fn add_define(&mut self, name: &str, value: &str) {
+		self.defines.insert(String::from(name), String::from(value));
+	}
+
+	/// Adds a file that shaders can include, replacing any with the same name
+	///
+	/// * `name` - the name to include it by, like "common.glsl"
+	/// * `source` - the source code of the file
+	pub fn add_file(&mut self, name: &str, source: &str) {
+		self.files.insert(String::from(name), String::from(source));
+	}
+
+	/// Gets the source code of an includable file
+	pub fn get_file(&self, name: &str) -> Option<&str> {
+		self.files.get(name).map(|source| source.as_str())
+	}
+
+	/// Creates a preprocessor with no files to include
+	pub fn new() -> Preprocessor {
+		Preprocessor {
+			defines: Defines::new(),
+			files: HashMap::new()
+		}
+	}
+
+	/// Expands a shader's includes and adds defines to the top
+	///
+	/// Defines go after the #version line if there is one, since it has to
+	/// come first. The preprocessor's own defines go in too. A #line directive after them puts the line count back to
+	/// where the shader's own source continues.
+	///
+	/// * `source` - the source code of the shader
+	/// * `defines` - defines to add
+	///
+	/// Returns - the expanded source code, or what went wrong
+	pub fn process(&self, source: &str, defines: &Defines) -> Result<String, PreprocessError> {
+		// Expand includes
+		let mut body = String::new();
+		let mut included = Vec::new();
+		self.expand(source, None, &mut included, &mut body)?;
+
+		// Keep the version first
+		let (version, body) = match body.split_once('\n') {
+			Some((first, rest)) if first.trim_start().starts_with("#version") => (Some(first), rest),
+			_ => (None, body.as_str())
+		};
+
+		let mut output = String::new();
+		if let Some(version) = version {
+			output.push_str(version);
+			output.push('\n');
+		}
+		let mut all_defines = self.defines.clone();
+		all_defines.extend(defines.iter().map(|(name, value)| (name.clone(), value.clone())));
+		for (name, value) in &all_defines {
+			output.push_str(&format!("#define {} {}\n", name, value));
+		}
+		let first_line = if version.is_some() { 2 } else { 1 };
+		output.push_str(&format!("#line {} 0\n", first_line));
+		output.push_str(body);
+
+		Ok(output)
+	}
+
+	/// Pastes a file into the output, expanding its includes as it goes
+	///
+	/// * `source` - the source code of the file
+	/// * `file` - the name of the file, or None for the shader itself
+	/// * `included` - files that have already been pasted in, in order, so
+	///   each one's source string number is its position plus one
+	/// * `output` - where to put the expanded source
+	fn expand(
+		&self,
+		source: &str,
+		file: Option<&str>,
+		included: &mut Vec<String>,
+		output: &mut String
+	) -> Result<(), PreprocessError> {
+		let string_number = match file {
+			Some(file) => included.iter().position(|name| name == file).map_or(0, |index| index + 1),
+			None => 0
+		};
+
+		for (index, line) in source.lines().enumerate() {
+			let error = |message: String| PreprocessError {
+				file: file.map(String::from),
+				line: index as u32 + 1,
+				message
+			};
+
+			// Pass everything but includes straight through
+			let directive = line.trim_start();
+			let rest = match directive.strip_prefix("#include") {
+				Some(rest) => rest.trim(),
+				None => {
+					output.push_str(line);
+					output.push('\n');
+					continue;
+				}
+			};
+
+			// Find the file
+			let name = rest.strip_prefix('"')
+				.and_then(|rest| rest.strip_suffix('"'))
+				.ok_or_else(|| error(format!("Expected #include \"file\", got {}", directive)))?;
+			let included_source = self.files.get(name)
+				.ok_or_else(|| error(format!("No file named {} to include", name)))?;
+
+			// Paste it in once, then carry on counting from the line after the
+			// include
+			if included.iter().any(|file| file == name) {
+				output.push('\n');
+			} else {
+				included.push(String::from(name));
+				output.push_str(&format!("#line 1 {} // {}\n", included.len(), name));
+				self.expand(included_source, Some(name), included, output)?;
+				output.push_str(&format!("#line {} {}\n", index + 2, string_number));
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Finds a line of expanded source by where the driver says it is
+///
+/// Follows the #line directives process adds, so a source string and line
+/// from an info log lead back to the file and the line in it. Source without
+/// any directives is just counted from the top.
+///
+/// * `expanded` - source code that came out of process
+/// * `string_number` - the source string the driver reported
+/// * `line` - the line the driver reported, counted from 1
+///
+/// Returns - the name of the included file the line is in, or None for the
+/// shader itself, and the text of the line
+pub fn find_line(expanded: &str, string_number: u32, line: u32) -> Option<(Option<&str>, &str)> {
+	let mut names = HashMap::new();
+	let (mut current_string, mut current_line) = (0, 1);
+	let mut found = None;
+	for text in expanded.lines() {
+		// Directives set the number of the line after them
+		if let Some(directive) = text.trim_start().strip_prefix("#line") {
+			let (numbers, name) = match directive.split_once("//") {
+				Some((numbers, name)) => (numbers, Some(name.trim())),
+				None => (directive, None)
+			};
+			let mut numbers = numbers.split_whitespace().map(|number| number.parse::<u32>());
+			if let Some(Ok(number)) = numbers.next() {
+				current_line = number;
+			}
+			if let Some(Ok(number)) = numbers.next() {
+				current_string = number;
+			}
+			if let Some(name) = name {
+				names.insert(current_string, name);
+			}
+			continue;
+		}
+
+		// Defines before the first directive can count up to the same lines
+		// as the shader's own source, so the last match wins
+		if current_string == string_number && current_line == line {
+			found = Some(text);
+		}
+		current_line += 1;
+	}
+
+	found.map(|text| (names.get(&string_number).copied(), text))
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::graphics::{backend::ShaderStage, shaders::ShaderError};
+	use super::*;
+
+	#[test]
+	fn pastes_includes_in_with_line_directives() {
+		let mut preprocessor = Preprocessor::new();
+		preprocessor.add_file("a.glsl", "float a;\nfloat b;");
+
+		let output = preprocessor.process("#include \"a.glsl\"\nvoid main() {}", &Defines::new()).unwrap();
+		assert_eq!(output, "#line 1 0\n#line 1 1 // a.glsl\nfloat a;\nfloat b;\n#line 2 0\nvoid main() {}\n");
+	}
+
+	#[test]
+	fn includes_each_file_once() {
+		let mut preprocessor = Preprocessor::new();
+		preprocessor.add_file("a.glsl", "#include \"b.glsl\"\nfloat a;");
+		preprocessor.add_file("b.glsl", "float b;");
+
+		let output = preprocessor.process("#include \"a.glsl\"\n#include \"b.glsl\"\nvoid main() {}", &Defines::new()).unwrap();
+		assert_eq!(output.matches("float b;").count(), 1);
+		assert_eq!(find_line(&output, 0, 3), Some((None, "void main() {}")));
+	}
+
+	#[test]
+	fn reports_missing_includes() {
+		let mut preprocessor = Preprocessor::new();
+		preprocessor.add_file("a.glsl", "float a;\n#include \"missing.glsl\"");
+
+		let error = preprocessor.process("#include \"a.glsl\"", &Defines::new()).unwrap_err();
+		assert_eq!(error, PreprocessError {
+			file: Some(String::from("a.glsl")),
+			line: 2,
+			message: String::from("No file named missing.glsl to include")
+		});
+	}
+
+	#[test]
+	fn injects_defines_after_version() {
+		let mut defines = Defines::new();
+		defines.insert(String::from("MAX_LIGHTS"), String::from("4"));
+
+		let output = Preprocessor::new().process("#version 300 es\nvoid main() {}", &defines).unwrap();
+		assert_eq!(output, "#version 300 es\n#define MAX_LIGHTS 4\n#line 2 0\nvoid main() {}\n");
+	}
+
+	#[test]
+	fn adds_its_own_defines() {
+		let mut preprocessor = Preprocessor::new();
+		preprocessor.add_define("MAX_LIGHTS", "4");
+		preprocessor.add_define("SKINNED", "0");
+
+		let mut defines = Defines::new();
+		defines.insert(String::from("SKINNED"), String::from("1"));
+
+		let output = preprocessor.process("void main() {}", &defines).unwrap();
+		assert_eq!(output, "#define MAX_LIGHTS 4\n#define SKINNED 1\n#line 1 0\nvoid main() {}\n");
+	}
+
+	#[test]
+	fn finds_lines_the_driver_reports() {
+		let mut preprocessor = Preprocessor::new();
+		preprocessor.add_file("a.glsl", "float a;\nfloat b;");
+		let mut defines = Defines::new();
+		defines.insert(String::from("A"), String::from("1"));
+
+		let output = preprocessor.process("#version 100\nfloat c;\n#include \"a.glsl\"\nvoid main() {}", &defines).unwrap();
+		assert_eq!(find_line(&output, 0, 1), Some((None, "#version 100")));
+		assert_eq!(find_line(&output, 0, 2), Some((None, "float c;")));
+		assert_eq!(find_line(&output, 1, 2), Some((Some("a.glsl"), "float b;")));
+		assert_eq!(find_line(&output, 0, 4), Some((None, "void main() {}")));
+		assert_eq!(find_line(&output, 2, 1), None);
+	}
+
+	#[test]
+	fn driver_errors_point_into_the_file_being_edited() {
+		let mut preprocessor = Preprocessor::new();
+		preprocessor.add_file("a.glsl", "float a;\nfloat b = nope;");
+		let output = preprocessor.process("#include \"a.glsl\"\nvoid main() {}", &Defines::new()).unwrap();
+
+		let error = ShaderError::from_log("test", Some(ShaderStage::Fragment), "ERROR: 1:2: 'nope' : undeclared identifier", Some(&output));
+		assert_eq!(error.line, Some(2));
+		assert_eq!(error.message, "In a.glsl: 'nope' : undeclared identifier");
+		assert_eq!(error.source_line.as_deref(), Some("float b = nope;"));
+	}
+}
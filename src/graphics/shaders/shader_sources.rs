@@ -1,25 +1,318 @@
 use std::collections::HashMap;
 
-use super::ShaderSource;
+use crate::graphics::{
+	debug_draw::DEBUG_LINES_SHADER,
+	lighting::MAX_LIGHTS,
+	shadows::{MAX_SPOT_SHADOWS, SHADOW_CASCADES, SHADOW_SHADER},
+	sky::{SKYBOX_SHADER, SKY_GRADIENT_SHADER},
+	vertex_layout::VertexAttribute
+};
+use super::{ShaderSource, UniformType, UniformValue};
+
+/// Gets the defines every shader is built with, by name
+///
+/// Limits shaders share with the renderer come from here, so they're only
+/// written down once.
+pub fn get_shader_defines() -> Vec<(&'static str, String)> {
+	vec![
+		// Size of the light arrays in lit shaders
+		("MAX_LIGHTS", MAX_LIGHTS.to_string()),
+		// How many shadows each shadow map holds side by side
+		("MAX_SPOT_SHADOWS", MAX_SPOT_SHADOWS.to_string()),
+		("SHADOW_CASCADES", SHADOW_CASCADES.to_string())
+	]
+}
+
+/// Gets the files shaders can include, by name
+pub fn get_shader_includes() -> HashMap<&'static str, &'static str> {
+	let mut includes = HashMap::new();
+
+	// Model, view, and projection uniforms
+	includes.insert("common.glsl", include_str!("common.glsl-min"));
+
+	// Depth stored in a color
+	includes.insert("packing.glsl", include_str!("packing.glsl-min"));
+
+	// Shadow map lookups
+	includes.insert("shadows.glsl", include_str!("shadows.glsl-min"));
+
+	includes
+}
 
 pub fn get_shader_sources() -> HashMap<&'static str, ShaderSource<'static>> {
 	let mut sources = HashMap::new();
 
 	// Basic bitch shader
 	sources.insert("Basic bitch", ShaderSource {
+		attributes: vec![("position", VertexAttribute::Position)],
+		instancing: false,
 		vertex_shader: Some(include_str!("here_vert.glsl-min")),
 		fragment_shader: Some(include_str!("orange_frag.glsl-min")),
+		uniform_defaults: HashMap::new(),
 		uniform_names: vec![],
 		uniform_types: vec![]
 	});
 
 	// 3d orange shader
+	// Orange unless a material says otherwise
+	let mut orange_defaults = HashMap::new();
+	orange_defaults.insert("color", UniformValue::Vec4([1.0, 0.6, 0.0, 1.0]));
 	sources.insert("3d orange", ShaderSource {
+		attributes: vec![("position", VertexAttribute::Position)],
+		instancing: true,
 		vertex_shader: Some(include_str!("3d_vert.glsl-min")),
-		fragment_shader: Some(include_str!("orange_frag.glsl-min")),
+		fragment_shader: Some(include_str!("color_frag.glsl-min")),
+		uniform_defaults: orange_defaults,
+		uniform_names: vec!["color"],
+		uniform_types: vec![UniformType::Vec4]
+	});
+
+	// 3d textured shader
+	// Samples albedo, tinted by color
+	let mut textured_defaults = HashMap::new();
+	textured_defaults.insert("color", UniformValue::Vec4([1.0, 1.0, 1.0, 1.0]));
+	sources.insert("3d textured", ShaderSource {
+		attributes: vec![("position", VertexAttribute::Position), ("uv0", VertexAttribute::Uv0)],
+		instancing: true,
+		vertex_shader: Some(include_str!("textured_vert.glsl-min")),
+		fragment_shader: Some(include_str!("texture_frag.glsl-min")),
+		uniform_defaults: textured_defaults,
+		uniform_names: vec!["albedo", "color"],
+		uniform_types: vec![UniformType::Sampler2D, UniformType::Vec4]
+	});
+
+	// 3d lit shader
+	// Blinn-Phong, lit by the nearest lights and shadowed by their shadow maps
+	let mut lit_defaults = HashMap::new();
+	lit_defaults.insert("ambient", UniformValue::Vec3([0.1, 0.1, 0.1]));
+	lit_defaults.insert("color", UniformValue::Vec4([1.0, 0.6, 0.0, 1.0]));
+	lit_defaults.insert("shininess", UniformValue::Float(32.0));
+	lit_defaults.insert("specular", UniformValue::Vec3([0.5, 0.5, 0.5]));
+	let light_array = |element| UniformType::Array(Box::new(element), MAX_LIGHTS);
+	sources.insert("3d lit", ShaderSource {
+		attributes: vec![("position", VertexAttribute::Position), ("normal", VertexAttribute::Normal)],
+		instancing: true,
+		vertex_shader: Some(include_str!("lit_vert.glsl-min")),
+		fragment_shader: Some(include_str!("blinn_phong_frag.glsl-min")),
+		uniform_defaults: lit_defaults,
+		uniform_names: vec![
+			"ambient",
+			"color",
+			"shininess",
+			"specular",
+			"camera_position",
+			"light_count",
+			"light_types",
+			"light_positions",
+			"light_directions",
+			"light_colors",
+			"light_attenuations",
+			"light_cones",
+			"receive_shadows",
+			"shadow_bias",
+			"shadow_map_size",
+			"cascade_light",
+			"cascade_matrices",
+			"cascade_map",
+			"spot_shadow_lights",
+			"spot_shadow_matrices",
+			"spot_shadow_map"
+		],
+		uniform_types: vec![
+			UniformType::Vec3,
+			UniformType::Vec4,
+			UniformType::Float,
+			UniformType::Vec3,
+			UniformType::Vec3,
+			UniformType::Int,
+			light_array(UniformType::Int),
+			light_array(UniformType::Vec3),
+			light_array(UniformType::Vec3),
+			light_array(UniformType::Vec3),
+			light_array(UniformType::Vec3),
+			light_array(UniformType::Vec2),
+			UniformType::Bool,
+			UniformType::Float,
+			UniformType::Float,
+			UniformType::Int,
+			UniformType::Array(Box::new(UniformType::Mat4), SHADOW_CASCADES),
+			UniformType::Sampler2D,
+			UniformType::Array(Box::new(UniformType::Int), MAX_SPOT_SHADOWS),
+			UniformType::Array(Box::new(UniformType::Mat4), MAX_SPOT_SHADOWS),
+			UniformType::Sampler2D
+		]
+	});
+
+	// Shadow depth shader
+	// Draws how far things are from a light into a shadow map
+	sources.insert(SHADOW_SHADER, ShaderSource {
+		attributes: vec![("position", VertexAttribute::Position)],
+		instancing: true,
+		vertex_shader: Some(include_str!("3d_vert.glsl-min")),
+		fragment_shader: Some(include_str!("shadow_depth_frag.glsl-min")),
+		uniform_defaults: HashMap::new(),
 		uniform_names: vec![],
 		uniform_types: vec![]
 	});
 
+	// Debug lines shader
+	// Colored per vertex, for debug drawing
+	sources.insert(DEBUG_LINES_SHADER, ShaderSource {
+		attributes: vec![("position", VertexAttribute::Position), ("color", VertexAttribute::Color)],
+		instancing: false,
+		vertex_shader: Some(include_str!("debug_lines_vert.glsl-min")),
+		fragment_shader: Some(include_str!("debug_lines_frag.glsl-min")),
+		uniform_defaults: HashMap::new(),
+		uniform_names: vec![],
+		uniform_types: vec![]
+	});
+
+	// Render mode shaders
+	// Stand in for every material when looking for bugs
+	sources.insert("debug normals", ShaderSource {
+		attributes: vec![("position", VertexAttribute::Position), ("normal", VertexAttribute::Normal)],
+		instancing: false,
+		vertex_shader: Some(include_str!("lit_vert.glsl-min")),
+		fragment_shader: Some(include_str!("debug_normals_frag.glsl-min")),
+		uniform_defaults: HashMap::new(),
+		uniform_names: vec![],
+		uniform_types: vec![]
+	});
+	let mut checker_defaults = HashMap::new();
+	checker_defaults.insert("checker_scale", UniformValue::Float(8.0));
+	sources.insert("debug uv checker", ShaderSource {
+		attributes: vec![("position", VertexAttribute::Position), ("uv0", VertexAttribute::Uv0)],
+		instancing: false,
+		vertex_shader: Some(include_str!("textured_vert.glsl-min")),
+		fragment_shader: Some(include_str!("debug_uv_checker_frag.glsl-min")),
+		uniform_defaults: checker_defaults,
+		uniform_names: vec!["checker_scale"],
+		uniform_types: vec![UniformType::Float]
+	});
+	sources.insert("debug depth", ShaderSource {
+		attributes: vec![("position", VertexAttribute::Position)],
+		instancing: false,
+		vertex_shader: Some(include_str!("debug_depth_vert.glsl-min")),
+		fragment_shader: Some(include_str!("debug_depth_frag.glsl-min")),
+		uniform_defaults: HashMap::new(),
+		uniform_names: vec!["depth_range"],
+		uniform_types: vec![UniformType::Vec2]
+	});
+
+	// Sky shaders
+	// Cover the screen at the far plane, behind everything drawn before them
+	sources.insert(SKYBOX_SHADER, ShaderSource {
+		attributes: vec![("position", VertexAttribute::Position)],
+		instancing: false,
+		vertex_shader: Some(include_str!("sky_vert.glsl-min")),
+		fragment_shader: Some(include_str!("skybox_frag.glsl-min")),
+		uniform_defaults: HashMap::new(),
+		uniform_names: vec!["sky", "sky_matrix"],
+		uniform_types: vec![UniformType::SamplerCube, UniformType::Mat4]
+	});
+	sources.insert(SKY_GRADIENT_SHADER, ShaderSource {
+		attributes: vec![("position", VertexAttribute::Position)],
+		instancing: false,
+		vertex_shader: Some(include_str!("sky_vert.glsl-min")),
+		fragment_shader: Some(include_str!("sky_gradient_frag.glsl-min")),
+		uniform_defaults: HashMap::new(),
+		uniform_names: vec!["bottom", "top", "sky_matrix"],
+		uniform_types: vec![UniformType::Vec3, UniformType::Vec3, UniformType::Mat4]
+	});
+
+	// Post-processing shaders
+	// Each covers the screen with one triangle and reads what was rendered
+	// before it from source
+	let post_source = |fragment_shader, defaults: Vec<(&'static str, UniformValue)>, mut names: Vec<&'static str>, mut types: Vec<UniformType>| {
+		names.extend(["source", "texel_size"]);
+		types.extend([UniformType::Sampler2D, UniformType::Vec2]);
+
+		ShaderSource {
+			attributes: vec![("position", VertexAttribute::Position)],
+			instancing: false,
+			vertex_shader: Some(include_str!("fullscreen_vert.glsl-min")),
+			fragment_shader: Some(fragment_shader),
+			uniform_defaults: defaults.into_iter().collect(),
+			uniform_names: names,
+			uniform_types: types
+		}
+	};
+
+	// Tonemapping
+	// Curve is 0 for Reinhard and 1 for ACES filmic
+	sources.insert("post tonemap", post_source(
+		include_str!("tonemap_frag.glsl-min"),
+		vec![("curve", UniformValue::Float(1.0)), ("exposure", UniformValue::Float(1.0))],
+		vec!["curve", "exposure"],
+		vec![UniformType::Float, UniformType::Float]
+	));
+
+	// Gamma correction
+	sources.insert("post gamma", post_source(
+		include_str!("gamma_frag.glsl-min"),
+		vec![("gamma", UniformValue::Float(2.2))],
+		vec!["gamma"],
+		vec![UniformType::Float]
+	));
+
+	// FXAA
+	sources.insert("post fxaa", post_source(
+		include_str!("fxaa_frag.glsl-min"),
+		vec![
+			("reduce_min", UniformValue::Float(1.0 / 128.0)),
+			("reduce_multiplier", UniformValue::Float(1.0 / 8.0)),
+			("span_max", UniformValue::Float(8.0))
+		],
+		vec!["reduce_min", "reduce_multiplier", "span_max"],
+		vec![UniformType::Float, UniformType::Float, UniformType::Float]
+	));
+
+	// Vignette
+	sources.insert("post vignette", post_source(
+		include_str!("vignette_frag.glsl-min"),
+		vec![
+			("intensity", UniformValue::Float(0.5)),
+			("radius", UniformValue::Float(1.0)),
+			("softness", UniformValue::Float(0.6))
+		],
+		vec!["intensity", "radius", "softness"],
+		vec![UniformType::Float, UniformType::Float, UniformType::Float]
+	));
+
+	// Color grading through a LUT
+	sources.insert("post color grading", post_source(
+		include_str!("color_grade_frag.glsl-min"),
+		vec![("intensity", UniformValue::Float(1.0)), ("lut_size", UniformValue::Float(16.0))],
+		vec!["lut", "intensity", "lut_size"],
+		vec![UniformType::Sampler2D, UniformType::Float, UniformType::Float]
+	));
+
+	// Bloom
+	// Picks out the bright parts, blurs them, and adds them back on top
+	// The extract and add passes share parameters so one material drives both
+	let bloom_defaults = || vec![
+		("intensity", UniformValue::Float(1.0)),
+		("knee", UniformValue::Float(0.5)),
+		("threshold", UniformValue::Float(0.8))
+	];
+	sources.insert("post bloom extract", post_source(
+		include_str!("bloom_extract_frag.glsl-min"),
+		bloom_defaults(),
+		vec!["intensity", "knee", "threshold"],
+		vec![UniformType::Float, UniformType::Float, UniformType::Float]
+	));
+	sources.insert("post blur", post_source(
+		include_str!("blur_frag.glsl-min"),
+		vec![("direction", UniformValue::Vec2([1.0, 0.0]))],
+		vec!["direction"],
+		vec![UniformType::Vec2]
+	));
+	sources.insert("post bloom", post_source(
+		include_str!("bloom_frag.glsl-min"),
+		bloom_defaults(),
+		vec!["bloom", "intensity", "knee", "threshold"],
+		vec![UniformType::Sampler2D, UniformType::Float, UniformType::Float, UniformType::Float]
+	));
+
 	sources
 }
@@ -0,0 +1,8 @@
+precision mediump float;
+
+#include "packing.glsl"
+
+// Start shader
+void main() {
+	gl_FragColor = pack_depth(gl_FragCoord.z);
+}
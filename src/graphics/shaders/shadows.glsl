@@ -0,0 +1,79 @@
+// Shadow map lookups for lit shaders
+#include "packing.glsl"
+
+// Shadow uniforms
+// The engine defines SHADOW_CASCADES and MAX_SPOT_SHADOWS, from shadows.rs
+// Lights are positions in the light arrays, or -1 for none
+// Each map holds its shadows side by side
+uniform bool receive_shadows;
+uniform float shadow_bias;
+uniform float shadow_map_size;
+uniform int cascade_light;
+uniform mat4 cascade_matrices[SHADOW_CASCADES];
+uniform sampler2D cascade_map;
+uniform int spot_shadow_lights[MAX_SPOT_SHADOWS];
+uniform mat4 spot_shadow_matrices[MAX_SPOT_SHADOWS];
+uniform sampler2D spot_shadow_map;
+
+// Moves a world position into a shadow's space, from 0 to 1 on each side
+vec3 shadow_coords(mat4 matrix, vec3 position) {
+	vec4 projected = matrix * vec4(position, 1.0);
+	return projected.xyz / projected.w * 0.5 + 0.5;
+}
+
+// Whether shadow coordinates land inside the shadow
+bool in_shadow_map(vec3 coords) {
+	return all(greaterThanEqual(coords, vec3(0.0))) && all(lessThanEqual(coords, vec3(1.0)));
+}
+
+// Depth tests a 3x3 block of texels around a point in one tile of a map
+// Returns how much of the block is lit, from 0 to 1
+float sample_shadow(sampler2D map, float tile, float tiles, vec3 coords) {
+	vec2 texel = vec2(1.0 / (shadow_map_size * tiles), 1.0 / shadow_map_size);
+
+	// Keep samples from reaching into the tiles next door
+	vec2 lowest = vec2(tile / tiles, 0.0) + texel * 0.5;
+	vec2 highest = vec2((tile + 1.0) / tiles, 1.0) - texel * 0.5;
+	vec2 center = vec2((tile + coords.x) / tiles, coords.y);
+
+	float lit = 0.0;
+	for (int x = -1; x <= 1; x++) {
+		for (int y = -1; y <= 1; y++) {
+			vec2 uv = clamp(center + vec2(float(x), float(y)) * texel, lowest, highest);
+			float closest = unpack_depth(texture2D(map, uv));
+			lit += coords.z - shadow_bias > closest ? 0.0 : 1.0;
+		}
+	}
+
+	return lit / 9.0;
+}
+
+// How much of a light reaches a world position, from 0 in shadow to 1 lit
+float shadow(int light, vec3 position) {
+	if (!receive_shadows) {
+		return 1.0;
+	}
+
+	// Use the sharpest cascade that covers the position
+	if (light == cascade_light) {
+		for (int i = 0; i < SHADOW_CASCADES; i++) {
+			vec3 coords = shadow_coords(cascade_matrices[i], position);
+			if (in_shadow_map(coords)) {
+				return sample_shadow(cascade_map, float(i), float(SHADOW_CASCADES), coords);
+			}
+		}
+
+		return 1.0;
+	}
+
+	for (int i = 0; i < MAX_SPOT_SHADOWS; i++) {
+		if (light == spot_shadow_lights[i]) {
+			vec3 coords = shadow_coords(spot_shadow_matrices[i], position);
+			if (in_shadow_map(coords)) {
+				return sample_shadow(spot_shadow_map, float(i), float(MAX_SPOT_SHADOWS), coords);
+			}
+		}
+	}
+
+	return 1.0;
+}
@@ -0,0 +1,16 @@
+precision mediump float;
+
+// Inputs
+varying vec4 far_point;
+
+// Uniforms
+uniform vec3 bottom;
+uniform vec3 top;
+
+// Start shader
+void main() {
+	vec3 direction = normalize(far_point.xyz / far_point.w);
+
+	// Halfway between the two at the horizon
+	gl_FragColor = vec4(mix(bottom, top, direction.y * 0.5 + 0.5), 1.0);
+}
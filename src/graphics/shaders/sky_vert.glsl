@@ -0,0 +1,17 @@
+// Attributes
+// One triangle big enough to cover the whole screen
+attribute vec4 position;
+
+// Uniforms
+// Clip space back to world space, turning with the camera but not moving
+uniform mat4 sky_matrix;
+
+// Outputs
+varying vec4 far_point;
+
+// Start shader
+void main() {
+	// Right on the far plane, so anything drawn this frame is in front
+	gl_Position = vec4(position.xy, 1.0, 1.0);
+	far_point = sky_matrix * gl_Position;
+}
@@ -0,0 +1,15 @@
+precision mediump float;
+
+// Inputs
+varying vec4 far_point;
+
+// Uniforms
+uniform samplerCube sky;
+
+// Start shader
+void main() {
+	// Divided here rather than per vertex, so it stays right across the screen
+	vec3 direction = far_point.xyz / far_point.w;
+
+	gl_FragColor = textureCube(sky, direction);
+}
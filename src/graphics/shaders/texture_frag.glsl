@@ -0,0 +1,13 @@
+precision mediump float;
+
+// Inputs
+varying vec2 uv;
+
+// Uniforms
+uniform sampler2D albedo;
+uniform vec4 color;
+
+// Start shader
+void main() {
+	gl_FragColor = texture2D(albedo, uv) * color;
+}
@@ -0,0 +1,17 @@
+// Attributes
+attribute vec4 position;
+attribute vec2 uv0;
+
+// Uniforms
+#include "common.glsl"
+
+// Outputs
+varying vec2 uv;
+
+// Start shader
+void main() {
+	mat4 mvp_matrix = projection * view * model;
+
+	uv = uv0;
+	gl_Position = mvp_matrix * position;
+}
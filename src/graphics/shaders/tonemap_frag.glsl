@@ -0,0 +1,24 @@
+precision mediump float;
+
+// Inputs
+varying vec2 uv;
+
+// Uniforms
+// Curve is 0 for Reinhard and 1 for ACES filmic
+uniform sampler2D source;
+uniform float curve;
+uniform float exposure;
+
+// Fitted ACES filmic curve
+vec3 aces(vec3 color) {
+	return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+}
+
+// Start shader
+void main() {
+	vec4 color = texture2D(source, uv);
+	vec3 exposed = color.rgb * exposure;
+
+	vec3 mapped = curve < 0.5 ? exposed / (exposed + 1.0) : aces(exposed);
+	gl_FragColor = vec4(mapped, color.a);
+}
@@ -0,0 +1,21 @@
+precision mediump float;
+
+// Inputs
+varying vec2 uv;
+
+// Uniforms
+uniform sampler2D source;
+uniform float intensity;
+uniform float radius;
+uniform float softness;
+
+// Start shader
+void main() {
+	vec4 color = texture2D(source, uv);
+
+	// 0 in the middle of the screen, 1 in the corners
+	float distance_out = distance(uv, vec2(0.5)) * 1.41421356;
+	float darkness = smoothstep(radius - softness, radius, distance_out);
+
+	gl_FragColor = vec4(color.rgb * (1.0 - darkness * intensity), color.a);
+}
@@ -0,0 +1,302 @@
+use std::f32::consts::PI;
+
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+
+use crate::logic::light::{Light, LightKind};
+use super::{
+	backend::{RenderBackend, TextureHandle, TextureTarget},
+	camera::Camera,
+	shaders::{CompiledShader, UniformError, UniformValue}
+};
+
+/// How many slices of the view a directional light's shadows are split into
+///
+/// Lit shaders get it as the SHADOW_CASCADES define.
+pub const SHADOW_CASCADES: usize = 3;
+
+/// The most spot lights that cast shadows at once
+///
+/// Lit shaders get it as the MAX_SPOT_SHADOWS define.
+pub const MAX_SPOT_SHADOWS: usize = 2;
+
+/// The render target cascades get drawn into, side by side
+pub const CASCADE_TARGET: &str = "shadow cascades";
+
+/// The render target spot light shadows get drawn into, side by side
+pub const SPOT_TARGET: &str = "shadow spots";
+
+/// The shader that draws depth into shadow maps
+pub const SHADOW_SHADER: &str = "shadow depth";
+
+/// How close to a spot light things can cast shadows
+const SPOT_NEAR: f32 = 0.1;
+
+/// How shadows get drawn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadowSettings {
+	/// How much closer to the light something has to be to shadow a surface,
+	/// which keeps surfaces from shadowing themselves
+	pub bias: f32,
+	/// How far from the camera directional shadows reach, and how far spot
+	/// light shadows reach from the light
+	pub distance: f32,
+	/// Whether shadows get drawn at all
+	pub enabled: bool,
+	/// Width and height in pixels of each cascade and spot light shadow
+	pub map_size: u32,
+	/// How cascades are spread out, from 0 for evenly to 1 for bunched up
+	/// near the camera
+	pub split_lambda: f32
+}
+
+impl ShadowSettings {
+	/// Gets these settings with maps shrunk, if they have to be, so the
+	/// widest shadow atlas still fits in a texture
+	///
+	/// * `max_texture_size` - the widest texture the backend can make
+	pub fn fit_to(&self, max_texture_size: u32) -> ShadowSettings {
+		let tiles = SHADOW_CASCADES.max(MAX_SPOT_SHADOWS) as u32;
+		ShadowSettings {
+			map_size: self.map_size.min(max_texture_size / tiles).max(1),
+			..*self
+		}
+	}
+}
+
+impl Default for ShadowSettings {
+	/// 1024 pixel maps reaching 50 units, mostly spent near the camera
+	fn default() -> ShadowSettings {
+		ShadowSettings {
+			bias: 0.001,
+			distance: 50.0,
+			enabled: true,
+			map_size: 1024,
+			split_lambda: 0.75
+		}
+	}
+}
+
+/// The shadow maps for one frame, and how lit shaders look things up in them
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShadowFrame {
+	/// Copied from the settings
+	pub bias: f32,
+	/// The directional light with cascades, if any
+	pub cascade_light: Option<Light>,
+	/// Where the cascades got drawn, once they have been
+	pub cascade_map: Option<TextureHandle>,
+	/// World to shadow map space for each cascade, nearest first
+	pub cascade_matrices: Vec<Matrix4<f32>>,
+	/// Copied from the settings
+	pub map_size: u32,
+	/// Spot lights with shadows, and world to shadow map space for each
+	pub spot_lights: Vec<(Light, Matrix4<f32>)>,
+	/// Where the spot light shadows got drawn, once they have been
+	pub spot_map: Option<TextureHandle>
+}
+
+/// Works out which lights get shadows this frame and where they look
+///
+/// The first directional light that casts shadows gets cascades fitted
+/// around the camera's view, and the first few spot lights that cast shadows
+/// get a map each.
+///
+/// * `lights` - every light in the world
+/// * `camera` - the camera the main view is drawn from
+/// * `settings` - how shadows get drawn
+///
+/// Returns - what to draw, with no maps drawn yet
+pub fn plan_shadows(lights: &[Light], camera: &Camera, settings: &ShadowSettings) -> ShadowFrame {
+	let mut frame = ShadowFrame {
+		bias: settings.bias,
+		map_size: settings.map_size,
+		..ShadowFrame::default()
+	};
+	if !settings.enabled {
+		return frame;
+	}
+
+	let casters = lights.iter().filter(|light| light.casts_shadows());
+	for light in casters {
+		match *light.get_kind() {
+			LightKind::Directional { direction } if frame.cascade_light.is_none() => {
+				frame.cascade_light = Some(*light);
+				frame.cascade_matrices = cascade_matrices(camera, &direction, settings);
+			},
+			LightKind::Spot { direction, outer_angle, .. } if frame.spot_lights.len() < MAX_SPOT_SHADOWS => {
+				let position = Point3::from(*light.get_position());
+				let view = Matrix4::look_at_rh(&position, &(position + direction), &light_up(&direction));
+
+				// Leave a little room around the cone for filtering
+				let fov = (outer_angle * 2.2).min(PI * 0.9);
+				let projection = Matrix4::new_perspective(1.0, fov, SPOT_NEAR, settings.distance.max(SPOT_NEAR * 2.0));
+				frame.spot_lights.push((*light, projection * view));
+			},
+			_ => ()
+		}
+	}
+
+	frame
+}
+
+/// Uploads shadow maps to a lit shader
+///
+/// Shaders without a cascade_light uniform don't take shadows and are left
+/// alone.
+///
+/// * `gl` - the backend to render with
+/// * `shader` - the shader being rendered with, already in use
+/// * `frame` - the shadows drawn this frame
+/// * `lights` - the lights uploaded to the shader, in the same order
+/// * `receive` - whether the object being drawn takes shadows
+/// * `first_unit` - the first texture unit the material isn't using
+/// * `errors` - where to put any problems setting the uniforms
+pub fn set_shadow_uniforms<B: RenderBackend>(
+	gl: &mut B,
+	shader: &CompiledShader,
+	frame: &ShadowFrame,
+	lights: &[&Light],
+	receive: bool,
+	first_unit: u32,
+	errors: &mut Vec<UniformError>
+) {
+	if !shader.uniforms.contains_key("cascade_light") {
+		return;
+	}
+
+	// Lights are found by their position in the shader's light arrays
+	let light_index = |light: &Light| lights.iter()
+		.position(|uploaded| **uploaded == *light)
+		.map_or(-1, |index| index as i32);
+
+	let mut values = vec![
+		("receive_shadows", UniformValue::Bool(receive)),
+		("shadow_bias", UniformValue::Float(frame.bias)),
+		("shadow_map_size", UniformValue::Float(frame.map_size as f32))
+	];
+
+	// Cascades
+	let cascade_light = match (&frame.cascade_light, frame.cascade_map) {
+		(Some(light), Some(map)) => {
+			gl.bind_texture(first_unit, TextureTarget::Texture2D, Some(map));
+			values.push(("cascade_map", UniformValue::Sampler2D(first_unit as i32)));
+			values.push((
+				"cascade_matrices",
+				UniformValue::Array(frame.cascade_matrices.iter().map(UniformValue::from).collect())
+			));
+			light_index(light)
+		},
+		_ => -1
+	};
+	values.push(("cascade_light", UniformValue::Int(cascade_light)));
+
+	// Spot lights, with every slot filled so none are left pointing at a light
+	let spot_unit = first_unit + 1;
+	let mut spot_lights = vec![UniformValue::Int(-1); MAX_SPOT_SHADOWS];
+	if let Some(map) = frame.spot_map {
+		gl.bind_texture(spot_unit, TextureTarget::Texture2D, Some(map));
+		values.push(("spot_shadow_map", UniformValue::Sampler2D(spot_unit as i32)));
+		values.push((
+			"spot_shadow_matrices",
+			UniformValue::Array(frame.spot_lights.iter().map(|(_, matrix)| UniformValue::from(matrix)).collect())
+		));
+		for (slot, (light, _)) in frame.spot_lights.iter().enumerate() {
+			spot_lights[slot] = UniformValue::Int(light_index(light));
+		}
+	}
+	values.push(("spot_shadow_lights", UniformValue::Array(spot_lights)));
+
+	// Upload
+	for (name, value) in &values {
+		if let Err(error) = shader.set_uniform(gl, name, value) {
+			errors.push(error);
+		}
+	}
+}
+
+/// Fits a cascade around each slice of the camera's view
+///
+/// Slices are split between evenly and logarithmically by split_lambda, so
+/// the ones near the camera are small and sharp.
+///
+/// * `camera` - the camera the main view is drawn from
+/// * `direction` - the direction the light travels in
+/// * `settings` - how shadows get drawn
+fn cascade_matrices(camera: &Camera, direction: &Vector3<f32>, settings: &ShadowSettings) -> Vec<Matrix4<f32>> {
+	let (near, far) = camera.get_projection().get_clip_planes();
+	let far = far.min(settings.distance).max(near * 2.0);
+
+	let mut splits = vec![near];
+	for cascade in 1..=SHADOW_CASCADES {
+		let fraction = cascade as f32 / SHADOW_CASCADES as f32;
+		let logarithmic = near * (far / near).powf(fraction);
+		let even = near + (far - near) * fraction;
+		splits.push(settings.split_lambda * logarithmic + (1.0 - settings.split_lambda) * even);
+	}
+
+	splits.windows(2)
+		.map(|slice| cascade_matrix(camera, direction, slice[0], slice[1], settings))
+		.collect()
+}
+
+/// Fits one cascade around a slice of the camera's view
+///
+/// * `camera` - the camera the main view is drawn from
+/// * `direction` - the direction the light travels in
+/// * `near` - distance from the camera to the start of the slice
+/// * `far` - distance from the camera to the end of the slice
+/// * `settings` - how shadows get drawn
+fn cascade_matrix(
+	camera: &Camera,
+	direction: &Vector3<f32>,
+	near: f32,
+	far: f32,
+	settings: &ShadowSettings
+) -> Matrix4<f32> {
+	let projection = camera.get_projection();
+	let camera_to_world = camera.get_view_matrix().try_inverse().unwrap_or_else(Matrix4::identity);
+	let tan_y = (projection.get_fov_y() / 2.0).tan();
+	let tan_x = tan_y * projection.get_aspect_ratio();
+
+	// Corners of the slice in world space
+	let mut corners = Vec::with_capacity(8);
+	for depth in [near, far] {
+		for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+			let corner = Point3::new(x * tan_x * depth, y * tan_y * depth, -depth);
+			corners.push(camera_to_world.transform_point(&corner));
+		}
+	}
+
+	// Fit a sphere around them, so the cascade stays the same size as the
+	// camera turns
+	let center = corners.iter().fold(Vector3::zeros(), |sum, corner| sum + corner.coords) / corners.len() as f32;
+	let radius = corners.iter().map(|corner| (corner.coords - center).norm()).fold(0.0, f32::max);
+	let radius = (radius * 16.0).ceil() / 16.0;
+
+	// Look along the light, reaching back far enough to catch things outside
+	// the view that shadow things inside it
+	let center = Point3::from(center);
+	let view = Matrix4::look_at_rh(&center, &(center + direction), &light_up(direction));
+	let projection = Matrix4::new_orthographic(-radius, radius, -radius, radius, -radius - settings.distance, radius);
+	let mut matrix = projection * view;
+
+	// Snap to whole texels so shadow edges don't crawl as the camera moves
+	let origin = matrix * Vector4::new(0.0, 0.0, 0.0, 1.0);
+	let texels = settings.map_size as f32 / 2.0;
+	matrix[(0, 3)] += ((origin.x * texels).round() - origin.x * texels) / texels;
+	matrix[(1, 3)] += ((origin.y * texels).round() - origin.y * texels) / texels;
+
+	matrix
+}
+
+/// Picks an up vector to look along a light with
+///
+/// * `direction` - the direction the light travels in
+fn light_up(direction: &Vector3<f32>) -> Vector3<f32> {
+	// Straight up or down would make world up useless
+	if direction.normalize().y.abs() > 0.99 {
+		Vector3::z()
+	} else {
+		Vector3::y()
+	}
+}
@@ -0,0 +1,85 @@
+use nalgebra::Matrix4;
+
+use super::{material::Material, shaders::UniformValue};
+
+/// The shader a skybox is drawn with
+pub const SKYBOX_SHADER: &str = "sky box";
+
+/// The shader a gradient background is drawn with
+pub const SKY_GRADIENT_SHADER: &str = "sky gradient";
+
+/// What gets drawn behind everything else
+#[derive(Clone, Debug, PartialEq)]
+pub enum Background {
+	/// One rgb color, cleared to
+	Color([f32; 3]),
+	/// Blends from one rgb color looking straight down to another looking
+	/// straight up, turning with the camera
+	Gradient {
+		bottom: [f32; 3],
+		top: [f32; 3]
+	},
+	/// A cube map seen in every direction, by the name it was uploaded with
+	Skybox(String)
+}
+
+impl Background {
+	/// Gets the color to clear to before anything gets drawn
+	pub fn get_clear_color(&self) -> [f32; 3] {
+		match self {
+			Background::Color(color) => *color,
+			Background::Gradient { bottom, top } => [
+				(bottom[0] + top[0]) / 2.0,
+				(bottom[1] + top[1]) / 2.0,
+				(bottom[2] + top[2]) / 2.0
+			],
+			Background::Skybox(_) => [0.0, 0.0, 0.0]
+		}
+	}
+
+	/// Gets the material the sky gets drawn with
+	///
+	/// Returns - None if clearing is all it takes
+	pub fn get_material(&self) -> Option<Material> {
+		match self {
+			Background::Color(_) => None,
+			Background::Gradient { bottom, top } => {
+				let mut material = Material::new(SKY_GRADIENT_SHADER);
+				material.set_uniform("bottom", UniformValue::Vec3(*bottom));
+				material.set_uniform("top", UniformValue::Vec3(*top));
+
+				Some(material)
+			},
+			Background::Skybox(texture_name) => {
+				let mut material = Material::new(SKYBOX_SHADER);
+				material.set_texture("sky", texture_name);
+
+				Some(material)
+			}
+		}
+	}
+}
+
+impl Default for Background {
+	/// Black, like an empty canvas
+	fn default() -> Background {
+		Background::Color([0.0, 0.0, 0.0])
+	}
+}
+
+/// Makes the matrix that turns points on the far plane into the directions
+/// they're seen in
+///
+/// Only the camera's rotation counts, so the sky looks infinitely far away
+/// wherever the camera goes.
+///
+/// * `view_matrix` - the view matrix of the camera drawing the sky
+/// * `projection_matrix` - the projection matrix of the camera drawing the sky
+pub fn sky_matrix(view_matrix: &Matrix4<f32>, projection_matrix: &Matrix4<f32>) -> Matrix4<f32> {
+	let mut rotation = *view_matrix;
+	for row in 0..3 {
+		rotation[(row, 3)] = 0.0;
+	}
+
+	(projection_matrix * rotation).try_inverse().unwrap_or_else(Matrix4::identity)
+}
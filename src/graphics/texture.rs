@@ -0,0 +1,645 @@
+use std::{f32::consts::PI, fmt};
+
+use nalgebra::Vector3;
+
+use super::backend::{CubeFace, RenderBackend, TextureHandle, TextureTarget};
+
+/// An RGBA image with 8 bits per channel
+///
+/// Rows go from the top of the image to the bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+	/// Height in pixels
+	pub height: u32,
+	/// Pixel data, 4 bytes per pixel
+	pub pixels: Vec<u8>,
+	/// Width in pixels
+	pub width: u32
+}
+
+impl Image {
+	/// Checks there are pixels, and exactly as many bytes of them as the size
+	/// needs
+	///
+	/// Returns - why the pixels can't be used, if they can't
+	pub fn check_pixels(&self) -> Result<(), TextureError> {
+		if self.width == 0 || self.height == 0 {
+			return Err(TextureError::EmptyImage);
+		}
+
+		let expected = self.width as usize * self.height as usize * 4;
+		if self.pixels.len() != expected {
+			return Err(TextureError::WrongPixelCount { expected, actual: self.pixels.len() });
+		}
+
+		Ok(())
+	}
+
+	/// Resamples a panorama into the six faces of a cube map
+	///
+	/// The panorama wraps all the way around, with straight up along the top
+	/// row and -z down the middle column.
+	///
+	/// * `size` - width and height of each face in pixels
+	///
+	/// Returns - the faces, in the order of CubeFace::ALL
+	pub fn equirectangular_to_cube(&self, size: u32) -> [Image; 6] {
+		let size = size.max(1);
+		CubeFace::ALL.map(|face| {
+			let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+			for y in 0..size {
+				for x in 0..size {
+					// Find which way this pixel faces, through its center
+					let s = (x as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+					let t = (y as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+					pixels.extend_from_slice(&sample_equirectangular(self, &cube_direction(face, s, t)));
+				}
+			}
+
+			Image { height: size, pixels, width: size }
+		})
+	}
+
+	/// Whether both sides are a power of two
+	pub fn is_power_of_two(&self) -> bool {
+		self.width.is_power_of_two() && self.height.is_power_of_two()
+	}
+
+	/// Gets the color of a pixel
+	///
+	/// * `x` - column from the left
+	/// * `y` - row from the top
+	pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+		let start = ((y * self.width + x) * 4) as usize;
+		let mut pixel = [0; 4];
+		pixel.copy_from_slice(&self.pixels[start..start + 4]);
+
+		pixel
+	}
+
+	/// Resamples this image up to the next power of two on each side
+	///
+	/// Bilinear filtered, so it still looks right once it gets minified.
+	///
+	/// Returns - the resized image, a copy if it was already a power of two,
+	/// or an error if there's nothing to resize
+	pub fn resized_to_power_of_two(&self) -> Result<Image, TextureError> {
+		self.check_pixels()?;
+		let width = self.width.next_power_of_two();
+		let height = self.height.next_power_of_two();
+		if width == self.width && height == self.height {
+			return Ok(self.clone());
+		}
+
+		let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+		for y in 0..height {
+			// Find where this row falls in the source, in pixel centers
+			let source_y = ((y as f32 + 0.5) * self.height as f32 / height as f32 - 0.5).max(0.0);
+			let y0 = (source_y.floor() as u32).min(self.height - 1);
+			let y1 = (y0 + 1).min(self.height - 1);
+			let ty = source_y - y0 as f32;
+
+			for x in 0..width {
+				let source_x = ((x as f32 + 0.5) * self.width as f32 / width as f32 - 0.5).max(0.0);
+				let x0 = (source_x.floor() as u32).min(self.width - 1);
+				let x1 = (x0 + 1).min(self.width - 1);
+				let tx = source_x - x0 as f32;
+
+				// Blend the four closest source pixels
+				let (a, b) = (self.pixel(x0, y0), self.pixel(x1, y0));
+				let (c, d) = (self.pixel(x0, y1), self.pixel(x1, y1));
+				for channel in 0..4 {
+					let top = a[channel] as f32 * (1.0 - tx) + b[channel] as f32 * tx;
+					let bottom = c[channel] as f32 * (1.0 - tx) + d[channel] as f32 * tx;
+					pixels.push((top * (1.0 - ty) + bottom * ty).round() as u8);
+				}
+			}
+		}
+
+		Ok(Image { height, pixels, width })
+	}
+}
+
+/// Something that went wrong decoding an image
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextureError {
+	/// The bytes aren't a PNG or a JPEG
+	UnknownFormat,
+	/// The PNG decoder gave up
+	Png(String),
+	/// The JPEG decoder gave up
+	Jpeg(String),
+	/// The image decoded to pixels that can't be turned into RGBA
+	UnsupportedPixelFormat(String),
+	/// The faces of a cube map aren't all squares of the same size
+	UnevenCubeFaces,
+	/// The image is 0 pixels wide or tall
+	EmptyImage,
+	/// The pixel data is a different length than the size needs
+	WrongPixelCount { expected: usize, actual: usize }
+}
+
+impl fmt::Display for TextureError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			TextureError::UnknownFormat => write!(f, "Image is not a PNG or JPEG"),
+			TextureError::Png(message) => write!(f, "Could not decode PNG: {}", message),
+			TextureError::Jpeg(message) => write!(f, "Could not decode JPEG: {}", message),
+			TextureError::UnsupportedPixelFormat(format) => write!(f, "Unsupported pixel format {}", format),
+			TextureError::UnevenCubeFaces => write!(f, "Cube map faces must all be squares of the same size"),
+			TextureError::EmptyImage => write!(f, "Image has no pixels"),
+			TextureError::WrongPixelCount { expected, actual } => {
+				write!(f, "Image needs {} bytes of pixels but has {}", expected, actual)
+			}
+		}
+	}
+}
+
+impl std::error::Error for TextureError {}
+
+/// How texture coordinates outside of 0-1 are handled
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WrapMode {
+	Repeat,
+	ClampToEdge,
+	MirroredRepeat
+}
+
+/// How texels are blended when sampled
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FilterMode {
+	Nearest,
+	Linear
+}
+
+/// What each pixel of a texture stores
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TextureFormat {
+	/// 8 bit red, green, blue, and alpha
+	Rgba8,
+	/// Depth, for rendering into
+	Depth
+}
+
+/// How a texture gets sampled
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureOptions {
+	/// Filter used when the texture is drawn bigger than it is
+	pub mag_filter: FilterMode,
+	/// Filter used when the texture is drawn smaller than it is
+	pub min_filter: FilterMode,
+	/// Whether to generate and sample mipmaps
+	pub mipmaps: bool,
+	/// Wrapping across the texture
+	pub wrap_s: WrapMode,
+	/// Wrapping down the texture
+	pub wrap_t: WrapMode
+}
+
+impl TextureOptions {
+	/// Smooth, clamped and mipmapped, for cube maps
+	///
+	/// Clamping keeps the edge of one face from bleeding into the other side
+	/// of it.
+	pub fn cube_map() -> TextureOptions {
+		TextureOptions {
+			wrap_s: WrapMode::ClampToEdge,
+			wrap_t: WrapMode::ClampToEdge,
+			..TextureOptions::default()
+		}
+	}
+
+	/// Whether these options need a power of two texture on WebGL1
+	pub fn needs_power_of_two(&self) -> bool {
+		self.mipmaps || self.wrap_s != WrapMode::ClampToEdge || self.wrap_t != WrapMode::ClampToEdge
+	}
+}
+
+impl Default for TextureOptions {
+	/// Smooth, repeating and mipmapped
+	fn default() -> TextureOptions {
+		TextureOptions {
+			mag_filter: FilterMode::Linear,
+			min_filter: FilterMode::Linear,
+			mipmaps: true,
+			wrap_s: WrapMode::Repeat,
+			wrap_t: WrapMode::Repeat
+		}
+	}
+}
+
+/// A texture that has been uploaded to the GPU
+pub struct Texture {
+	/// The texture on the GPU
+	pub handle: Option<TextureHandle>,
+	/// Height in pixels, after any resizing, of each face for cube maps
+	pub height: u32,
+	/// Whether it's a flat image or a cube map
+	pub target: TextureTarget,
+	/// Width in pixels, after any resizing, of each face for cube maps
+	pub width: u32
+}
+
+impl Texture {
+	/// Creates a texture with nothing in it yet, to render into
+	///
+	/// Nothing gets mipmapped, since what's in it changes every frame.
+	///
+	/// * `backend` - the backend to create it with
+	/// * `width` - width in pixels
+	/// * `height` - height in pixels
+	/// * `format` - what each pixel stores
+	/// * `options` - how the texture gets sampled
+	pub fn allocate<B: RenderBackend>(
+		backend: &mut B,
+		width: u32,
+		height: u32,
+		format: TextureFormat,
+		options: &TextureOptions
+	) -> Texture {
+		let options = TextureOptions {
+			mipmaps: false,
+			..*options
+		};
+
+		let handle = backend.create_texture();
+		backend.bind_texture(0, TextureTarget::Texture2D, handle);
+		backend.allocate_texture(width, height, format);
+		backend.set_texture_options(TextureTarget::Texture2D, &options);
+
+		Texture {
+			handle,
+			height,
+			target: TextureTarget::Texture2D,
+			width
+		}
+	}
+
+	/// Frees this texture from the GPU
+	///
+	/// * `backend` - the backend the texture was created with
+	pub fn delete<B: RenderBackend>(&self, backend: &mut B) {
+		if let Some(handle) = self.handle {
+			backend.delete_texture(handle);
+		}
+	}
+
+	/// Uploads an image as a new texture
+	///
+	/// WebGL1 can't mipmap or repeat a texture that isn't a power of two in
+	/// size, so those get resized first when the backend needs it.
+	///
+	/// * `backend` - the backend to upload with
+	/// * `image` - the image to upload
+	/// * `options` - how the texture gets sampled
+	///
+	/// Returns - the texture, or an error if the image is empty or its pixels
+	/// don't fit its size
+	pub fn upload<B: RenderBackend>(backend: &mut B, image: &Image, options: &TextureOptions) -> Result<Texture, TextureError> {
+		image.check_pixels()?;
+
+		// Get the image into a size the backend can deal with
+		let resized;
+		let image = if options.needs_power_of_two() && !image.is_power_of_two() && !backend.capabilities().npot_textures {
+			resized = image.resized_to_power_of_two()?;
+			&resized
+		} else {
+			image
+		};
+
+		// Upload
+		let handle = backend.create_texture();
+		backend.bind_texture(0, TextureTarget::Texture2D, handle);
+		backend.tex_image_2d(image.width, image.height, &image.pixels);
+		backend.set_texture_options(TextureTarget::Texture2D, options);
+		if options.mipmaps {
+			backend.generate_mipmap(TextureTarget::Texture2D);
+		}
+
+		Ok(Texture {
+			handle,
+			height: image.height,
+			target: TextureTarget::Texture2D,
+			width: image.width
+		})
+	}
+
+	/// Uploads six images as the faces of a new cube map
+	///
+	/// Faces get resized to a power of two first when the backend needs it,
+	/// the same as with upload.
+	///
+	/// * `backend` - the backend to upload with
+	/// * `faces` - the images, in the order of CubeFace::ALL
+	/// * `options` - how the cube map gets sampled
+	///
+	/// Returns - the cube map, or an error if the faces aren't all squares of the same size
+	pub fn upload_cube<B: RenderBackend>(backend: &mut B, faces: &[Image; 6], options: &TextureOptions) -> Result<Texture, TextureError> {
+		let size = faces[0].width;
+		if faces.iter().any(|face| face.width != size || face.height != size) {
+			return Err(TextureError::UnevenCubeFaces);
+		}
+		for face in faces {
+			face.check_pixels()?;
+		}
+
+		// Get the faces into a size the backend can deal with
+		let resized: [Image; 6];
+		let faces = if options.needs_power_of_two() && !size.is_power_of_two() && !backend.capabilities().npot_textures {
+			// Every face was checked, so none of them can fail to resize
+			resized = std::array::from_fn(|index| faces[index].resized_to_power_of_two().unwrap());
+			&resized
+		} else {
+			faces
+		};
+		let size = faces[0].width;
+
+		// Upload
+		let handle = backend.create_texture();
+		backend.bind_texture(0, TextureTarget::CubeMap, handle);
+		for (face, image) in CubeFace::ALL.iter().zip(faces) {
+			backend.tex_image_cube_face(*face, size, size, &image.pixels);
+		}
+		backend.set_texture_options(TextureTarget::CubeMap, options);
+		if options.mipmaps {
+			backend.generate_mipmap(TextureTarget::CubeMap);
+		}
+
+		Ok(Texture {
+			handle,
+			height: size,
+			target: TextureTarget::CubeMap,
+			width: size
+		})
+	}
+}
+
+/// Decodes a PNG or JPEG into RGBA pixels
+///
+/// * `bytes` - the encoded image file
+///
+/// Returns - the decoded image, or why it couldn't be decoded
+pub fn decode_image(bytes: &[u8]) -> Result<Image, TextureError> {
+	const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+	const JPEG_SIGNATURE: [u8; 3] = [0xff, 0xd8, 0xff];
+
+	if bytes.starts_with(&PNG_SIGNATURE) {
+		decode_png(bytes)
+	} else if bytes.starts_with(&JPEG_SIGNATURE) {
+		decode_jpeg(bytes)
+	} else {
+		Err(TextureError::UnknownFormat)
+	}
+}
+
+/// Finds the direction a point on a cube map face looks along
+///
+/// Undoes how WebGL picks a face and a point on it for a direction, so
+/// sampling the cube map in this direction lands on this point.
+///
+/// * `face` - the face the point is on
+/// * `s` - how far across the face, from -1 to 1
+/// * `t` - how far down the face, from -1 to 1
+fn cube_direction(face: CubeFace, s: f32, t: f32) -> Vector3<f32> {
+	let direction = match face {
+		CubeFace::PositiveX => Vector3::new(1.0, -t, -s),
+		CubeFace::NegativeX => Vector3::new(-1.0, -t, s),
+		CubeFace::PositiveY => Vector3::new(s, 1.0, t),
+		CubeFace::NegativeY => Vector3::new(s, -1.0, -t),
+		CubeFace::PositiveZ => Vector3::new(s, -t, 1.0),
+		CubeFace::NegativeZ => Vector3::new(-s, -t, -1.0)
+	};
+
+	direction.normalize()
+}
+
+/// Decodes a JPEG into RGBA pixels
+fn decode_jpeg(bytes: &[u8]) -> Result<Image, TextureError> {
+	let mut decoder = jpeg_decoder::Decoder::new(bytes);
+	let data = decoder.decode().map_err(|error| TextureError::Jpeg(error.to_string()))?;
+	let info = decoder.info().ok_or_else(|| TextureError::Jpeg(String::from("Missing image info")))?;
+
+	// Samples over 8 bits only go up to the precision the frame was coded with
+	let max = match info.pixel_format {
+		jpeg_decoder::PixelFormat::L16 => {
+			let precision = jpeg_precision(bytes).ok_or_else(|| TextureError::Jpeg(String::from("Missing frame header")))?;
+			(1u32 << precision) - 1
+		},
+		_ => 255
+	};
+
+	// Expand everything out to RGBA
+	let pixels = match info.pixel_format {
+		jpeg_decoder::PixelFormat::L8 => data.iter()
+			.flat_map(|&l| [l, l, l, 255])
+			.collect(),
+		jpeg_decoder::PixelFormat::L16 => data.chunks_exact(2)
+			.map(|l| scale_to_u8(u16::from_ne_bytes([l[0], l[1]]), max))
+			.flat_map(|l| [l, l, l, 255])
+			.collect(),
+		jpeg_decoder::PixelFormat::RGB24 => data.chunks_exact(3)
+			.flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+			.collect(),
+		jpeg_decoder::PixelFormat::CMYK32 => {
+			return Err(TextureError::UnsupportedPixelFormat(String::from("CMYK")));
+		}
+	};
+
+	Ok(Image {
+		height: info.height as u32,
+		pixels,
+		width: info.width as u32
+	})
+}
+
+/// Finds how many bits each sample of a JPEG was coded with
+///
+/// * `bytes` - the encoded image file
+///
+/// Returns - the precision from the frame header, or None if there isn't one
+/// before the image data
+fn jpeg_precision(bytes: &[u8]) -> Option<u8> {
+	// Walk the segments after the start of image marker
+	let mut position = 2;
+	while position + 4 < bytes.len() {
+		if bytes[position] != 0xff {
+			return None;
+		}
+		let marker = bytes[position + 1];
+		let length = u16::from_be_bytes([bytes[position + 2], bytes[position + 3]]) as usize;
+		match marker {
+			// Frame headers, leaving out the Huffman, arithmetic coding, and
+			// extension markers that share their range
+			0xc0..=0xcf if marker != 0xc4 && marker != 0xc8 && marker != 0xcc => {
+				return bytes.get(position + 4).copied();
+			},
+			// The image data starts, so there was never a frame header
+			0xda => return None,
+			_ => position += 2 + length
+		}
+	}
+
+	None
+}
+
+/// Decodes a PNG into RGBA pixels
+fn decode_png(bytes: &[u8]) -> Result<Image, TextureError> {
+	// Expand palettes and low bit depths, and cut 16 bit channels down to 8
+	let mut decoder = png::Decoder::new(bytes);
+	decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+
+	let mut reader = decoder.read_info().map_err(|error| TextureError::Png(error.to_string()))?;
+	let mut data = vec![0; reader.output_buffer_size()];
+	let info = reader.next_frame(&mut data).map_err(|error| TextureError::Png(error.to_string()))?;
+	let data = &data[..info.buffer_size()];
+
+	// Expand everything out to RGBA
+	let pixels = match info.color_type {
+		png::ColorType::Rgba => data.to_vec(),
+		png::ColorType::Rgb => data.chunks_exact(3)
+			.flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+			.collect(),
+		png::ColorType::GrayscaleAlpha => data.chunks_exact(2)
+			.flat_map(|la| [la[0], la[0], la[0], la[1]])
+			.collect(),
+		png::ColorType::Grayscale => data.iter()
+			.flat_map(|&l| [l, l, l, 255])
+			.collect(),
+		png::ColorType::Indexed => {
+			return Err(TextureError::UnsupportedPixelFormat(String::from("indexed")));
+		}
+	};
+
+	Ok(Image {
+		height: info.height,
+		pixels,
+		width: info.width
+	})
+}
+
+/// Scales a sample down to 8 bits
+///
+/// * `value` - the sample, from 0 to max
+/// * `max` - the largest value a sample can have at its precision
+fn scale_to_u8(value: u16, max: u32) -> u8 {
+	((value as u32).min(max) * 255 / max) as u8
+}
+
+/// Gets the color a panorama has in some direction, bilinear filtered
+///
+/// * `image` - the panorama, wrapping around horizontally
+/// * `direction` - which way to look, one unit long
+fn sample_equirectangular(image: &Image, direction: &Vector3<f32>) -> [u8; 4] {
+	if image.width == 0 || image.height == 0 {
+		return [0, 0, 0, 255];
+	}
+
+	// Longitude across, with -z in the middle, and latitude down from the top
+	let u = 0.5 + direction.x.atan2(-direction.z) / (2.0 * PI);
+	let v = 0.5 - direction.y.clamp(-1.0, 1.0).asin() / PI;
+
+	// Find the four closest pixels, wrapping across and clamping down
+	let x = u * image.width as f32 - 0.5;
+	let y = (v * image.height as f32 - 0.5).clamp(0.0, (image.height - 1) as f32);
+	let (tx, ty) = (x - x.floor(), y - y.floor());
+	let x0 = (x.floor() as i64).rem_euclid(image.width as i64) as u32;
+	let x1 = (x0 + 1) % image.width;
+	let y0 = y.floor() as u32;
+	let y1 = (y0 + 1).min(image.height - 1);
+
+	// Blend them
+	let (a, b) = (image.pixel(x0, y0), image.pixel(x1, y0));
+	let (c, d) = (image.pixel(x0, y1), image.pixel(x1, y1));
+	let mut pixel = [0; 4];
+	for channel in 0..4 {
+		let top = a[channel] as f32 * (1.0 - tx) + b[channel] as f32 * tx;
+		let bottom = c[channel] as f32 * (1.0 - tx) + d[channel] as f32 * tx;
+		pixel[channel] = (top * (1.0 - ty) + bottom * ty).round() as u8;
+	}
+
+	pixel
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::graphics::backend::headless::HeadlessBackend;
+	use super::*;
+
+	/// Encodes an 8x8 JPEG where every pixel is a gray of 200
+	///
+	/// Quantization is all ones, so the single block only needs its DC
+	/// coefficient of 8 * (200 - 128).
+	fn gray_jpeg() -> Vec<u8> {
+		let mut bytes = vec![0xff, 0xd8];
+		// Quantization table
+		bytes.extend([0xff, 0xdb, 0x00, 0x43, 0x00]);
+		bytes.extend([1; 64]);
+		// Frame header, 8 bit precision, 8x8, one component
+		bytes.extend([0xff, 0xc0, 0x00, 0x0b, 0x08, 0x00, 0x08, 0x00, 0x08, 0x01, 0x01, 0x11, 0x00]);
+		// The standard luminance DC table
+		bytes.extend([0xff, 0xc4, 0x00, 0x1f, 0x00, 0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0]);
+		bytes.extend(0..12);
+		// An AC table with nothing but end of block
+		bytes.extend([0xff, 0xc4, 0x00, 0x14, 0x10, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x00]);
+		// Scan header, then DC category 10 with 576, end of block, and padding
+		bytes.extend([0xff, 0xda, 0x00, 0x08, 0x01, 0x01, 0x00, 0x00, 0x3f, 0x00]);
+		bytes.extend([0xfe, 0x90, 0x1f]);
+		bytes.extend([0xff, 0xd9]);
+
+		bytes
+	}
+
+	#[test]
+	fn decodes_png() {
+		let mut bytes = Vec::new();
+		let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+		encoder.set_color(png::ColorType::Rgb);
+		encoder.set_depth(png::BitDepth::Eight);
+		encoder.write_header().unwrap().write_image_data(&[255, 0, 0, 0, 0, 255]).unwrap();
+
+		let image = decode_image(&bytes).unwrap();
+		assert_eq!((image.width, image.height), (2, 1));
+		assert_eq!(image.pixels, vec![255, 0, 0, 255, 0, 0, 255, 255]);
+	}
+
+	#[test]
+	fn decodes_jpeg() {
+		let image = decode_image(&gray_jpeg()).unwrap();
+		assert_eq!((image.width, image.height), (8, 8));
+		assert!(image.pixels.chunks_exact(4).all(|pixel| pixel == [200, 200, 200, 255]));
+	}
+
+	#[test]
+	fn rejects_unknown_formats() {
+		assert_eq!(decode_image(b"GIF89a"), Err(TextureError::UnknownFormat));
+		assert_eq!(decode_image(&[]), Err(TextureError::UnknownFormat));
+		assert!(matches!(decode_image(&[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]), Err(TextureError::Png(_))));
+	}
+
+	#[test]
+	fn scales_samples_by_precision() {
+		let mut twelve_bit = gray_jpeg();
+		twelve_bit[75] = 12;
+		assert_eq!(jpeg_precision(&gray_jpeg()), Some(8));
+		assert_eq!(jpeg_precision(&twelve_bit), Some(12));
+
+		assert_eq!(scale_to_u8(4095, 4095), 255);
+		assert_eq!(scale_to_u8(2048, 4095), 127);
+		assert_eq!(scale_to_u8(511, 511), 255);
+		assert_eq!(scale_to_u8(u16::MAX, u16::MAX as u32), 255);
+	}
+
+	#[test]
+	fn rejects_images_that_dont_fit_their_size() {
+		let empty = Image { height: 0, pixels: Vec::new(), width: 0 };
+		assert_eq!(empty.resized_to_power_of_two(), Err(TextureError::EmptyImage));
+
+		let short = Image { height: 3, pixels: vec![0; 4], width: 3 };
+		let mut backend = HeadlessBackend::new(1, 1);
+		assert_eq!(
+			Texture::upload(&mut backend, &short, &TextureOptions::default()).map(|_| ()),
+			Err(TextureError::WrongPixelCount { expected: 36, actual: 4 })
+		);
+		assert!(backend.commands().is_empty());
+	}
+}
@@ -0,0 +1,151 @@
+/// The first attribute location of the model matrix instanced draws take for
+/// each instance
+///
+/// A mat4 attribute takes four locations, one for each column, so this and
+/// the three after it are used. WebGL1 only has to have 8 locations, so
+/// instancing needs a device with more.
+pub const INSTANCE_MODEL_LOCATION: u32 = 8;
+
+/// Something a vertex can carry
+///
+/// Each attribute always binds to the same attribute location, so any shader
+/// can draw any mesh that has the attributes it reads.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum VertexAttribute {
+	/// Position in model space, xyz
+	Position,
+	/// Surface normal, xyz
+	Normal,
+	/// Surface tangent, xyz plus handedness in w
+	Tangent,
+	/// First texture coordinate set, uv
+	Uv0,
+	/// Second texture coordinate set, uv
+	Uv1,
+	/// Vertex color, rgba
+	Color,
+	/// Indices of the joints that move this vertex
+	Joints,
+	/// How much each joint moves this vertex
+	Weights
+}
+
+impl VertexAttribute {
+	/// Every attribute, in attribute location order
+	pub const ALL: [VertexAttribute; 8] = [
+		VertexAttribute::Position,
+		VertexAttribute::Normal,
+		VertexAttribute::Tangent,
+		VertexAttribute::Uv0,
+		VertexAttribute::Uv1,
+		VertexAttribute::Color,
+		VertexAttribute::Joints,
+		VertexAttribute::Weights
+	];
+
+	/// Gets the number of floats this attribute takes up per vertex
+	pub fn components(&self) -> usize {
+		match self {
+			VertexAttribute::Position => 3,
+			VertexAttribute::Normal => 3,
+			VertexAttribute::Tangent => 4,
+			VertexAttribute::Uv0 => 2,
+			VertexAttribute::Uv1 => 2,
+			VertexAttribute::Color => 4,
+			VertexAttribute::Joints => 4,
+			VertexAttribute::Weights => 4
+		}
+	}
+
+	/// Gets the attribute location this is always bound to
+	pub fn location(&self) -> u32 {
+		match self {
+			VertexAttribute::Position => 0,
+			VertexAttribute::Normal => 1,
+			VertexAttribute::Tangent => 2,
+			VertexAttribute::Uv0 => 3,
+			VertexAttribute::Uv1 => 4,
+			VertexAttribute::Color => 5,
+			VertexAttribute::Joints => 6,
+			VertexAttribute::Weights => 7
+		}
+	}
+}
+
+/// A buffer of vertex data holding one or more interleaved attributes
+#[derive(Clone, Debug, PartialEq)]
+pub struct VertexStream {
+	/// The attributes in each vertex, in the order they are stored
+	pub attributes: Vec<VertexAttribute>
+}
+
+impl VertexStream {
+	/// Gets the number of floats before an attribute in each vertex
+	///
+	/// Returns - None if this stream doesn't hold the attribute
+	pub fn offset_of(&self, attribute: VertexAttribute) -> Option<usize> {
+		let mut offset = 0;
+		for &stored in &self.attributes {
+			if stored == attribute {
+				return Some(offset);
+			}
+			offset += stored.components();
+		}
+
+		None
+	}
+
+	/// Gets the number of floats in each vertex of this stream
+	pub fn stride(&self) -> usize {
+		self.attributes.iter().map(|attribute| attribute.components()).sum()
+	}
+}
+
+/// Describes how a mesh's vertex data is split into streams
+#[derive(Clone, Debug, PartialEq)]
+pub struct VertexLayout {
+	/// Each stream gets its own buffer
+	pub streams: Vec<VertexStream>
+}
+
+impl VertexLayout {
+	/// Finds where an attribute is stored
+	///
+	/// Returns - the index of the stream holding it and the stream itself, or
+	/// None if no stream holds it
+	pub fn find(&self, attribute: VertexAttribute) -> Option<(usize, &VertexStream)> {
+		self.streams.iter()
+			.enumerate()
+			.find(|(_, stream)| stream.offset_of(attribute).is_some())
+	}
+
+	/// Whether any stream holds an attribute
+	pub fn has(&self, attribute: VertexAttribute) -> bool {
+		self.find(attribute).is_some()
+	}
+
+	/// Creates a layout with every attribute interleaved in one stream
+	///
+	/// * `attributes` - the attributes in each vertex, in the order they are stored
+	pub fn interleaved(attributes: Vec<VertexAttribute>) -> VertexLayout {
+		VertexLayout {
+			streams: vec![VertexStream { attributes }]
+		}
+	}
+
+	/// Creates a layout that only has positions, stored like [x1, y1, z1, x2, y2, z2]
+	pub fn position_only() -> VertexLayout {
+		Self::interleaved(vec![VertexAttribute::Position])
+	}
+
+	/// Creates a layout with every attribute in its own stream
+	///
+	/// * `attributes` - the attributes, one per stream
+	pub fn separate(attributes: Vec<VertexAttribute>) -> VertexLayout {
+		VertexLayout {
+			streams: attributes.into_iter()
+				.map(|attribute| VertexStream { attributes: vec![attribute] })
+				.collect()
+		}
+	}
+}
@@ -1,7 +1,17 @@
 #[cfg(debug_assertions)]
 extern crate console_error_panic_hook;
 
-use graphics::Graphics;
+use graphics::{
+	backend::{webgl::WebGlBackend, ShaderStage},
+	frame_stats::FrameStats,
+	post_process::{Effect, EffectKind, PostProcessError},
+	render_mode::RenderMode,
+	shaders::{ShaderError, UniformValue},
+	sky::Background,
+	texture::TextureOptions,
+	Graphics
+};
+use js_sys::{Array, Object, Reflect, Uint8Array};
 use logic::world::World;
 use wasm_bindgen::prelude::*;
 
@@ -11,12 +21,41 @@ pub mod logic;
 /// Contain all the information for the web app
 #[wasm_bindgen]
 pub struct WebApp {
-	graphics: Graphics,
+	graphics: Graphics<WebGlBackend>,
 	world: World
 }
 
 #[wasm_bindgen]
 impl WebApp {
+	/// Adds a post-processing effect to the end of the stack
+	///
+	/// * `kind` - one of bloom, color_grading, fxaa, gamma, tonemap, or vignette
+	///
+	/// Returns - the position of the effect, for changing it later
+	#[wasm_bindgen]
+	pub fn add_post_effect(&mut self, kind: &str) -> Result<usize, JsValue> {
+		let kind = EffectKind::from_name(kind)
+			.ok_or_else(|| post_error_to_js(PostProcessError::UnknownKind(String::from(kind))))?;
+
+		Ok(self.graphics.get_post_process_mut().add_effect(Effect::new(kind)))
+	}
+
+	/// Removes every post-processing effect
+	#[wasm_bindgen]
+	pub fn clear_post_effects(&mut self) {
+		self.graphics.get_post_process_mut().get_effects_mut().clear();
+	}
+
+	/// Gets what the last frame drew, and how long each part of it took
+	///
+	/// Returns - counts of draw calls, triangles, vertices, uploaded bytes,
+	/// program switches, calls the state cache saved, and culled and visible
+	/// objects, plus CPU times in milliseconds
+	#[wasm_bindgen]
+	pub fn frame_stats(&self) -> FrameStats {
+		*self.graphics.get_frame_stats()
+	}
+
 	/// Initialize rust
 	///
 	/// Function called from the browser to initialize the rust program
@@ -36,12 +75,190 @@ impl WebApp {
 		WebApp { graphics: graphics, world: world }
 	}
 
+	/// Decodes six PNGs or JPEGs and uploads them as a named cube map
+	///
+	/// Skyboxes and samplerCube uniforms find it by this name. The faces must
+	/// all be squares of the same size.
+	///
+	/// * `name` - the name to give the cube map
+	/// * `faces` - six Uint8Arrays of encoded image files, facing +x, -x, +y, -y, +z, then -z
+	#[wasm_bindgen]
+	pub fn load_cube_map(&mut self, name: &str, faces: Array) -> Result<(), JsValue> {
+		if faces.length() != 6 {
+			return Err(JsValue::from_str("Cube maps take 6 faces"));
+		}
+		let faces: Vec<Vec<u8>> = faces.iter().map(|face| Uint8Array::new(&face).to_vec()).collect();
+		let faces = [&faces[0][..], &faces[1], &faces[2], &faces[3], &faces[4], &faces[5]];
+
+		self.graphics.load_cube_map(name, faces, &TextureOptions::cube_map())
+			.map_err(|error| JsValue::from_str(&error.to_string()))
+	}
+
+	/// Decodes a PNG or JPEG panorama and uploads it as a named cube map
+	///
+	/// * `name` - the name to give the cube map
+	/// * `bytes` - the encoded image file, twice as wide as it is tall
+	#[wasm_bindgen]
+	pub fn load_equirectangular(&mut self, name: &str, bytes: &[u8]) -> Result<(), JsValue> {
+		self.graphics.load_equirectangular(name, bytes, &TextureOptions::cube_map())
+			.map_err(|error| JsValue::from_str(&error.to_string()))
+	}
+
+	/// Decodes a PNG or JPEG and uploads it as a named texture
+	///
+	/// Materials sample it by this name.
+	///
+	/// * `name` - the name to give the texture
+	/// * `bytes` - the encoded image file
+	#[wasm_bindgen]
+	pub fn load_texture(&mut self, name: &str, bytes: &[u8]) -> Result<(), JsValue> {
+		self.graphics.load_texture(name, bytes, &TextureOptions::default())
+			.map_err(|error| JsValue::from_str(&error.to_string()))
+	}
+
+	/// Recompiles a shader from new source code and swaps it in
+	///
+	/// If the new source doesn't build, the old program stays in use.
+	///
+	/// * `name` - the name of the shader to replace
+	/// * `vertex_src` - new vertex shader source
+	/// * `fragment_src` - new fragment shader source
+	///
+	/// Returns - an error object like the ones from shader_errors if it
+	/// didn't build
+	#[wasm_bindgen]
+	pub fn reload_shader(&mut self, name: &str, vertex_src: &str, fragment_src: &str) -> Result<(), JsValue> {
+		self.graphics.reload_shader(name, vertex_src, fragment_src)
+			.map_err(|error| shader_error_to_js(&error))
+	}
+
 	/// Kicks off rendering
 	#[wasm_bindgen]
 	pub fn render(&mut self) {
 		self.graphics.render(&self.world);
 	}
 
+	/// Fits rendering to a canvas that changed size
+	///
+	/// Call this whenever the window is resized or moved to a screen with a
+	/// different pixel ratio.
+	///
+	/// * `width` - width of the canvas in CSS pixels
+	/// * `height` - height of the canvas in CSS pixels
+	/// * `dpr` - the window's devicePixelRatio
+	#[wasm_bindgen]
+	pub fn resize(&mut self, width: u32, height: u32, dpr: f32) {
+		self.graphics.resize(width, height, dpr);
+	}
+
+	/// Clears behind everything to one color
+	///
+	/// * `red` - red from 0 to 1
+	/// * `green` - green from 0 to 1
+	/// * `blue` - blue from 0 to 1
+	#[wasm_bindgen]
+	pub fn set_background_color(&mut self, red: f32, green: f32, blue: f32) {
+		self.graphics.set_background(Background::Color([red, green, blue]));
+	}
+
+	/// Blends behind everything from one color looking down to another
+	/// looking up
+	///
+	/// * `bottom` - red, green, and blue looking straight down
+	/// * `top` - red, green, and blue looking straight up
+	#[wasm_bindgen]
+	pub fn set_background_gradient(&mut self, bottom: &[f32], top: &[f32]) -> Result<(), JsValue> {
+		let (bottom, top) = match (bottom.try_into(), top.try_into()) {
+			(Ok(bottom), Ok(top)) => (bottom, top),
+			_ => return Err(JsValue::from_str("Gradient colors take 3 numbers each"))
+		};
+		self.graphics.set_background(Background::Gradient { bottom, top });
+
+		Ok(())
+	}
+
+	/// Turns a post-processing effect on or off
+	///
+	/// * `index` - the position of the effect
+	/// * `enabled` - whether it gets drawn
+	#[wasm_bindgen]
+	pub fn set_post_effect_enabled(&mut self, index: usize, enabled: bool) -> Result<(), JsValue> {
+		self.graphics.get_post_process_mut()
+			.get_effects_mut()
+			.get_mut(index)
+			.ok_or_else(|| post_error_to_js(PostProcessError::NoEffect(index)))?
+			.set_enabled(enabled);
+
+		Ok(())
+	}
+
+	/// Changes a parameter of a post-processing effect
+	///
+	/// * `index` - the position of the effect
+	/// * `name` - the name of the parameter, like exposure or intensity
+	/// * `value` - 1 to 4 numbers, for a float up to a vec4
+	#[wasm_bindgen]
+	pub fn set_post_effect_param(&mut self, index: usize, name: &str, value: &[f32]) -> Result<(), JsValue> {
+		let value = match *value {
+			[x] => UniformValue::Float(x),
+			[x, y] => UniformValue::Vec2([x, y]),
+			[x, y, z] => UniformValue::Vec3([x, y, z]),
+			[x, y, z, w] => UniformValue::Vec4([x, y, z, w]),
+			_ => return Err(JsValue::from_str("Parameters take 1 to 4 numbers"))
+		};
+
+		self.graphics.set_post_effect_param(index, name, value)
+			.map_err(post_error_to_js)
+	}
+
+	/// Sets the texture a post-processing effect samples, like color grading's lut
+	///
+	/// * `index` - the position of the effect
+	/// * `sampler` - the name of the sampler
+	/// * `texture_name` - the name the texture was loaded with
+	#[wasm_bindgen]
+	pub fn set_post_effect_texture(&mut self, index: usize, sampler: &str, texture_name: &str) -> Result<(), JsValue> {
+		self.graphics.get_post_process_mut()
+			.get_effects_mut()
+			.get_mut(index)
+			.ok_or_else(|| post_error_to_js(PostProcessError::NoEffect(index)))?
+			.set_texture(sampler, texture_name);
+
+		Ok(())
+	}
+
+	/// Changes how the scene is drawn, to look for bugs in meshes
+	///
+	/// * `mode` - shaded, wireframe, normals, uv_checker, depth, overdraw, or random_color
+	#[wasm_bindgen]
+	pub fn set_render_mode(&mut self, mode: &str) -> Result<(), JsValue> {
+		let mode = RenderMode::from_name(mode)
+			.ok_or_else(|| JsValue::from_str(&format!("Unknown render mode {}", mode)))?;
+		self.graphics.set_render_mode(mode);
+
+		Ok(())
+	}
+
+	/// Draws a cube map behind everything
+	///
+	/// * `name` - the name the cube map was loaded with
+	#[wasm_bindgen]
+	pub fn set_skybox(&mut self, name: &str) {
+		self.graphics.set_background(Background::Skybox(String::from(name)));
+	}
+
+	/// Gets what went wrong with each shader permutation that didn't build
+	///
+	/// Returns - an array of objects with name, stage, line, column,
+	/// sourceLine, and message fields, plus a readable text field
+	#[wasm_bindgen]
+	pub fn shader_errors(&self) -> Array {
+		self.graphics.get_shader_errors()
+			.into_iter()
+			.map(shader_error_to_js)
+			.collect()
+	}
+
 	/// Kicks off world update
 	#[wasm_bindgen]
 	pub fn update(&mut self, dt: f32) {
@@ -49,3 +266,38 @@ impl WebApp {
 	}
 
 }
+
+/// Converts a post-processing error to a JS string
+fn post_error_to_js(error: PostProcessError) -> JsValue {
+	JsValue::from_str(&error.to_string())
+}
+
+/// Converts a shader error to a plain JS object
+///
+/// Missing fields are null.
+///
+/// * `error` - the error to convert
+fn shader_error_to_js(error: &ShaderError) -> JsValue {
+	let stage = match error.stage {
+		Some(ShaderStage::Vertex) => "vertex",
+		Some(ShaderStage::Fragment) => "fragment",
+		None => "link"
+	};
+	let fields = [
+		("name", JsValue::from_str(&error.name)),
+		("stage", JsValue::from_str(stage)),
+		("line", error.line.map(JsValue::from).unwrap_or(JsValue::NULL)),
+		("column", error.column.map(JsValue::from).unwrap_or(JsValue::NULL)),
+		("sourceLine", error.source_line.as_deref().map(JsValue::from_str).unwrap_or(JsValue::NULL)),
+		("message", JsValue::from_str(&error.message)),
+		("text", JsValue::from_str(&error.to_string()))
+	];
+
+	// Setting fields on a fresh object can't fail
+	let object = Object::new();
+	for (key, value) in fields {
+		Reflect::set(&object, &JsValue::from_str(key), &value).unwrap();
+	}
+
+	object.into()
+}
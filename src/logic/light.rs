@@ -0,0 +1,182 @@
+use nalgebra::Vector3;
+
+/// How a light's strength falls off with distance
+///
+/// Strength is divided by constant + linear * d + quadratic * d^2.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attenuation {
+	pub constant: f32,
+	pub linear: f32,
+	pub quadratic: f32
+}
+
+impl Attenuation {
+	/// Falls off to about 1% at a distance
+	///
+	/// * `range` - the distance the light should reach
+	pub fn for_range(range: f32) -> Attenuation {
+		Attenuation {
+			constant: 1.0,
+			linear: 4.5 / range,
+			quadratic: 75.0 / (range * range)
+		}
+	}
+}
+
+/// The shape of a light
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightKind {
+	/// Infinitely far away, like the sun
+	Directional {
+		/// The direction the light travels in
+		direction: Vector3<f32>
+	},
+	/// Shines in every direction from a point
+	Point {
+		attenuation: Attenuation
+	},
+	/// Shines in a cone from a point
+	Spot {
+		attenuation: Attenuation,
+		/// The direction the cone points in
+		direction: Vector3<f32>,
+		/// Angle from the center to where the light starts fading (radians)
+		inner_angle: f32,
+		/// Angle from the center to where the light is gone (radians)
+		outer_angle: f32
+	}
+}
+
+/// Something that lights up the world
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+	/// Whether the light casts shadows
+	casts_shadows: bool,
+	/// Color of the light, rgb from 0 to 1
+	color: Vector3<f32>,
+	/// Brightness the color gets multiplied by
+	intensity: f32,
+	/// The shape of the light
+	kind: LightKind,
+	/// Where the light is in world space, ignored for directional lights
+	position: Vector3<f32>
+}
+
+impl Light {
+	/// Whether the light casts shadows
+	pub fn casts_shadows(&self) -> bool {
+		self.casts_shadows
+	}
+
+	/// Creates a directional light
+	///
+	/// * `direction` - the direction the light travels in
+	/// * `color` - color of the light, rgb from 0 to 1
+	/// * `intensity` - brightness the color gets multiplied by
+	pub fn directional(direction: Vector3<f32>, color: Vector3<f32>, intensity: f32) -> Light {
+		Light {
+			casts_shadows: false,
+			color,
+			intensity,
+			kind: LightKind::Directional { direction: direction.normalize() },
+			position: Vector3::zeros()
+		}
+	}
+
+	/// Gets the color of the light
+	pub fn get_color(&self) -> &Vector3<f32> {
+		&self.color
+	}
+
+	/// Gets the brightness the color gets multiplied by
+	pub fn get_intensity(&self) -> f32 {
+		self.intensity
+	}
+
+	/// Gets the shape of the light
+	pub fn get_kind(&self) -> &LightKind {
+		&self.kind
+	}
+
+	/// Gets where the light is in world space
+	pub fn get_position(&self) -> &Vector3<f32> {
+		&self.position
+	}
+
+	/// Creates a point light
+	///
+	/// * `position` - where the light is in world space
+	/// * `color` - color of the light, rgb from 0 to 1
+	/// * `intensity` - brightness the color gets multiplied by
+	/// * `attenuation` - how the light falls off with distance
+	pub fn point(position: Vector3<f32>, color: Vector3<f32>, intensity: f32, attenuation: Attenuation) -> Light {
+		Light {
+			casts_shadows: false,
+			color,
+			intensity,
+			kind: LightKind::Point { attenuation },
+			position
+		}
+	}
+
+	/// Turns shadows from this light on or off
+	///
+	/// Only directional and spot lights cast shadows.
+	pub fn set_casts_shadows(&mut self, casts_shadows: bool) {
+		self.casts_shadows = casts_shadows;
+	}
+
+	/// Sets the color of the light
+	///
+	/// * `color` - rgb from 0 to 1
+	pub fn set_color(&mut self, color: Vector3<f32>) {
+		self.color = color;
+	}
+
+	/// Sets the brightness the color gets multiplied by
+	pub fn set_intensity(&mut self, intensity: f32) {
+		self.intensity = intensity;
+	}
+
+	/// Moves the light
+	///
+	/// * `position` - where the light is in world space
+	pub fn set_position(&mut self, position: Vector3<f32>) {
+		self.position = position;
+	}
+
+	/// Creates a spot light
+	///
+	/// * `position` - where the light is in world space
+	/// * `direction` - the direction the cone points in
+	/// * `inner_angle` - angle from the center to where the light starts fading (radians)
+	/// * `outer_angle` - angle from the center to where the light is gone (radians)
+	/// * `color` - color of the light, rgb from 0 to 1
+	/// * `intensity` - brightness the color gets multiplied by
+	/// * `attenuation` - how the light falls off with distance
+	pub fn spot(
+		position: Vector3<f32>,
+		direction: Vector3<f32>,
+		inner_angle: f32,
+		outer_angle: f32,
+		color: Vector3<f32>,
+		intensity: f32,
+		attenuation: Attenuation
+	) -> Light {
+		// Keep the fade from going backwards
+		let inner_angle = inner_angle.min(outer_angle);
+
+		Light {
+			casts_shadows: false,
+			color,
+			intensity,
+			kind: LightKind::Spot {
+				attenuation,
+				direction: direction.normalize(),
+				inner_angle,
+				outer_angle
+			},
+			position
+		}
+	}
+}
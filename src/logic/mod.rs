@@ -1,2 +1,3 @@
+pub mod light;
 pub mod object;
 pub mod world;
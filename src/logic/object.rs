@@ -1,17 +1,26 @@
-use js_sys::{WebAssembly, Float32Array};
+use std::rc::Rc;
+
 use nalgebra::{Matrix4, Vector3, UnitQuaternion, UnitVector3, Point3};
 use nalgebra_glm::quat_to_mat4;
-use wasm_bindgen::{JsCast, JsValue};
-use web_sys::WebGlRenderingContext;
 
-use crate::graphics::{shaders::CompiledShader, gl};
+use crate::graphics::{
+	backend::{BufferTarget, Primitive, RenderBackend, VertexComponent},
+	bounds::{Aabb, BoundingSphere},
+	vertex_layout::VertexAttribute,
+	gl,
+	material::Material,
+	mesh::{GpuMesh, GpuMeshPart, Indices, Mesh},
+	shaders::CompiledShader
+};
 
 /// Something that can be rendered to the screen
 pub struct Object {
-	/// The JS memory allocated for this object
-	js_memory: JsValue,
-	/// The JS buffer contained the vertices for rendering
-	js_vertex_buffer: Float32Array,
+	/// Whether this object casts shadows
+	casts_shadows: bool,
+	/// How this object looks, which may be shared with other objects
+	material: Rc<Material>,
+	/// The geometry of this object, which may be shared with other objects
+	mesh: Rc<Mesh>,
 	/// Model matrix for rendering. A combination of translation, rotation, scale
 	model_matrix: Matrix4<f32>,
 	/// The unit quaternion that describes the orientation of this object
@@ -23,35 +32,80 @@ pub struct Object {
 	orientation_quaternion: UnitQuaternion<f32>,
 	/// The position of this object's origin in world space
 	position: Vector3<f32>,
+	/// Whether shadows fall on this object
+	receives_shadows: bool,
 	/// The rotation matrix for rendering
 	rotation_matrix: Matrix4<f32>,
 	/// The scale of this object compared to world space
 	scale: f32,
 	/// The scale matrix for rendering
 	scale_matrix: Matrix4<f32>,
-	/// The name of the shader to use on this object
-	shader_name: &'static str,
 	/// Translation matrix for rendering
-	translation_matrix: Matrix4<f32>,
-	/// Contains an index array for rendering
-	triangle_indices: Vec<u8>,
-	/// A vector of vertices optimized for rendering vs physics
-	/// Stored like [x1, y2, z1, x2, y2, z2]
-	vertices: Vec<f32>
+	translation_matrix: Matrix4<f32>
 }
-#[allow(dead_code)]
 impl Object {
+	/// Points each attribute the shader reads at the stream holding it, and
+	/// binds the part's indices
+	///
+	/// * `gl` - the backend to render with
+	/// * `shader` - the compiled shader program to render with
+	/// * `part` - the part of this object's mesh about to be drawn
+	fn bind_part<B: RenderBackend>(&self, gl: &mut B, shader: &CompiledShader, part: &GpuMeshPart) {
+		let layout = self.mesh.get_layout();
+		for attribute in VertexAttribute::ALL {
+			let location = attribute.location();
+			let found = layout.find(attribute)
+				.filter(|_| shader.attributes.contains(&attribute));
+
+			match found {
+				Some((stream_index, stream)) => {
+					let stride = (stream.stride() * 4) as i32;
+					let offset = (stream.offset_of(attribute).unwrap_or(0) * 4) as i32;
+					let size = attribute.components() as i32;
+
+					gl.bind_buffer(BufferTarget::Array, part.vertex_buffers[stream_index]);
+					gl.vertex_attrib_pointer(location, size, VertexComponent::Float, false, stride, offset);
+					gl.enable_vertex_attrib_array(location);
+				},
+				None => gl.disable_vertex_attrib_array(location)
+			}
+		}
+
+		gl.bind_buffer(BufferTarget::ElementArray, part.index_buffer);
+	}
+
+	/// Whether this object casts shadows
+	pub fn casts_shadows(&self) -> bool {
+		self.casts_shadows
+	}
+
 	/// Change the scale of this object relative to the world
 	///
 	/// * `scale` - the scale to set, 1.0 being bounded at 2 units^3
+	#[allow(dead_code)]
 	fn change_scale(&mut self, scale: f32) {
 		self.scale = scale;
 		self.scale_matrix = Matrix4::new_scaling(scale);
 	}
 
-	/// Get the name of the shader to use when rendering this object
-	pub fn get_shader_name(&self) -> &'static str {
-		self.shader_name
+	/// Get the mesh this object is drawn with
+	pub fn get_mesh(&self) -> &Rc<Mesh> {
+		&self.mesh
+	}
+
+	/// Get the material this object is drawn with
+	pub fn get_material(&self) -> &Rc<Material> {
+		&self.material
+	}
+
+	/// Get the matrix that moves this object's mesh into world space
+	pub fn get_model_matrix(&self) -> &Matrix4<f32> {
+		&self.model_matrix
+	}
+
+	/// Get the position of this object's origin in world space
+	pub fn get_position(&self) -> &Vector3<f32> {
+		&self.position
 	}
 
 	/// Gets the vertices for this object as a Vec of Point3
@@ -59,24 +113,31 @@ impl Object {
 		let mut vertices = Vec::new();
 
 		// Yank out vertices one by one
-		let num_vertices = self.vertices.len() / 3;
-		for i in 0..num_vertices {
-			let vert = Point3::new(
-				self.vertices[i],
-				self.vertices[i + 1],
-				self.vertices[i + 2]
-			);
-
-			vertices.push(vert);
+		for [x, y, z] in self.mesh.get_positions() {
+			vertices.push(Point3::new(x, y, z));
 		}
 
 
 		vertices
 	}
 
+	/// Get a box around this object in world space
+	///
+	/// Fits around its mesh's box after that's been moved, turned, and
+	/// scaled, so it can be a bit bigger than the object.
+	pub fn get_world_bounds(&self) -> Aabb {
+		self.mesh.get_bounds().transformed(&self.model_matrix)
+	}
+
+	/// Get a sphere around this object in world space
+	pub fn get_world_sphere(&self) -> BoundingSphere {
+		self.mesh.get_bounding_sphere().transformed(&self.model_matrix)
+	}
+
 	/// Moves this object in some direction over some vector
 	///
 	/// * `direction` - the vector to move this object by
+	#[allow(dead_code)]
 	fn move_dir(&mut self, direction: Vector3<f32>) {
 		let position = self.position + direction;
 		self.position = position;
@@ -84,26 +145,25 @@ impl Object {
 		self.update_model_matrix();
 	}
 
-	/// Creates a new Object
+	/// Creates a new Object drawn with an existing mesh
+	///
+	/// Objects made from the same mesh share its GPU buffers.
 	///
 	/// * `position` - The position of this object's origin in world space
 	/// * `pitch` - The pitch of this object up from the horizon (radians)
 	/// * `yaw` - The yaw of this object clockwise viewed from above (radians)
 	/// * `roll` - The roll of this object clockwise viewed in the x direction (radians)
 	/// * `scale` - The relative scale of this object in world space
-	/// * `shader_name` - The name of the shader to use on this object
-	/// * `triangle_indices` - Contains an index array for rendering
-	/// * `vertices` - A vector of vertices optimized for rendering vs physics
-	/// Stored like [x1, y2, z1, x2, y2, z2]
-	pub fn new(
+	/// * `material` - How this object looks
+	/// * `mesh` - The geometry to draw this object with
+	pub fn from_mesh(
 		position: Vector3<f32>,
 		pitch: f32,
 		yaw: f32,
 		roll: f32,
 		scale: f32,
-		shader_name: &'static str,
-		triangle_indices: Vec<u8>,
-		vertices: Vec<f32>
+		material: Rc<Material>,
+		mesh: Rc<Mesh>
 	) -> Object {
 		// Generate the orientation quaternion
 		let orientation_quaternion = UnitQuaternion::from_euler_angles(roll, pitch, yaw);
@@ -114,62 +174,118 @@ impl Object {
 		let rotation_matrix = quat_to_mat4(&orientation_quaternion);
 		let model_matrix = translation_matrix * rotation_matrix * scale_matrix;
 
-		// Allocate memory for vertex array
-		let memory = wasm_bindgen::memory()
-		.dyn_into::<WebAssembly::Memory>().expect("Failed to allocate memory to render an Object");
-
-		let js_memory = memory.buffer();
-
-		// Set vertex array
-		let vertices_location = vertices.as_ptr() as u32 / 4;
-		let js_vertex_buffer = js_sys::Float32Array::new(&js_memory)
-            .subarray(vertices_location, vertices_location + vertices.len() as u32);
-
 		// Return Object
 		Object {
-			js_memory,
-			js_vertex_buffer,
+			casts_shadows: true,
+			material,
+			mesh,
 			model_matrix,
 			orientation_quaternion,
 			position,
+			receives_shadows: true,
 			translation_matrix,
 			rotation_matrix,
 			scale,
-			scale_matrix,
-			shader_name,
-			triangle_indices,
-			vertices
+			scale_matrix
 		}
 	}
 
+	/// Creates a new Object
+	///
+	/// * `position` - The position of this object's origin in world space
+	/// * `pitch` - The pitch of this object up from the horizon (radians)
+	/// * `yaw` - The yaw of this object clockwise viewed from above (radians)
+	/// * `roll` - The roll of this object clockwise viewed in the x direction (radians)
+	/// * `scale` - The relative scale of this object in world space
+	/// * `material` - How this object looks
+	/// * `triangle_indices` - Contains an index array for rendering
+	/// * `vertices` - A vector of vertices optimized for rendering vs physics
+	///   stored like [x1, y1, z1, x2, y2, z2]
+	pub fn new(
+		position: Vector3<f32>,
+		pitch: f32,
+		yaw: f32,
+		roll: f32,
+		scale: f32,
+		material: Rc<Material>,
+		triangle_indices: Indices,
+		vertices: Vec<f32>
+	) -> Object {
+		let mesh = Rc::new(Mesh::new(triangle_indices, vertices));
+
+		Self::from_mesh(position, pitch, yaw, roll, scale, material, mesh)
+	}
+
+	/// Whether shadows fall on this object
+	pub fn receives_shadows(&self) -> bool {
+		self.receives_shadows
+	}
+
 	/// Render this object
 	///
 	/// If this is None the render just does nothing, because there would be
 	/// no point.
 	///
-	/// * `gl` - the rendering context to use
-	/// * `shader_option` - an option to a reference to a compile shader program
-	/// * `view_matrix` - the view matrix to render with, usually from a Camera
-	pub fn render(
+	/// * `gl` - the backend to render with
+	/// * `shader` - the compiled shader program to render with
+	/// * `gpu_mesh` - the uploaded buffers of this object's mesh
+	pub fn render<B: RenderBackend>(
 		&self,
-		gl: &WebGlRenderingContext,
-		shader: &CompiledShader
+		gl: &mut B,
+		shader: &CompiledShader,
+		gpu_mesh: &GpuMesh
 	) {
 		// Set Model uniform value
-		gl::set_mat4_uniform(gl, &shader.model_uniform, self.model_matrix.as_slice());
+		gl::set_mat4_uniform(gl, &shader.model_uniform, &self.model_matrix);
 
-		// Set vertex buffer
-		gl.buffer_data_with_array_buffer_view(WebGlRenderingContext::ARRAY_BUFFER, &self.js_vertex_buffer, WebGlRenderingContext::STATIC_DRAW);
+		// Draw each part of the mesh
+		for part in &gpu_mesh.parts {
+			self.bind_part(gl, shader, part);
+			gl.draw_elements(Primitive::Triangles, part.index_count, part.index_type, 0);
+		}
+	}
 
-		// Set index buffer
-		gl.buffer_data_with_u8_array(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, &self.triangle_indices[..], WebGlRenderingContext::STATIC_DRAW);
+	/// Render this object's mesh many times in one draw call per part
+	///
+	/// The shader has to be an INSTANCED permutation, with the model matrix of
+	/// each instance already bound, since this object's own isn't used.
+	///
+	/// * `gl` - the backend to render with
+	/// * `shader` - the compiled shader program to render with
+	/// * `gpu_mesh` - the uploaded buffers of this object's mesh
+	/// * `instance_count` - how many instances to draw
+	pub fn render_instances<B: RenderBackend>(
+		&self,
+		gl: &mut B,
+		shader: &CompiledShader,
+		gpu_mesh: &GpuMesh,
+		instance_count: i32
+	) {
+		for part in &gpu_mesh.parts {
+			self.bind_part(gl, shader, part);
+			gl.draw_elements_instanced(Primitive::Triangles, part.index_count, part.index_type, 0, instance_count);
+		}
+	}
 
-		// Set attrib pointer
-		gl.vertex_attrib_pointer_with_i32(0, 3, WebGlRenderingContext::FLOAT, false, 0, 0);
-		gl.enable_vertex_attrib_array(0);
+	/// Render the edges of this object's triangles as lines
+	///
+	/// * `gl` - the backend to render with
+	/// * `shader` - the compiled shader program to render with
+	/// * `gpu_mesh` - the uploaded buffers of this object's mesh's wireframe
+	pub fn render_wireframe<B: RenderBackend>(
+		&self,
+		gl: &mut B,
+		shader: &CompiledShader,
+		gpu_mesh: &GpuMesh
+	) {
+		// Set Model uniform value
+		gl::set_mat4_uniform(gl, &shader.model_uniform, &self.model_matrix);
 
-		// Draw
-		gl.draw_elements_with_i32(WebGlRenderingContext::TRIANGLES, self.triangle_indices.len() as i32, WebGlRenderingContext::UNSIGNED_BYTE, 0);
+		// Draw each part of the wireframe
+		for part in &gpu_mesh.parts {
+			self.bind_part(gl, shader, part);
+			gl.draw_elements(Primitive::Lines, part.index_count, part.index_type, 0);
+		}
 	}
 
 	/// Rotate this object in all directions
@@ -190,6 +306,7 @@ impl Object {
 	///
 	/// * `vector` - the vector to face this object in the direction of
 	/// * `roll` - the roll angle after aiming in that direction
+	#[allow(dead_code)]
 	fn set_direction_from_vector(&mut self, vector: Vector3<f32>, roll: f32) {
 		let axis = UnitVector3::new_normalize(vector);
 		self.orientation_quaternion = UnitQuaternion::from_axis_angle(&axis, roll);
@@ -202,30 +319,47 @@ impl Object {
 	/// * `pitch` -  how much this object is pitched up from the horizon
 	/// * `yaw` - how much this object is yawed clockwise (when viewed from above)
 	/// * `roll` - how much this object is rolled clockwise
+	#[allow(dead_code)]
 	fn set_rotation(&mut self, pitch: f32, yaw: f32, roll: f32) {
 		self.orientation_quaternion = UnitQuaternion::from_euler_angles(roll, pitch, yaw);
 		self.rotation_matrix = quat_to_mat4(&self.orientation_quaternion);
 		self.update_model_matrix();
 	}
 
+	/// Turns shadows from this object on or off
+	pub fn set_casts_shadows(&mut self, casts_shadows: bool) {
+		self.casts_shadows = casts_shadows;
+	}
+
+	/// Sets the material this object is drawn with
+	///
+	/// * `material` - How this object looks
+	pub fn set_material(&mut self, material: Rc<Material>) {
+		self.material = material;
+	}
+
+	/// Turns shadows falling on this object on or off
+	pub fn set_receives_shadows(&mut self, receives_shadows: bool) {
+		self.receives_shadows = receives_shadows;
+	}
+
 	/// Sets new geometry for this object
 	///
+	/// If the mesh is shared with other objects, this object gets its own copy
+	/// first so the others are left alone. The new vertices are uploaded the
+	/// next time this object is rendered.
+	///
 	/// * `vertices` - A vector of vertices optimized for rendering vs physics
-	/// Stored like [x1, y2, z1, x2, y2, z2]
-	fn set_vertices(&mut self, vertices: Vec<f32>) {
-		// Create vertex array from these new vertices
-		let vertices_location = self.vertices.as_ptr() as u32 / 4;
-		self.js_vertex_buffer = js_sys::Float32Array::new(&self.js_memory)
-            .subarray(vertices_location, vertices_location + vertices.len() as u32);
-
-		// Set vertices
-		self.vertices = vertices;
+	///   stored like [x1, y1, z1, x2, y2, z2]
+	pub fn set_vertices(&mut self, vertices: Vec<f32>) {
+		Rc::make_mut(&mut self.mesh).set_vertices(vertices);
 	}
 
 	/// Teleports this object to a new position in the world
 	///
 	/// * `position` - the position to teleport to
 	/// Specifically moves the origin point of this object to this position
+	#[allow(dead_code)]
 	fn teleport(&mut self, position: Vector3<f32>) {
 		self.position = position;
 		self.translation_matrix = Matrix4::new_translation(&position);
@@ -1,14 +1,35 @@
+use std::rc::Rc;
+
 use nalgebra::Vector3;
 
-use super::object::Object;
+use crate::graphics::{debug_draw::DebugDraw, material::Material, mesh::Indices, render_state::{CullMode, RenderState}};
+use super::{light::Light, object::Object};
 
 /// Contains the game world
 pub struct World {
+	lights: Vec<Light>,
 	objects: Vec<Object>,
 	time_elapsed: f32
 }
 
 impl World {
+	/// Adds a light to the world
+	///
+	/// * `light` - the light to add
+	pub fn add_light(&mut self, light: Light) {
+		self.lights.push(light);
+	}
+
+	/// Gets all the lights in the world
+	pub fn get_lights(&self) -> &Vec<Light> {
+		&self.lights
+	}
+
+	/// Gets all the lights in the world, mutably
+	pub fn get_lights_mut(&mut self) -> &mut Vec<Light> {
+		&mut self.lights
+	}
+
 	/// Gets all the objects in the world as a vector
 	///
 	/// Returns - the vector containing all the objects in the world
@@ -16,6 +37,11 @@ impl World {
 		&self.objects
 	}
 
+	/// Gets all the objects in the world, mutably
+	pub fn get_objects_mut(&mut self) -> &mut Vec<Object> {
+		&mut self.objects
+	}
+
 	/// Gets the time (in seconds) since the program started
 	pub fn get_time_elapsed(&self) -> f32 {
 		self.time_elapsed
@@ -24,8 +50,23 @@ impl World {
 	/// Initialize the game world
 	pub fn init() -> World {
 		let objects = load_objects();
+		let mut world = Self::new(objects);
+
+		// Light it from above
+		let sun_direction = Vector3::new(-0.3, -1.0, -0.5);
+		let mut sun = Light::directional(sun_direction, Vector3::new(1.0, 1.0, 1.0), 1.0);
+		sun.set_casts_shadows(true);
+		world.add_light(sun);
+
+		world
+	}
 
+	/// Creates a game world out of a set of objects
+	///
+	/// * `objects` - the objects in the world
+	pub fn new(objects: Vec<Object>) -> World {
 		World {
+			lights: Vec::new(),
 			objects: objects,
 			time_elapsed: 0.0
 		}
@@ -38,6 +79,10 @@ impl World {
 		// Update timer
 		self.time_elapsed += dt;
 
+		// Let timed debug shapes run out, which count in seconds rather than
+		// milliseconds
+		DebugDraw::advance(dt / 1000.0);
+
 		// Update objects
 		for object in &mut self.objects {
 			object.update(dt);
@@ -84,7 +129,15 @@ fn load_objects() -> Vec<Object> {
 
 	let position = Vector3::new(0.0, 0.0, 0.0);
 	let scale = 1.0;
-	objects.push(Object::new(position, 0.0, 0.0, 0.0, scale, "3d orange", indices, vertices));
+
+	// The winding of these faces isn't consistent, so don't cull any of them
+	let mut material = Material::new("3d orange");
+	material.set_render_state(Some(RenderState {
+		cull_mode: CullMode::None,
+		..RenderState::opaque()
+	}));
+
+	objects.push(Object::new(position, 0.0, 0.0, 0.0, scale, Rc::new(material), Indices::U8(indices), vertices));
 
 	objects
 }
@@ -9,6 +9,17 @@ const init = async () => {
 	const wasm: InitOutput = await wasmInit("./wasm_engine_bg.wasm")
 	webApp = WebApp.init()
 
+	// Report shaders that didn't build
+	for(const error of webApp.shader_errors()) console.error(error.text)
+
+	// Keep the drawing buffer matched to the canvas
+	const resize = () => {
+		const canvas = document.getElementById("webGL")
+		webApp.resize(canvas.clientWidth, canvas.clientHeight, window.devicePixelRatio)
+	}
+	window.addEventListener("resize", resize)
+	resize()
+
 	// Add fps monitoring
 	let lastTimeRender: number = Date.now()
 	let renderDt = []
@@ -16,6 +27,12 @@ const init = async () => {
 		if(DEBUG) {
 			const rollingAvg = renderDt.reduce((a, b) => a + b) / renderDt.length
 			console.log(`FPS: ${Math.round(1000/rollingAvg)}`)
+
+			// Report what the last frame asked the GPU for
+			const stats = webApp.frame_stats()
+			console.log(`Draw calls: ${stats.draw_calls}, triangles: ${stats.triangles}, CPU: ${stats.total_time.toFixed(2)}ms`)
+			console.log(`Saved binds: ${stats.saved_binds}, uniform sets: ${stats.saved_uniform_sets}, state changes: ${stats.saved_state_changes}`)
+			stats.free()
 		}
 	}
 